@@ -1,4 +1,5 @@
 use clap::{Parser, Subcommand};
+use std::path::PathBuf;
 
 #[derive(Parser)]
 #[command(about = "A tool for choosing a random book from a weighted list")]
@@ -13,24 +14,128 @@ pub struct Cli {
     /// Use a list other than the default
     #[arg(short, long)]
     pub list: Option<String>,
+
+    /// Skip rows of the list file that cannot be read, instead of failing. The list is then read-only
+    #[arg(long)]
+    pub lenient: bool,
+
+    /// Directory of config, lists and backups to use. Defaults to $WYCH_BOOK_HOME, or wych_book in $XDG_CONFIG_HOME or ~/.config
+    #[arg(long, value_name = "DIR")]
+    pub config_dir: Option<PathBuf>,
 }
 
 #[derive(Subcommand, PartialEq, Eq)]
 pub enum Commands {
+    /// Bundle all lists, configuration, history and backups into one file, or bring them back
+    Archive {
+        #[command(subcommand)]
+        command: ArchiveCommand,
+    },
+
     /// Add, delete, or modify books in your list
     Book {
         #[command(subcommand)]
         command: BookCommand,
     },
 
+    /// View and restore backups of your lists and configuration
+    Backup {
+        #[command(subcommand)]
+        command: BackupCommand,
+    },
+
     /// Edit your configuration
     Config {
         #[command(subcommand)]
         command: ConfigCommand,
     },
 
+    /// Export the list to a file, or print it
+    Export {
+        /// Format to export as: json, toml, org, todotxt, md, html, bibtex, ris. Defaults to the output file's extension, or json
+        #[arg(short, long, value_name = "FORMAT")]
+        format: Option<String>,
+
+        /// File to write to. Prints the list if not given
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+
+    /// Import books from a file, showing them before adding any that aren't already in the list
+    Import {
+        /// File to import, or - to read stdin, such as a list pasted from an email
+        #[arg(value_name = "FILE")]
+        file: PathBuf,
+
+        /// Format of the file: json, toml, org, todotxt, goodreads, storygraph, librarything, kindle, calibre, text. Defaults to the file's extension, kindle for My Clippings.txt, or text for stdin
+        #[arg(short, long, value_name = "FORMAT")]
+        format: Option<String>,
+
+        /// TOML file naming the column that holds each field, for importing any CSV or TSV file
+        #[arg(short, long, value_name = "MAPPING", conflicts_with = "format")]
+        mapping: Option<PathBuf>,
+
+        /// Pattern for lines of text, such as "{title} by {author}" or "{last}, {first} - {title}". Can be repeated, and is tried in order
+        #[arg(short, long, value_name = "PATTERN", conflicts_with = "mapping")]
+        pattern: Vec<String>,
+
+        /// Keep highlights and notes from a Kindle's My Clippings.txt in each book's notes column, including books already in the list
+        #[arg(long)]
+        highlights: bool,
+
+        /// Replace the whole list with the imported books
+        #[arg(short, long)]
+        replace: bool,
+
+        /// Import a shelf into a list, as SHELF=LIST. Can be repeated. Defaults to to-read=<current list>
+        #[arg(short, long, value_name = "SHELF=LIST")]
+        shelf: Vec<String>,
+
+        /// Initial weight of the books on a shelf, as SHELF=WEIGHT. Can be repeated
+        #[arg(short, long, value_name = "SHELF=WEIGHT")]
+        weight: Vec<String>,
+
+        /// Use a book's rating as its initial weight, if it has been rated
+        #[arg(long)]
+        rating_weights: bool,
+
+        /// Import without asking for confirmation
+        #[arg(short = 'y', long = "yes")]
+        auto_confirm: bool,
+    },
+
+    /// Add the books from a friend's share code to the list, showing any that aren't already in it
+    ImportShare {
+        /// Code made by `share`, or - to read it from stdin
+        #[arg(value_name = "CODE")]
+        code: String,
+
+        /// Import without asking for confirmation
+        #[arg(short = 'y', long = "yes")]
+        auto_confirm: bool,
+    },
+
     /// List table of books
-    List,
+    List {
+        /// User-defined columns to display, separated by commas
+        #[arg(short, long, value_name = "COLUMNS", value_delimiter = ',')]
+        columns: Vec<String>,
+
+        /// Only show books where COLUMN contains VALUE (case-insensitive). Can be repeated
+        #[arg(short, long, value_name = "COLUMN=VALUE")]
+        filter: Vec<String>,
+    },
+
+    /// Write a static website of all lists, finished books and an Atom feed of picks
+    Publish {
+        /// Directory to write the website to
+        #[arg(value_name = "OUT_DIR")]
+        out_dir: PathBuf,
+
+        /// URL the website will be hosted at, used for links in the feed
+        #[arg(long, value_name = "URL")]
+        base_url: Option<String>,
+    },
 
     /// Reset weight of all books to 1
     Reset {
@@ -39,6 +144,28 @@ pub enum Commands {
         auto_confirm: bool,
     },
 
+    /// Find EPUB and PDF files in a directory, showing them before adding any that aren't in the list
+    Scan {
+        /// Directory to scan, including its subdirectories
+        #[arg(value_name = "DIR")]
+        dir: PathBuf,
+
+        /// Add the books without asking for confirmation
+        #[arg(short = 'y', long = "yes")]
+        auto_confirm: bool,
+    },
+
+    /// Print a short code holding a list, which can be pasted into a chat and read with `import-share`
+    Share {
+        /// List to share. Defaults to the current list
+        #[arg(value_name = "LIST")]
+        list: Option<String>,
+
+        /// Also draw the code as a QR code
+        #[arg(long)]
+        qr: bool,
+    },
+
     /// Sort book list
     Sort {
         /// Column to order by
@@ -46,23 +173,69 @@ pub enum Commands {
         input: String,
     },
 
+    /// Undo the last change to a list
+    Undo,
+
+    /// Redo the last undone change to a list
+    Redo,
+
     /// Select a random book based on weight values
-    Wych,
+    Wych {
+        /// Set the book's status to picked, so it shows up in Org-mode and todo.txt exports
+        #[arg(short, long)]
+        mark: bool,
+    },
+}
+
+#[derive(Subcommand, PartialEq, Eq)]
+pub enum ArchiveCommand {
+    /// Write everything to a compressed archive
+    Export {
+        /// Archive to write, such as wych.zip
+        #[arg(value_name = "FILE")]
+        file: PathBuf,
+
+        /// Write encrypted lists to the archive unencrypted without asking for confirmation
+        #[arg(short = 'y', long = "yes")]
+        auto_confirm: bool,
+    },
+
+    /// Check an archive, then add its books and lists to yours
+    Import {
+        /// Archive to read
+        #[arg(value_name = "FILE")]
+        file: PathBuf,
+
+        /// Replace all lists, configuration and history with the archive's, deleting lists that aren't in it
+        #[arg(short, long)]
+        replace: bool,
+
+        /// Import without asking for confirmation
+        #[arg(short = 'y', long = "yes")]
+        auto_confirm: bool,
+    },
 }
 
 #[derive(Subcommand, PartialEq, Eq)]
 pub enum BookCommand {
     /// Add book to list
     Add {
-        /// Author of book
-        #[arg(short, long, value_name = "AUTHOR")]
-        author: String,
+        /// Author of book. Looked up from the ISBN if not given
+        #[arg(short, long, value_name = "AUTHOR", required_unless_present = "isbn")]
+        author: Option<String>,
+
+        /// Title of book. Looked up from the ISBN if not given
+        #[arg(short, long, value_name = "TITLE", required_unless_present = "isbn")]
+        book: Option<String>,
 
-        /// Title of book
-        #[arg(short, long, value_name = "TITLE")]
-        book: String,
+        /// ISBN-10 or ISBN-13 of book
+        #[arg(short, long, value_name = "ISBN")]
+        isbn: Option<String>,
     },
 
+    /// Add books by ISBN, read one per line from stdin, such as from a barcode scanner
+    Isbns,
+
     /// Delete book from list
     Delete {
         /// Title or index of book
@@ -74,6 +247,20 @@ pub enum BookCommand {
         auto_confirm: bool,
     },
 
+    /// Remove a book from the list and record it as finished
+    Finish {
+        /// Title or index of book
+        #[arg(short = 'b', long = "book", value_name = "TITLE | INDEX")]
+        input: String,
+    },
+
+    /// Fill in the author, pages, year and subjects of books from Open Library
+    Enrich {
+        /// Title or index of book. Enriches every book if not given
+        #[arg(short = 'b', long = "book", value_name = "TITLE | INDEX")]
+        input: Option<String>,
+    },
+
     /// Modify the weight of a books
     Weight {
         /// Title or index of book
@@ -85,6 +272,37 @@ pub enum BookCommand {
     },
 }
 
+#[derive(Subcommand, PartialEq, Eq)]
+pub enum BackupCommand {
+    /// List all snapshots
+    List,
+
+    /// Replace a list with one of its snapshots
+    Restore {
+        /// List to restore
+        #[arg(value_name = "LIST")]
+        list: String,
+
+        /// Snapshot to restore
+        #[arg(value_name = "SNAPSHOT | INDEX")]
+        snapshot: String,
+    },
+
+    /// Replace your configuration with one of its snapshots
+    RestoreConfig {
+        /// Snapshot to restore
+        #[arg(value_name = "SNAPSHOT | INDEX")]
+        snapshot: String,
+    },
+
+    /// Set the number of snapshots kept for each list. Zero turns off backups
+    Retention {
+        /// Number of snapshots to keep
+        #[arg(value_name = "COUNT")]
+        count: usize,
+    },
+}
+
 #[derive(Subcommand, PartialEq, Eq)]
 pub enum ConfigCommand {
     /// Copy a list to a new list
@@ -102,6 +320,13 @@ pub enum ConfigCommand {
         overwrite: bool,
     },
 
+    /// Set the Open Library server used by `book enrich`
+    OpenLibrary {
+        /// Base URL of the server. Goes back to openlibrary.org if not given
+        #[arg(value_name = "URL")]
+        url: Option<String>,
+    },
+
     /// Set a new default list
     Default {
         /// List to set as default
@@ -109,6 +334,13 @@ pub enum ConfigCommand {
         list: String,
     },
 
+    /// Stop encrypting a list
+    Decrypt {
+        /// List to decrypt
+        #[arg(short, long, value_name = "NAME | INDEX")]
+        list: String,
+    },
+
     /// Delete a book list
     Delete {
         /// List to delete
@@ -116,13 +348,39 @@ pub enum ConfigCommand {
         list: String,
     },
 
+    /// Encrypt a list, its undo history and its backups with a passphrase or key file
+    Encrypt {
+        /// List to encrypt
+        #[arg(short, long, value_name = "NAME | INDEX")]
+        list: String,
+
+        /// Unlock the list with this file instead of a passphrase
+        #[arg(short, long, value_name = "FILE")]
+        key_file: Option<PathBuf>,
+    },
+
     /// List names of all book lists
     List,
 
+    /// Move all lists into a SQLite database, and use it from now on
+    #[cfg(feature = "sqlite")]
+    Migrate,
+
     /// Create a new list
     New {
         /// List to create
         #[arg(short, long, value_name = "LIST")]
         list: String,
     },
+
+    /// Rename a list
+    Rename {
+        /// List to rename
+        #[arg(short, long, value_name = "NAME | INDEX")]
+        list: String,
+
+        /// New name for the list
+        #[arg(short, long, value_name = "NEW NAME")]
+        to: String,
+    },
 }
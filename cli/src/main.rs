@@ -1,13 +1,46 @@
 use clap::Parser;
-use cli::{BookCommand, Cli, Commands, ConfigCommand};
-use std::{error::Error, process};
+use cli::{ArchiveCommand, BackupCommand, BookCommand, Cli, Commands, ConfigCommand};
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    io::{BufRead, IsTerminal, Read, Write},
+    path::Path,
+    process,
+};
 use wych_book::{
-    books::book::{Book, Header},
-    io::config,
+    books::{
+        book::{field, status, Book, Header},
+        book_records::BookRecords,
+        history::Event,
+        isbn::Isbn,
+        journal::{Change, Journal},
+    },
+    io::{
+        archive::{self, Manifest, Workspace},
+        backup::Snapshots,
+        config::{self, WychConfig},
+        csv::{self, RowWarning},
+        encryption::{self, Secret},
+        export::{self, Format},
+        files::write_atomic,
+        history::{read_history, write_history},
+        import::{self, ImportOptions, Importer},
+        kindle::{self, Kindle},
+        metadata::{self, enrich_book, MetadataProvider},
+        openlibrary::OpenLibrary,
+        paths::WychPaths,
+        plain_text::PlainText,
+        publish, scan, share,
+        store::ListStore,
+    },
+    search::IndexSearch,
 };
 
 mod cli;
 
+/// File name that stands for stdin.
+const STDIN: &str = "-";
+
 fn main() {
     let cli = Cli::parse();
 
@@ -20,65 +53,477 @@ fn main() {
 fn run(cli_args: Cli) -> Result<(), Box<dyn Error>> {
     println!();
 
-    let mut config = config::get_config()?;
+    let paths = WychPaths::resolve(cli_args.config_dir.as_deref())?;
+    let _lock = config::lock_lists(&paths)?;
+    let mut config = config::get_config(&paths)?;
+    let mut store = config::list_store(&paths, &config)?;
 
     if config.get_default().is_empty() {
         let default = prompt_for_default_list()?;
-        config.add_new_empty_list(&default)?;
+        config.add_new_empty_list(&mut *store, &default)?;
     }
 
-    // list_name used for printing; filename for actual list manipulation
-    let (list_name, filename) = if let Some(list) = cli_args.list {
-        (list.clone(), config::csv_file(&list))
+    let list_name = if let Some(list) = cli_args.list {
+        list
     } else {
-        (config.get_default().to_string(), config.default_csv())
+        config.get_default().to_string()
     };
 
-    let mut books = wych_book::io::csv::read_csv_file(&filename)?;
+    // only loaded when needed, so that an encrypted list isn't unlocked for nothing
+    let mut skipped: Vec<RowWarning> = Vec::new();
+    let mut books = BookRecords::default();
+    let mut journal = Journal::default();
+    if reads_list(&cli_args.command) {
+        books = if cli_args.lenient {
+            let (books, warnings) = store.load_lenient(&list_name)?;
+            for warning in &warnings {
+                eprintln!("Skipped row: {warning}");
+            }
+            skipped = warnings;
+            books
+        } else {
+            store.load(&list_name)?
+        };
+        journal = store.load_journal(&list_name)?;
+    }
+    if !skipped.is_empty() && changes_list(&cli_args.command) {
+        return Err(format!(
+            "Cannot change {list_name} while rows are skipped, fix {} first",
+            csv::skipped_lines(&skipped)
+        )
+        .into());
+    }
     let mut print_list = !cli_args.quiet; // if quiet, don't print list
+    let mut save_list = skipped.is_empty(); // skipped rows would be lost by saving
+    let mut columns = Vec::new();
+    let mut filters = Vec::new();
+
+    backup_before(&cli_args.command, &paths, &config, &*store, &list_name)?;
 
     match cli_args.command {
+        Commands::Archive { command } => {
+            print_list = false;
+            save_list = false;
+            match command {
+                ArchiveCommand::Export { file, auto_confirm } => {
+                    let encrypted: Vec<&str> = config
+                        .get_collection()
+                        .iter()
+                        .filter(|l| config.is_encrypted(l))
+                        .map(String::as_str)
+                        .collect();
+                    if should_archive_encrypted(&encrypted, auto_confirm)? {
+                        let manifest =
+                            archive::write_archive(&file, &config.workspace(&paths, &*store)?)?;
+                        println!(
+                            "Archived {} lists and {} backups to {}",
+                            manifest.lists.len(),
+                            manifest.backups.len(),
+                            file.display()
+                        );
+                    }
+                }
+                ArchiveCommand::Import {
+                    file,
+                    replace,
+                    auto_confirm,
+                } => {
+                    let (manifest, workspace) = archive::read_archive(&file)?;
+                    preview_archive(&manifest, &workspace, &*store, replace)?;
+
+                    let question = match replace {
+                        true => "Replace everything with the archive?",
+                        false => "Merge the archive into your lists?",
+                    };
+                    if should_import(question, auto_confirm, false)? {
+                        let changed =
+                            config.import_workspace(&paths, &mut *store, workspace, replace)?;
+                        println!("Imported {} lists from {}", changed.len(), file.display());
+                    }
+                }
+            }
+        }
+        Commands::Backup { command } => {
+            print_list = false;
+            save_list = false;
+            match command {
+                BackupCommand::List => print_snapshots(&paths)?,
+                BackupCommand::Restore { list, snapshot } => {
+                    let restored = config.restore_list(&paths, &mut *store, &list, &snapshot)?;
+                    println!("Restored {list} from {restored}");
+                }
+                BackupCommand::RestoreConfig { snapshot } => {
+                    let restored = config.restore_config(&paths, &snapshot)?;
+                    println!("Restored config from {restored}");
+                }
+                BackupCommand::Retention { count } => {
+                    config.set_backup_retention(count);
+                    println!("Keeping {count} snapshots of each list");
+                }
+            }
+        }
         Commands::Book { command } => match command {
-            BookCommand::Add { author, book } => books.add_book(&author, &book),
+            BookCommand::Add { author, book, isbn } => {
+                let new_book = match isbn {
+                    Some(isbn) => {
+                        let isbn =
+                            Isbn::parse(&isbn).map_err(|e| format!("Invalid ISBN {isbn}, {e}"))?;
+                        let provider = OpenLibrary::new(config.open_library_url());
+                        book_with_isbn(&provider, &isbn, author, book)?
+                    }
+                    None => Book::new(author.unwrap_or_default(), book.unwrap_or_default(), 1),
+                };
+
+                match new_book.isbn() {
+                    Some(isbn) if books.contains_isbn(&isbn) => {
+                        eprintln!("A book with ISBN {isbn} is already in the list")
+                    }
+                    _ => journal.record(books.add(new_book)),
+                }
+            }
+            BookCommand::Isbns => {
+                let provider = OpenLibrary::new(config.open_library_url());
+                let stdin = std::io::stdin();
+                if stdin.is_terminal() {
+                    println!("Scan or type ISBNs, one per line. Press Ctrl-D when done\n");
+                }
+                add_isbns(&mut books, &mut journal, &provider, stdin.lock())?;
+            }
             BookCommand::Delete {
                 input,
                 auto_confirm,
             } => {
                 let book = books.get_book(&input);
                 if should_delete(book, auto_confirm)? {
-                    books.remove_book(&input);
+                    if let Some(change) = books.remove_book(&input) {
+                        journal.record(change);
+                    }
+                }
+            }
+            BookCommand::Finish { input } => {
+                let change = books
+                    .finish_book(&input)
+                    .ok_or_else(|| format!("No book matching {input}"))?;
+                if let Change::Finish { book, .. } = &change {
+                    record_history(&paths, &config, Event::Finished, &list_name, book)?;
+                    println!("Finished: {} by {}\n", book.title, book.author);
+                }
+                journal.record(change);
+            }
+            BookCommand::Enrich { input } => {
+                let indexes = match input {
+                    Some(input) => match books.get_from_input(&input) {
+                        Some((i, _)) => vec![i],
+                        None => return Err(format!("No book matching {input}").into()),
+                    },
+                    None => (0..books.records().len()).collect(),
+                };
+                let provider = OpenLibrary::new(config.open_library_url());
+                enrich_books(&mut books, &mut journal, &provider, &indexes);
+            }
+            BookCommand::Weight { input, weight } => {
+                if let Some(change) = books.change_weight(&input, weight) {
+                    journal.record(change);
                 }
             }
-            BookCommand::Weight { input, weight } => books.change_weight(&input, weight),
         },
         Commands::Config { command } => {
             print_list = false;
+            save_list = false;
             match command {
                 ConfigCommand::Copy {
                     from,
                     to,
                     overwrite,
-                } => config.copy_csv_list(&from, &to, overwrite)?,
-                ConfigCommand::Delete { list } => config.delete_list(&list)?,
-                ConfigCommand::Default { list } => config.set_default(&list)?,
+                } => config.copy_list(&mut *store, &from, &to, overwrite)?,
+                ConfigCommand::Decrypt { list } => {
+                    let list = config.decrypt_list(&paths, &mut *store, &list)?;
+                    println!("Decrypted {list}\n");
+                }
+                ConfigCommand::Delete { list } => config.delete_list(&paths, &mut *store, &list)?,
+                ConfigCommand::Encrypt { list, key_file } => {
+                    let secret = match key_file {
+                        Some(key_file) => Secret::KeyFile(key_file),
+                        None => Secret::Passphrase(new_passphrase(&list)?),
+                    };
+                    let list = config.encrypt_list(&paths, &mut *store, &list, &secret)?;
+                    println!("Encrypted {list}\n");
+                }
+                ConfigCommand::Default { list } => config.set_default(&*store, &list)?,
                 ConfigCommand::List => (),
-                ConfigCommand::New { list } => config.add_new_empty_list(&list)?,
+                ConfigCommand::OpenLibrary { url } => {
+                    config.set_open_library_url(url);
+                    println!("Looking up books at {}\n", config.open_library_url());
+                }
+                ConfigCommand::New { list } => config.add_new_empty_list(&mut *store, &list)?,
+                ConfigCommand::Rename { list, to } => {
+                    config.rename_list(&paths, &mut *store, &list, &to)?
+                }
+                #[cfg(feature = "sqlite")]
+                ConfigCommand::Migrate => {
+                    for list in config::migrate_to_sqlite(&paths, &mut config)? {
+                        println!("Migrated {list}");
+                    }
+                    println!(
+                        "Lists are now stored in {}\n",
+                        paths.database_file().display()
+                    );
+                }
             }
             config.print_lists();
         }
-        Commands::List => print_list = true,
+        Commands::Export { format, output } => {
+            print_list = false;
+            save_list = false;
+            let format = export_format(format.as_deref(), output.as_deref())?;
+            let exported = export::export_books(&books, &list_name, format)?;
+
+            match output {
+                Some(output) => {
+                    write_atomic(&output, |f| Ok(f.write_all(exported.as_bytes())?))?;
+                    println!("Exported {list_name} to {}", output.display());
+                }
+                None => println!("{exported}"),
+            }
+        }
+        Commands::Import {
+            file,
+            format,
+            mapping,
+            pattern,
+            highlights,
+            replace,
+            shelf,
+            weight,
+            rating_weights,
+            auto_confirm,
+        } => {
+            let from_stdin = file == Path::new(STDIN);
+            let is_text = |f: &str| f.eq_ignore_ascii_case("text");
+            let is_kindle = match (&mapping, &format) {
+                (None, Some(format)) => format.eq_ignore_ascii_case("kindle"),
+                (None, None) => Kindle::is_clippings_file(&file),
+                (Some(_), _) => false,
+            };
+            if highlights && !is_kindle {
+                return Err("--highlights can only be used with the kindle format".into());
+            }
+
+            let (importer, imported): (Box<dyn Importer>, BookRecords) = match (mapping, format) {
+                (Some(mapping), _) => read_import(import::mapping_importer(&mapping)?, &file)?,
+                _ if is_kindle => read_import(Box::new(Kindle { highlights }), &file)?,
+                (None, Some(format)) if !is_text(&format) => {
+                    if !pattern.is_empty() {
+                        return Err("--pattern can only be used with the text format".into());
+                    }
+                    read_import(import::importer(&format)?, &file)?
+                }
+                (None, None) if pattern.is_empty() && !from_stdin => {
+                    read_import(Box::new(Format::from_path(&file)?), &file)?
+                }
+                _ => {
+                    let text = PlainText::new(&pattern)?;
+                    let (books, unmatched) = text.parse(&String::from_utf8(read_input(&file)?)?);
+                    for (line, contents) in unmatched {
+                        eprintln!("Skipped line {line}, it doesn't match any pattern: {contents}");
+                    }
+                    (Box::new(text), books)
+                }
+            };
+
+            let highlighted = match highlights && !replace {
+                true => books_with_new_highlights(&books, &imported),
+                false => Vec::new(),
+            };
+            let updated = match importer.updates_books() && !replace {
+                true => import::updated_books(&books, &imported),
+                false => Vec::new(),
+            };
+
+            let options = ImportOptions {
+                shelves: parse_pairs(&shelf, "SHELF=LIST")?.into_iter().collect(),
+                shelf_weights: parse_shelf_weights(&weight)?,
+                rating_weights,
+            };
+            let lists = import::sort_into_lists(&imported, &*importer, &list_name, &options);
+
+            let count = preview_import(&lists, &books, &list_name, &*store, replace)?;
+            if !highlighted.is_empty() {
+                println!(
+                    "Add new highlights to {} books already in {list_name}\n",
+                    highlighted.len()
+                );
+            }
+
+            if !updated.is_empty() {
+                let changed: Vec<Book> = updated.iter().map(|(_, b)| b.clone()).collect();
+                println!("Update {} books already in {list_name}:", changed.len());
+                println!("{}\n", BookRecords::from(changed));
+            }
+
+            let question = match count {
+                0 if highlighted.is_empty() => "Update the books?".to_string(),
+                0 => "Add the new highlights?".to_string(),
+                _ => format!("Add {count} books?"),
+            };
+            if count == 0 && highlighted.is_empty() && updated.is_empty() {
+                println!("No new books to import\n");
+            } else if should_import(&question, auto_confirm, from_stdin)? {
+                for (index, book) in highlighted.into_iter().chain(updated) {
+                    if let Some(change) = books.edit_book(index, book) {
+                        journal.record(change);
+                    }
+                }
+                for (list, imported) in lists {
+                    if list == list_name {
+                        import_books(&mut books, &mut journal, imported, replace);
+                    } else {
+                        merge_into_list(&paths, &mut config, &mut *store, &list, imported)?;
+                    }
+                }
+            }
+        }
+        Commands::ImportShare { code, auto_confirm } => {
+            let from_stdin = code == STDIN;
+            let code = match from_stdin {
+                true => String::from_utf8(read_input(Path::new(STDIN))?)?,
+                false => code,
+            };
+            let (shared_name, imported) = share::read_share_code(&code)?;
+            println!("Shared list: {shared_name}\n");
+
+            let lists = BTreeMap::from([(list_name.clone(), imported.clone())]);
+            let count = preview_import(&lists, &books, &list_name, &*store, false)?;
+            if count == 0 {
+                println!("No new books to import\n");
+            } else if should_import(&format!("Add {count} books?"), auto_confirm, from_stdin)? {
+                import_books(&mut books, &mut journal, imported, false);
+            }
+        }
+        Commands::List { columns: c, filter } => {
+            print_list = true;
+            columns = c;
+            filters = parse_pairs(&filter, "COLUMN=VALUE")?;
+
+            let unknown = columns.iter().chain(filters.iter().map(|(c, _)| c));
+            if let Some(column) = unknown.into_iter().find(|c| !books.has_column(c)) {
+                return Err(format!("Unknown column: {column}").into());
+            }
+        }
+        Commands::Publish { out_dir, base_url } => {
+            print_list = false;
+            save_list = false;
+            let mut lists = Vec::new();
+            for name in store.list()? {
+                // the site isn't encrypted
+                if config.is_encrypted(&name) {
+                    eprintln!("Skipped {name}, as it is encrypted");
+                    continue;
+                }
+                let list = store.load(&name)?;
+                lists.push((name, list));
+            }
+            let history = read_history(&paths.history_file())?;
+            let written = publish::publish(&out_dir, &lists, &history, base_url.as_deref())?;
+            println!("Wrote {} files to {}\n", written.len(), out_dir.display());
+        }
         Commands::Reset { auto_confirm } => {
             if should_reset_weights(auto_confirm)? {
-                books.reset_weights();
+                journal.record(books.reset_weights());
             }
         }
+        Commands::Scan { dir, auto_confirm } => {
+            let scan = scan::scan_directory(&dir, &books)?;
+            for warning in scan.warnings {
+                eprintln!("Unreadable metadata: {warning}");
+            }
+
+            let lists = BTreeMap::from([(list_name.clone(), scan.books)]);
+            let count = preview_import(&lists, &books, &list_name, &*store, false)?;
+            if !scan.found.is_empty() {
+                let found: Vec<Book> = scan.found.iter().map(|(_, b)| b.clone()).collect();
+                println!(
+                    "Add the file of {} books already in {list_name}:",
+                    found.len()
+                );
+                println!("{}\n", BookRecords::from(found));
+            }
+
+            let question = match count {
+                0 => "Add the files?".to_string(),
+                _ => format!("Add {count} books?"),
+            };
+            if count == 0 && scan.found.is_empty() {
+                println!("No new books found\n");
+            } else if should_import(&question, auto_confirm, false)? {
+                for (index, book) in scan.found {
+                    if let Some(change) = books.edit_book(index, book) {
+                        journal.record(change);
+                    }
+                }
+                for (_, scanned) in lists {
+                    import_books(&mut books, &mut journal, scanned, false);
+                }
+            }
+        }
+        Commands::Share { list, qr } => {
+            print_list = false;
+            save_list = false;
+            let (name, shared) = match list {
+                Some(list) if list != list_name => {
+                    let shared = store.load(&list)?;
+                    (list, shared)
+                }
+                _ => (list_name.clone(), books.clone()),
+            };
+
+            let code = share::share_code(&name, &shared)?;
+            if qr {
+                println!("{}\n", share::qr_code(&code)?);
+            }
+            println!("{code}\n");
+        }
         Commands::Sort { input } => {
             let header = Header::from(&input)?;
-            books.sort_by(header);
+            journal.record(books.sort_by(header));
         }
-        Commands::Wych => {
-            if let Some(book) = books.select_random_book() {
-                println!("You should read: {} by {}\n", book.title, book.author);
+        Commands::Undo => match journal.undo(&mut books)? {
+            Some(change) => {
+                if let Change::Finish { book, .. } = &change {
+                    forget_history(&paths, &config, Event::Finished, &list_name, book)?;
+                }
+                println!("Undid: {change}");
+            }
+            None => eprintln!("Nothing to undo"),
+        },
+        Commands::Redo => match journal.redo(&mut books)? {
+            Some(change) => {
+                if let Change::Finish { book, .. } = &change {
+                    record_history(&paths, &config, Event::Finished, &list_name, book)?;
+                }
+                println!("Redid: {change}");
+            }
+            None => eprintln!("Nothing to redo"),
+        },
+        Commands::Wych { mark } => {
+            if let Some(book) = books.select_random_book().cloned() {
+                println!("You should read: {} by {}", book.title, book.author);
+                record_history(&paths, &config, Event::Picked, &list_name, &book)?;
+                if let Some(file) = book.field(field::FILE) {
+                    println!("It's at: {file}");
+                }
+                println!();
+
+                if mark {
+                    let index = books.records().iter().position(|b| *b == book);
+                    let mut picked = book.clone();
+                    picked
+                        .custom
+                        .insert(field::STATUS.into(), status::PICKED.into());
+                    if let Some(change) = index.and_then(|i| books.edit_book(i, picked)) {
+                        journal.record(change);
+                    }
+                }
             } else {
                 eprintln!("Could not select a book\n");
             }
@@ -88,11 +533,426 @@ fn run(cli_args: Cli) -> Result<(), Box<dyn Error>> {
     if print_list {
         let list_print = format!("| List Name: {list_name} |");
         let print_cap = str::repeat("-", list_print.len());
-        println!("{print_cap}\n{list_print}\n{books}\n");
+        let table = books.table_string(&books.filter_indexes(&filters), &columns);
+        println!("{print_cap}\n{list_print}\n{table}\n");
+    }
+
+    if save_list {
+        store.save(&list_name, &books)?;
+        store.save_journal(&list_name, &journal)?;
+    }
+    config::save_config(&paths, &mut config, &*store)
+}
+
+/// Whether a command reads the current list. The others leave it alone, and don't save it.
+fn reads_list(command: &Commands) -> bool {
+    !matches!(
+        command,
+        Commands::Archive { .. }
+            | Commands::Backup { .. }
+            | Commands::Config { .. }
+            | Commands::Publish { .. }
+    )
+}
+
+/// Whether a command changes the current list.
+fn changes_list(command: &Commands) -> bool {
+    matches!(
+        command,
+        Commands::Book { .. }
+            | Commands::Import { .. }
+            | Commands::ImportShare { .. }
+            | Commands::Reset { .. }
+            | Commands::Scan { .. }
+            | Commands::Sort { .. }
+            | Commands::Undo
+            | Commands::Redo
+            | Commands::Wych { mark: true }
+    )
+}
+
+/// Snapshot the lists and config file that a command is about to change.
+fn backup_before(
+    command: &Commands,
+    paths: &WychPaths,
+    config: &WychConfig,
+    store: &dyn ListStore,
+    list_name: &str,
+) -> Result<(), Box<dyn Error>> {
+    if changes_list(command) {
+        return config.backup_list(paths, store, list_name);
+    }
+
+    match command {
+        Commands::Config { command } => match command {
+            ConfigCommand::Copy { to, .. } => {
+                config.backup_list(paths, store, to)?;
+                config.backup_config(paths)
+            }
+            ConfigCommand::Delete { list } => {
+                if let Some((_, name)) = config.get_from_input(list) {
+                    config.backup_list(paths, store, name)?;
+                }
+                config.backup_config(paths)
+            }
+            ConfigCommand::Default { .. }
+            | ConfigCommand::Decrypt { .. }
+            | ConfigCommand::Encrypt { .. }
+            | ConfigCommand::New { .. }
+            | ConfigCommand::OpenLibrary { .. }
+            | ConfigCommand::Rename { .. } => config.backup_config(paths),
+            #[cfg(feature = "sqlite")]
+            ConfigCommand::Migrate => config.backup_config(paths),
+            ConfigCommand::List => Ok(()),
+        },
+        Commands::Archive { command } => match command {
+            ArchiveCommand::Import { .. } => {
+                for name in store.list()? {
+                    config.backup_list(paths, store, &name)?;
+                }
+                config.backup_config(paths)
+            }
+            ArchiveCommand::Export { .. } => Ok(()),
+        },
+        Commands::Backup {
+            command: BackupCommand::Retention { .. },
+        } => config.backup_config(paths),
+        _ => Ok(()),
+    }
+}
+
+/// Add an event to the reading history shared by all lists.
+/// Encrypted lists are left out, as the history isn't encrypted.
+fn record_history(
+    paths: &WychPaths,
+    config: &WychConfig,
+    event: Event,
+    list: &str,
+    book: &Book,
+) -> Result<(), Box<dyn Error>> {
+    if config.is_encrypted(list) {
+        return Ok(());
+    }
+    let filename = paths.history_file();
+    let mut history = read_history(&filename)?;
+    history.record(event, list, book);
+    write_history(&filename, &history)
+}
+
+/// Remove the latest time an event happened to a book from the reading history, when it is undone.
+fn forget_history(
+    paths: &WychPaths,
+    config: &WychConfig,
+    event: Event,
+    list: &str,
+    book: &Book,
+) -> Result<(), Box<dyn Error>> {
+    if config.is_encrypted(list) {
+        return Ok(());
+    }
+    let filename = paths.history_file();
+    let mut history = read_history(&filename)?;
+    if history.remove_latest(event, list, book) {
+        write_history(&filename, &history)?;
+    }
+    Ok(())
+}
+
+/// Use the given format, or guess it from the output file, or fall back to JSON.
+fn export_format(format: Option<&str>, output: Option<&Path>) -> Result<Format, Box<dyn Error>> {
+    match (format, output) {
+        (Some(format), _) => Format::from(format),
+        (None, Some(output)) => Format::from_path(output),
+        (None, None) => Ok(Format::Json),
+    }
+}
+
+/// Add imported books to the list, or replace the list with them.
+fn import_books(
+    books: &mut BookRecords,
+    journal: &mut Journal,
+    imported: BookRecords,
+    replace: bool,
+) {
+    let count = imported.records().len();
+
+    if replace {
+        *books = imported;
+        // earlier changes no longer apply to the replaced list
+        *journal = Journal::default();
+        println!("Replaced list with {count} books\n");
+    } else {
+        let changes = books.merge(imported);
+        println!("Added {} of {count} books\n", changes.len());
+        changes.into_iter().for_each(|c| journal.record(c));
+    }
+}
+
+/// Books already in the list that imported books have new highlights for,
+/// with their notes updated, and their indexes.
+fn books_with_new_highlights(books: &BookRecords, imported: &BookRecords) -> Vec<(usize, Book)> {
+    imported
+        .records()
+        .iter()
+        .filter_map(|book| {
+            let notes = book.custom.get(field::NOTES)?;
+            let index = books.position(book)?;
+            Some((index, kindle::add_highlights(books.get(index)?, notes)?))
+        })
+        .collect()
+}
+
+/// Add imported books to a list other than the current one, creating it if needed.
+fn merge_into_list(
+    paths: &WychPaths,
+    config: &mut WychConfig,
+    store: &mut dyn ListStore,
+    list: &str,
+    imported: BookRecords,
+) -> Result<(), Box<dyn Error>> {
+    if store.exists(list)? {
+        config.backup_list(paths, store, list)?;
+    } else {
+        config.add_new_empty_list(store, list)?;
+    }
+
+    let mut books = store.load(list)?;
+    let mut journal = store.load_journal(list)?;
+    let count = imported.records().len();
+    let changes = books.merge(imported);
+    println!("Added {} of {count} books to {list}\n", changes.len());
+    changes.into_iter().for_each(|c| journal.record(c));
+
+    store.save(list, &books)?;
+    store.save_journal(list, &journal)
+}
+
+/// Read the file to import, or stdin if the file is `-`.
+fn read_input(file: &Path) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut contents = Vec::new();
+    if file == Path::new(STDIN) {
+        std::io::stdin().read_to_end(&mut contents)?;
+    } else {
+        contents =
+            std::fs::read(file).map_err(|e| format!("Cannot open {}, {e}", file.display()))?;
+    }
+    Ok(contents)
+}
+
+/// Read the books in the file to import, or in stdin if the file is `-`.
+fn read_import(
+    importer: Box<dyn Importer>,
+    file: &Path,
+) -> Result<(Box<dyn Importer>, BookRecords), Box<dyn Error>> {
+    let imported = if file == Path::new(STDIN) {
+        importer.import(&read_input(file)?, file)?
+    } else {
+        importer.import_file(file)?
+    };
+    Ok((importer, imported))
+}
+
+/// Show the books an import would add to each list. Returns how many there are.
+fn preview_import(
+    lists: &BTreeMap<String, BookRecords>,
+    books: &BookRecords,
+    list_name: &str,
+    store: &dyn ListStore,
+    replace: bool,
+) -> Result<usize, Box<dyn Error>> {
+    let mut count = 0;
+    for (list, imported) in lists {
+        if replace && list == list_name {
+            println!("Replace {list} with {} books:", imported.records().len());
+            println!("{imported}\n");
+            count += imported.records().len();
+            continue;
+        }
+
+        let mut target = if list == list_name {
+            books.clone()
+        } else if store.exists(list)? {
+            store.load(list)?
+        } else {
+            BookRecords::default()
+        };
+        let added: Vec<Book> = target
+            .merge(imported.clone())
+            .into_iter()
+            .filter_map(|change| match change {
+                Change::Add { book, .. } => Some(book),
+                _ => None,
+            })
+            .collect();
+
+        if !added.is_empty() {
+            println!(
+                "Add {} of {} books to {list}:",
+                added.len(),
+                imported.records().len()
+            );
+            count += added.len();
+            println!("{}\n", BookRecords::from(added));
+        }
     }
+    Ok(count)
+}
+
+/// A book with an ISBN, looking up its author and title unless both are given.
+fn book_with_isbn(
+    provider: &dyn MetadataProvider,
+    isbn: &Isbn,
+    author: Option<String>,
+    title: Option<String>,
+) -> Result<Book, Box<dyn Error>> {
+    let mut book = match (author.clone(), title.clone()) {
+        (Some(author), Some(title)) => Book::new(author, title, 1),
+        _ => match metadata::book_from_isbn(provider, isbn)? {
+            Some(book) => book,
+            None => {
+                let message =
+                    format!("No book found with ISBN {isbn}, add it with --author and --book");
+                return Err(message.into());
+            }
+        },
+    };
 
-    wych_book::io::csv::write_csv_file(&filename, &books)?;
-    config::save_config(&mut config)
+    book.author = author.unwrap_or(book.author);
+    book.title = title.unwrap_or(book.title);
+    book.custom
+        .insert(field::ISBN.to_string(), isbn.to_string());
+    Ok(book)
+}
+
+/// Show what's in an archive, and which lists replacing would delete.
+fn preview_archive(
+    manifest: &Manifest,
+    workspace: &Workspace,
+    store: &dyn ListStore,
+    replace: bool,
+) -> Result<(), Box<dyn Error>> {
+    println!(
+        "Archive from {}, made by wych_book {}",
+        manifest.created.format("%Y-%m-%d %H:%M"),
+        manifest.wych_book_version
+    );
+    for list in &workspace.lists {
+        println!("- {}: {} books", list.name, list.books.records().len());
+    }
+    println!("{} backups\n", manifest.backups.len());
+
+    if replace {
+        let deleted: Vec<String> = store
+            .list()?
+            .into_iter()
+            .filter(|name| !workspace.lists.iter().any(|l| l.name == *name))
+            .collect();
+        if !deleted.is_empty() {
+            println!("Delete lists not in the archive: {}\n", deleted.join(", "));
+        }
+    }
+    Ok(())
+}
+
+/// Add a book for each ISBN read, one per line, skipping invalid ISBNs and books already in the list.
+/// Stops if the metadata provider can't be reached.
+fn add_isbns(
+    books: &mut BookRecords,
+    journal: &mut Journal,
+    provider: &dyn MetadataProvider,
+    input: impl BufRead,
+) -> Result<(), Box<dyn Error>> {
+    for line in input.lines() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let isbn = match Isbn::parse(line) {
+            Ok(isbn) => isbn,
+            Err(e) => {
+                eprintln!("Skipped {line}, {e}");
+                continue;
+            }
+        };
+        if books.contains_isbn(&isbn) {
+            eprintln!("Skipped {isbn}, it's already in the list");
+            continue;
+        }
+
+        match metadata::book_from_isbn(provider, &isbn) {
+            Ok(Some(book)) => {
+                println!("Added {} by {}", book.title, book.author);
+                journal.record(books.add(book));
+            }
+            Ok(None) => eprintln!("Skipped {isbn}, no book found with that ISBN"),
+            Err(e) => {
+                eprintln!("{e}\nStopped adding books, any already added are kept");
+                break;
+            }
+        }
+    }
+    println!();
+    Ok(())
+}
+
+/// Fill in details of books from a metadata provider, stopping if it can't be reached.
+fn enrich_books(
+    books: &mut BookRecords,
+    journal: &mut Journal,
+    provider: &dyn MetadataProvider,
+    indexes: &[usize],
+) {
+    let mut enriched = 0;
+    for &i in indexes {
+        let book = books.get(i).expect("Should be valid index").clone();
+        match provider.lookup(&book) {
+            Ok(Some(metadata)) => {
+                if let Some(change) = books.edit_book(i, enrich_book(&book, &metadata)) {
+                    println!("Updated {} by {}", book.title, book.author);
+                    journal.record(change);
+                    enriched += 1;
+                }
+            }
+            Ok(None) => eprintln!("No match for {} by {}", book.title, book.author),
+            Err(e) => {
+                eprintln!("{e}\nStopped looking up books, any already updated are kept");
+                break;
+            }
+        }
+    }
+    println!("Updated {enriched} of {} books\n", indexes.len());
+}
+
+fn print_snapshots(paths: &WychPaths) -> Result<(), Box<dyn Error>> {
+    println!("{}", Snapshots::for_config(paths)?);
+    for snapshots in Snapshots::for_all_lists(paths)? {
+        println!("{snapshots}");
+    }
+    Ok(())
+}
+
+/// Split each `KEY=VALUE` argument into its key and value.
+fn parse_pairs(pairs: &[String], expected: &str) -> Result<Vec<(String, String)>, Box<dyn Error>> {
+    pairs
+        .iter()
+        .map(|p| match p.split_once('=') {
+            Some((key, value)) => Ok((key.trim().to_string(), value.trim().to_string())),
+            None => Err(format!("Invalid argument {p}, expected {expected}").into()),
+        })
+        .collect()
+}
+
+/// Parse each `SHELF=WEIGHT` argument.
+fn parse_shelf_weights(weights: &[String]) -> Result<BTreeMap<String, u8>, Box<dyn Error>> {
+    parse_pairs(weights, "SHELF=WEIGHT")?
+        .into_iter()
+        .map(|(shelf, weight)| match weight.parse() {
+            Ok(weight) => Ok((shelf, weight)),
+            Err(_) => Err(format!("Invalid weight {weight} for shelf {shelf}").into()),
+        })
+        .collect()
 }
 
 fn should_delete(book: Option<&Book>, auto_confirm: bool) -> Result<bool, Box<dyn Error>> {
@@ -110,6 +970,41 @@ fn should_delete(book: Option<&Book>, auto_confirm: bool) -> Result<bool, Box<dy
     prompt_for_choice()
 }
 
+/// `from_stdin` means the books were read from stdin, so the answer is read from the terminal.
+fn should_import(
+    question: &str,
+    auto_confirm: bool,
+    from_stdin: bool,
+) -> Result<bool, Box<dyn Error>> {
+    if auto_confirm {
+        return Ok(true);
+    }
+
+    println!("[Y/n] {question}");
+    if !from_stdin {
+        return prompt_for_choice();
+    }
+
+    let terminal = if cfg!(windows) { "CONIN$" } else { "/dev/tty" };
+    match std::fs::File::open(terminal) {
+        Ok(terminal) => read_choice(std::io::BufReader::new(terminal)),
+        Err(_) => Err("Cannot ask for confirmation, pass --yes to import from stdin".into()),
+    }
+}
+
+/// Ask before writing encrypted lists to an archive, where they aren't encrypted.
+fn should_archive_encrypted(lists: &[&str], auto_confirm: bool) -> Result<bool, Box<dyn Error>> {
+    if lists.is_empty() || auto_confirm {
+        return Ok(true);
+    }
+
+    println!(
+        "[Y/n] {} will be written to the archive unencrypted, continue?",
+        lists.join(", ")
+    );
+    prompt_for_choice()
+}
+
 fn should_reset_weights(auto_confirm: bool) -> Result<bool, Box<dyn Error>> {
     if auto_confirm {
         return Ok(true);
@@ -120,8 +1015,12 @@ fn should_reset_weights(auto_confirm: bool) -> Result<bool, Box<dyn Error>> {
 }
 
 fn prompt_for_choice() -> Result<bool, Box<dyn Error>> {
+    read_choice(std::io::stdin().lock())
+}
+
+fn read_choice(mut reader: impl BufRead) -> Result<bool, Box<dyn Error>> {
     let mut input = String::new();
-    std::io::stdin().read_line(&mut input)?;
+    reader.read_line(&mut input)?;
 
     match input.trim() {
         "Y" | "y" => Ok(true),
@@ -130,6 +1029,25 @@ fn prompt_for_choice() -> Result<bool, Box<dyn Error>> {
     }
 }
 
+/// The passphrase to encrypt a list with, from `PASSPHRASE_VAR` or asked for twice.
+fn new_passphrase(list: &str) -> Result<String, Box<dyn Error>> {
+    let passphrase = match std::env::var(encryption::PASSPHRASE_VAR) {
+        Ok(passphrase) => passphrase,
+        Err(_) => {
+            let passphrase = encryption::prompt_passphrase(list)?;
+            if encryption::prompt_passphrase(list)? != passphrase {
+                return Err("Passphrases do not match".into());
+            }
+            passphrase
+        }
+    };
+
+    if passphrase.is_empty() {
+        return Err("Passphrase cannot be empty".into());
+    }
+    Ok(passphrase)
+}
+
 fn prompt_for_default_list() -> Result<String, Box<dyn Error>> {
     println!("Enter name for default book list: ");
     let mut input = String::new();
@@ -1,21 +1,77 @@
-use std::fmt::Display;
+use super::isbn::Isbn;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fmt::Display};
 
-#[derive(Debug, Eq, PartialEq, Clone)]
+#[derive(Debug, Eq, PartialEq, Clone, Serialize, Deserialize)]
 pub struct Book {
     pub author: String,
     pub title: String,
     pub weight: u8,
+    /// User-defined columns, keyed by column name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub custom: BTreeMap<String, String>,
 }
 
+/// Names of user-defined columns that importers and exporters fill in or read from.
+pub mod field {
+    pub const ADDITIONAL_AUTHORS: &str = "additional_authors";
+    pub const FILE: &str = "file";
+    pub const ISBN: &str = "isbn";
+    pub const NOTES: &str = "notes";
+    pub const PAGES: &str = "pages";
+    pub const PUBLISHED: &str = "published";
+    pub const PUBLISHER: &str = "publisher";
+    pub const RATING: &str = "rating";
+    pub const SERIES: &str = "series";
+    pub const SHELF: &str = "shelf";
+    pub const STATUS: &str = "status";
+    pub const SUBJECTS: &str = "subjects";
+    pub const TAGS: &str = "tags";
+    pub const YEAR: &str = "year";
+}
+
+/// Values of the status field that exports and imports understand.
+pub mod status {
+    /// Chosen by `wych --mark`.
+    pub const PICKED: &str = "picked";
+    /// Marked as read, such as DONE in Org-mode.
+    pub const FINISHED: &str = "finished";
+}
+
+/// Max string length of a user-defined column when printing to console.
+const CUSTOM_PRINT_LEN: usize = 20;
+
 impl Book {
     pub fn new(author: String, title: String, weight: u8) -> Self {
         Book {
             author,
             title,
             weight,
+            custom: BTreeMap::new(),
+        }
+    }
+
+    /// Get the value of a column, which may be one of the standard headers or a user-defined column.
+    pub fn field(&self, column: &str) -> Option<String> {
+        match Header::from(column) {
+            Ok(Header::Author) => Some(self.author.clone()),
+            Ok(Header::Title) => Some(self.title.clone()),
+            Ok(Header::Weight) => Some(self.weight.to_string()),
+            _ => self.custom.get(column).cloned(),
         }
     }
 
+    /// Whether the book has the given author and title, ignoring case and surrounding spaces.
+    pub fn is_by(&self, author: &str, title: &str) -> bool {
+        let matches = |a: &str, b: &str| a.trim().to_lowercase() == b.trim().to_lowercase();
+        matches(&self.author, author) && matches(&self.title, title)
+    }
+
+    /// The book's ISBN, if it has a valid one.
+    pub fn isbn(&self) -> Option<Isbn> {
+        self.custom.get(field::ISBN)?.parse().ok()
+    }
+
     pub fn as_string_array(&self) -> [String; 3] {
         [
             self.author.clone(),
@@ -30,56 +86,42 @@ impl Book {
 
     /// Create a string of attributes for displaying to console.
     pub fn print_string(&self, index: usize) -> String {
+        self.print_string_with_columns(index, &[])
+    }
+
+    /// Create a string of attributes for displaying to console, followed by the given user-defined columns.
+    pub fn print_string_with_columns(&self, index: usize, columns: &[String]) -> String {
         let [i_len, a_len, t_len, w_len] = Header::lens();
 
-        // if these are greater than one, we'll need to multiline print
-        let author_div = (self.author.len() as f32 / a_len as f32).ceil() as usize;
-        let title_div = (self.title.len() as f32 / t_len as f32).ceil() as usize;
-        let mut lines = if author_div >= title_div {
-            author_div
-        } else {
-            title_div
-        };
-
-        let mut print_string = String::new();
-
-        let mut author = self.author.clone();
-        let mut title = self.title.clone();
-        let mut weight = self.weight.to_string();
-        let mut index = index.to_string();
-
-        while lines > 0 {
-            let formatted = format!(
-                "|{}|{}|{}|{}|",
-                centre(&index, i_len),
-                centre(&author, a_len),
-                centre(&title, t_len),
-                centre(&weight, w_len)
-            );
-
-            print_string.push_str(&formatted);
-
-            if lines > 1 {
-                print_string.push('\n');
-                weight = "".to_string();
-                index = "".to_string();
-
-                author = if author_div > 1 {
-                    author[a_len..].to_string()
-                } else {
-                    "".to_string()
-                };
-
-                title = if title_div > 1 {
-                    title[t_len..].to_string()
-                } else {
-                    "".to_string()
-                }
-            }
-            lines -= 1;
+        let mut cells = vec![
+            (index.to_string(), i_len),
+            (self.author.clone(), a_len),
+            (self.title.clone(), t_len),
+            (self.weight.to_string(), w_len),
+        ];
+        for column in columns {
+            let value = self.custom.get(column).cloned().unwrap_or_default();
+            cells.push((value, CUSTOM_PRINT_LEN));
         }
 
-        print_string
+        // if any cell is longer than its space, we'll need to multiline print
+        let lines = cells
+            .iter()
+            .map(|(value, len)| (value.chars().count() as f32 / *len as f32).ceil() as usize)
+            .max()
+            .unwrap_or(1)
+            .max(1);
+
+        (0..lines)
+            .map(|line| {
+                let row = cells.iter().fold(String::new(), |acc, (value, len)| {
+                    let part: String = value.chars().skip(line * len).take(*len).collect();
+                    format!("{acc}|{}", centre(&part, *len))
+                });
+                format!("{row}|")
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
     }
 }
 
@@ -94,11 +136,7 @@ impl Display for Book {
 }
 
 fn centre(string: &str, space: usize) -> String {
-    let print_str = if string.len() >= space {
-        &string[..space]
-    } else {
-        string
-    };
+    let print_str: String = string.chars().take(space).collect();
     format!("{:^space$}", print_str)
 }
 
@@ -151,6 +189,15 @@ impl Header {
             Header::Weight => 10,
         }
     }
+
+    /// Length of a table line with the given number of user-defined columns.
+    pub fn line_len(custom_columns: usize) -> usize {
+        80 + custom_columns * (CUSTOM_PRINT_LEN + 1)
+    }
+
+    pub fn custom_print_len() -> usize {
+        CUSTOM_PRINT_LEN
+    }
 }
 
 //
@@ -186,4 +233,27 @@ mod tests {
 |    |        riter        |    debut: A Novel in multiple parts    |          |";
         assert_eq!(result, b.print_string(0));
     }
+
+    #[test]
+    fn test_print_custom_columns() {
+        let mut b = Book::new("A. Writer".into(), "Title1".into(), 5);
+        b.custom
+            .insert("notes".into(), "Borrowed from the library".into());
+
+        let columns = vec!["notes".to_string(), "missing".into()];
+        let result = "| 0  |      A. Writer      |                 Title1                 |    5     |Borrowed from the li|                    |
+|    |                     |                                        |          |       brary        |                    |";
+        assert_eq!(result, b.print_string_with_columns(0, &columns));
+    }
+
+    #[test]
+    fn test_field() {
+        let mut b = Book::new("A. Writer".into(), "Title1".into(), 5);
+        b.custom.insert("notes".into(), "Signed copy".into());
+
+        assert_eq!(b.field("author"), Some("A. Writer".into()));
+        assert_eq!(b.field("weight"), Some("5".into()));
+        assert_eq!(b.field("notes"), Some("Signed copy".into()));
+        assert_eq!(b.field("recommended_by"), None);
+    }
 }
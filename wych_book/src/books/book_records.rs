@@ -1,22 +1,39 @@
-use std::fmt::Display;
+use std::{error::Error, fmt::Display};
 
-use super::book::{Book, Header};
+use super::{
+    book::{Book, Header},
+    isbn::Isbn,
+    journal::Change,
+};
 use crate::search::IndexSearch;
 
 use rand::{prelude::thread_rng, seq::SliceRandom};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Eq, PartialEq, Clone, Default)]
+#[derive(Debug, Eq, PartialEq, Clone, Default, Serialize, Deserialize)]
 pub struct BookRecords {
+    #[serde(rename = "books", default)]
     records: Vec<Book>,
+    /// Names of user-defined columns, in the order they appear in the list file.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    custom_columns: Vec<String>,
+    /// Order of every column in the list file, kept only if it isn't the standard headers
+    /// followed by the user-defined columns.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    column_order: Vec<String>,
 }
 
-const MAX_WEIGHT: u8 = 10;
+pub const MAX_WEIGHT: u8 = 10;
 
 impl BookRecords {
     pub fn get(&self, index: usize) -> Option<&Book> {
         self.records.get(index)
     }
 
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut Book> {
+        self.records.get_mut(index)
+    }
+
     pub fn records(&self) -> &Vec<Book> {
         &self.records
     }
@@ -25,14 +42,109 @@ impl BookRecords {
         self.records.push(book);
     }
 
-    pub fn sort_by(&mut self, header: Header) {
-        let r = &mut self.records;
+    pub fn custom_columns(&self) -> &Vec<String> {
+        &self.custom_columns
+    }
+
+    /// Add a user-defined column. Does nothing if the column already exists.
+    pub fn add_custom_column(&mut self, name: &str) {
+        if !self.has_column(name) {
+            self.custom_columns.push(name.to_string());
+        }
+    }
+
+    /// Every column in the order it's written: the order of the list file it was read from,
+    /// followed by any columns that file didn't have.
+    pub fn columns(&self) -> Vec<String> {
+        let mut columns: Vec<String> = self
+            .column_order
+            .iter()
+            .filter(|c| self.has_column(c))
+            .cloned()
+            .collect();
+        let all = Header::headers()
+            .into_iter()
+            .map(str::to_string)
+            .chain(self.custom_columns.iter().cloned());
+        for column in all {
+            if !columns.contains(&column) {
+                columns.push(column);
+            }
+        }
+        columns
+    }
+
+    /// Keep the order of the columns in the list file, so they're written back in the same order.
+    pub fn set_column_order(&mut self, columns: Vec<String>) {
+        self.column_order.clear();
+        if columns != self.columns() {
+            self.column_order = columns;
+        }
+    }
+
+    /// Check if a column is one of the standard headers or a user-defined column.
+    pub fn has_column(&self, name: &str) -> bool {
+        Header::headers().contains(&name) || self.custom_columns.iter().any(|c| c == name)
+    }
+
+    /// Get the indexes of all books where each (column, value) filter is contained in the book's column value.
+    /// Matching is case-insensitive.
+    pub fn filter_indexes(&self, filters: &[(String, String)]) -> Vec<usize> {
+        self.records
+            .iter()
+            .enumerate()
+            .filter(|(_, book)| {
+                filters.iter().all(|(column, value)| {
+                    book.field(column)
+                        .map(|f| f.to_lowercase().contains(&value.to_lowercase()))
+                        .unwrap_or(false)
+                })
+            })
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Create a table of the books at the given indexes, including the given user-defined columns.
+    pub fn table_string(&self, indexes: &[usize], columns: &[String]) -> String {
+        let line = str::repeat("-", Header::line_len(columns.len()));
+        let [i_len, a_len, t_len, w_len] = Header::lens();
+        let c_len = Header::custom_print_len();
+
+        let custom_header = columns.iter().fold(String::new(), |acc, c| {
+            let name: String = c.chars().take(c_len).collect();
+            format!("{acc}{:^c_len$}|", name)
+        });
+        let header = format!(
+            "|{:^i_len$}|{:^a_len$}|{:^t_len$}|{:^w_len$}|{custom_header}",
+            "", "author", "title", "weight"
+        );
+
+        let books = indexes
+            .iter()
+            .filter_map(|i| {
+                self.get(*i)
+                    .map(|b| b.print_string_with_columns(*i, columns))
+            })
+            .reduce(|acc, b| format!("{}\n{}\n{}", acc, line, b));
+        let books = books.unwrap_or("".into());
+
+        let double_line = str::repeat("=", Header::line_len(columns.len()));
+        let full_header = format!("{line}\n{header}\n{double_line}");
+        format!("{full_header}\n{books}\n{line}")
+    }
+
+    pub fn sort_by(&mut self, header: Header) -> Change {
+        let r = &self.records;
+        let mut order: Vec<usize> = (0..r.len()).collect();
         match header {
-            Header::Author => r.sort_by_key(|b| b.author.clone()),
-            Header::Title => r.sort_by_key(|b| b.title.clone()),
-            Header::Weight => r.sort_by_key(|b| b.weight),
+            Header::Author => order.sort_by_key(|i| r[*i].author.clone()),
+            Header::Title => order.sort_by_key(|i| r[*i].title.clone()),
+            Header::Weight => order.sort_by_key(|i| r[*i].weight),
             _ => (),
         };
+
+        self.records = order.iter().map(|i| self.records[*i].clone()).collect();
+        Change::Sort { order }
     }
 
     /// Selects a random book from the provided list of books, based on the associated weight values.
@@ -57,12 +169,58 @@ impl BookRecords {
             .collect()
     }
 
-    pub fn add_book(&mut self, author: &str, title: &str) {
-        self.push(Book {
-            author: author.to_string(),
-            title: title.to_string(),
-            weight: 1,
-        });
+    pub fn add_book(&mut self, author: &str, title: &str) -> Change {
+        self.add(Book::new(author.to_string(), title.to_string(), 1))
+    }
+
+    /// Add a book to the end of the list, adding columns for any of its fields.
+    pub fn add(&mut self, book: Book) -> Change {
+        book.custom.keys().for_each(|c| self.add_custom_column(c));
+        self.push(book.clone());
+        Change::Add {
+            index: self.records.len() - 1,
+            book,
+        }
+    }
+
+    /// Check if a book with the same author and title is already in the list, ignoring case.
+    pub fn contains_book(&self, author: &str, title: &str) -> bool {
+        self.records.iter().any(|b| b.is_by(author, title))
+    }
+
+    /// Check if a book is already in the list, either with the same author and title
+    /// or with the same ISBN in either its ISBN-10 or ISBN-13 form.
+    pub fn contains(&self, book: &Book) -> bool {
+        self.position(book).is_some()
+    }
+
+    /// Index of the first book in the list that is the same as `book`, as `contains` matches them.
+    pub fn position(&self, book: &Book) -> Option<usize> {
+        let isbn = book.isbn();
+        self.records.iter().position(|b| {
+            b.is_by(&book.author, &book.title) || (isbn.is_some() && b.isbn() == isbn)
+        })
+    }
+
+    pub fn contains_isbn(&self, isbn: &Isbn) -> bool {
+        self.records.iter().any(|b| b.isbn().as_ref() == Some(isbn))
+    }
+
+    /// Add each book from another list that isn't already in this one, along with any new user-defined columns.
+    /// Returns a change for each added book.
+    pub fn merge(&mut self, other: BookRecords) -> Vec<Change> {
+        other
+            .custom_columns
+            .iter()
+            .for_each(|c| self.add_custom_column(c));
+
+        let mut changes = Vec::new();
+        for book in other.records {
+            if !self.contains(&book) {
+                changes.push(self.add(book));
+            }
+        }
+        changes
     }
 
     pub fn get_book(&self, input: &str) -> Option<&Book> {
@@ -76,20 +234,49 @@ impl BookRecords {
     }
 
     /// Can remove book based on index, or title.
-    pub fn remove_book(&mut self, input: &str) {
+    pub fn remove_book(&mut self, input: &str) -> Option<Change> {
         let result = self.get_from_input(input);
 
         if let Some((i, _)) = result {
-            self.records.remove(i);
+            let book = self.records.remove(i);
+            return Some(Change::Delete { index: i, book });
         }
+
+        None
+    }
+
+    /// Remove a book that has been read, based on index or title.
+    pub fn finish_book(&mut self, input: &str) -> Option<Change> {
+        let (i, _) = self.get_from_input(input)?;
+        let book = self.records.remove(i);
+        Some(Change::Finish { index: i, book })
     }
 
     /// Reset weight of all books to 1.
-    pub fn reset_weights(&mut self) {
+    pub fn reset_weights(&mut self) -> Change {
+        let weights = self.records.iter().map(|b| b.weight).collect();
         self.records.iter_mut().for_each(|b| b.change_weight(1));
+        Change::Reset { weights }
+    }
+
+    /// Replace the book at an index with an edited copy, adding columns for any new fields.
+    /// Returns None if there is no book at the index, or the edit changes nothing.
+    pub fn edit_book(&mut self, index: usize, book: Book) -> Option<Change> {
+        let from = self.records.get(index)?.clone();
+        if from == book {
+            return None;
+        }
+
+        book.custom.keys().for_each(|c| self.add_custom_column(c));
+        self.records[index] = book.clone();
+        Some(Change::Edit {
+            index,
+            from,
+            to: book,
+        })
     }
 
-    pub fn change_weight(&mut self, input: &str, new_weight: u8) {
+    pub fn change_weight(&mut self, input: &str, new_weight: u8) -> Option<Change> {
         let new_weight = if new_weight > MAX_WEIGHT {
             MAX_WEIGHT
         } else {
@@ -99,11 +286,102 @@ impl BookRecords {
         let result = self.get_from_input(input);
 
         if let Some((i, _)) = result {
-            self.records
-                .get_mut(i)
-                .expect("Should be valid index")
-                .change_weight(new_weight);
+            let book = self.records.get_mut(i).expect("Should be valid index");
+            let change = Change::Weight {
+                index: i,
+                from: book.weight,
+                to: new_weight,
+            };
+            book.change_weight(new_weight);
+            return Some(change);
         }
+
+        None
+    }
+
+    /// Make a change to the books again, after it has been reverted.
+    /// Fails if the books no longer match the state the change was made from.
+    pub fn apply(&mut self, change: &Change) -> Result<(), Box<dyn Error>> {
+        match change {
+            Change::Add { index, book } => {
+                self.check(*index <= self.records.len())?;
+                self.records.insert(*index, book.clone());
+            }
+            Change::Delete { index, book } | Change::Finish { index, book } => {
+                self.check(self.get(*index) == Some(book))?;
+                self.records.remove(*index);
+            }
+            Change::Weight { index, from, to } => {
+                self.check(self.get(*index).is_some_and(|b| b.weight == *from))?;
+                self.records[*index].change_weight(*to);
+            }
+            Change::Edit { index, from, to } => {
+                self.check(self.get(*index) == Some(from))?;
+                self.records[*index] = to.clone();
+            }
+            Change::Sort { order } => {
+                self.check(self.is_permutation(order))?;
+                self.records = order.iter().map(|i| self.records[*i].clone()).collect();
+            }
+            Change::Reset { weights } => {
+                self.check(weights.len() == self.records.len())?;
+                self.records.iter_mut().for_each(|b| b.change_weight(1));
+            }
+        };
+        Ok(())
+    }
+
+    /// Undo a change to the books.
+    /// Fails if the books no longer match the state the change resulted in.
+    pub fn revert(&mut self, change: &Change) -> Result<(), Box<dyn Error>> {
+        match change {
+            Change::Add { index, book } => {
+                self.check(self.get(*index) == Some(book))?;
+                self.records.remove(*index);
+            }
+            Change::Delete { index, book } | Change::Finish { index, book } => {
+                self.check(*index <= self.records.len())?;
+                self.records.insert(*index, book.clone());
+            }
+            Change::Weight { index, from, to } => {
+                self.check(self.get(*index).is_some_and(|b| b.weight == *to))?;
+                self.records[*index].change_weight(*from);
+            }
+            Change::Edit { index, from, to } => {
+                self.check(self.get(*index) == Some(to))?;
+                self.records[*index] = from.clone();
+            }
+            Change::Sort { order } => {
+                self.check(self.is_permutation(order))?;
+                let mut records = self.records.clone();
+                for (i, previous) in order.iter().enumerate() {
+                    records[*previous] = self.records[i].clone();
+                }
+                self.records = records;
+            }
+            Change::Reset { weights } => {
+                self.check(weights.len() == self.records.len())?;
+                self.records
+                    .iter_mut()
+                    .zip(weights)
+                    .for_each(|(b, w)| b.change_weight(*w));
+            }
+        };
+        Ok(())
+    }
+
+    fn check(&self, matches: bool) -> Result<(), Box<dyn Error>> {
+        if matches {
+            Ok(())
+        } else {
+            Err("The list has been changed since this was recorded".into())
+        }
+    }
+
+    fn is_permutation(&self, order: &[usize]) -> bool {
+        let mut sorted = order.to_vec();
+        sorted.sort();
+        sorted == (0..self.records.len()).collect::<Vec<usize>>()
     }
 }
 
@@ -121,32 +399,29 @@ impl IndexSearch for BookRecords {
 
 impl From<Vec<Book>> for BookRecords {
     fn from(records: Vec<Book>) -> Self {
-        BookRecords { records }
+        let mut book_records = BookRecords {
+            records,
+            custom_columns: Vec::new(),
+            column_order: Vec::new(),
+        };
+
+        let columns: Vec<String> = book_records
+            .records
+            .iter()
+            .flat_map(|b| b.custom.keys().cloned())
+            .collect();
+        columns
+            .iter()
+            .for_each(|c| book_records.add_custom_column(c));
+
+        book_records
     }
 }
 
 impl Display for BookRecords {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let line = str::repeat("-", 80);
-        let [i_len, a_len, t_len, w_len] = Header::lens();
-
-        let header = format!(
-            "|{:^i_len$}|{:^a_len$}|{:^t_len$}|{:^w_len$}|",
-            "", "author", "title", "weight"
-        );
-
-        let books = self
-            .records()
-            .iter()
-            .enumerate()
-            .map(|(i, b)| b.print_string(i))
-            .reduce(|acc, b| format!("{}\n{}\n{}", acc, line, b));
-        let books = books.unwrap_or("".into());
-
-        let double_line = str::repeat("=", 80);
-        let full_header = format!("{line}\n{header}\n{double_line}");
-        let final_string = format!("{full_header}\n{books}\n{line}");
-        write!(f, "{final_string}")
+        let indexes: Vec<usize> = (0..self.records.len()).collect();
+        write!(f, "{}", self.table_string(&indexes, &[]))
     }
 }
 
@@ -156,6 +431,7 @@ impl Display for BookRecords {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::books::book::field;
 
     fn books_to_test(weights: Vec<u8>) -> BookRecords {
         vec![
@@ -260,4 +536,72 @@ mod tests {
         assert_eq!(None, books.get_book("100"));
         assert_eq!(None, books.get_book("Ulysses"));
     }
+
+    #[test]
+    fn test_custom_columns() {
+        let mut book = Book::new("Franz Kafka".into(), "The Castle".into(), 1);
+        book.custom.insert("notes".into(), "Unfinished".into());
+        let mut books: BookRecords = vec![book].into();
+        books.add_custom_column("notes");
+        books.add_custom_column("recommended_by");
+
+        assert_eq!(books.custom_columns(), &vec!["notes", "recommended_by"]);
+        assert!(books.has_column("title"));
+        assert!(books.has_column("recommended_by"));
+        assert!(!books.has_column("publisher"));
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut books: BookRecords = books_to_test(vec![3, 2, 0]);
+        let mut other: BookRecords = vec![
+            Book::new("b. b. author".into(), "a title2 ".into(), 1),
+            Book::new("Franz Kafka".into(), "The Castle".into(), 4),
+        ]
+        .into();
+        other.add_custom_column("notes");
+
+        let changes = books.merge(other);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(collect_weights(&books), vec![3, 2, 0, 4]);
+        assert!(books.contains_book("Franz Kafka", "The Castle"));
+        assert!(books.has_column("notes"));
+
+        // the same ISBN in another form is a duplicate, whatever the title
+        let mut castle = Book::new("Kafka".into(), "Das Schloss".into(), 1);
+        castle
+            .custom
+            .insert(field::ISBN.into(), "0-8052-1106-3".into());
+        books.merge(vec![castle].into());
+        let mut duplicate = Book::new("F. Kafka".into(), "Castle".into(), 1);
+        duplicate
+            .custom
+            .insert(field::ISBN.into(), "9780805211061".into());
+        assert!(books.merge(vec![duplicate.clone()].into()).is_empty());
+        assert_eq!(books.position(&duplicate), Some(4));
+        assert_eq!(
+            books.position(&Book::new("FRANZ KAFKA".into(), "the castle".into(), 1)),
+            Some(3)
+        );
+    }
+
+    #[test]
+    fn test_filter_indexes() {
+        let mut books: BookRecords = books_to_test(vec![3, 2, 0]);
+        books.records[1]
+            .custom
+            .insert("recommended_by".into(), "Alex".into());
+
+        let filter = |c: &str, v: &str| vec![(c.to_string(), v.to_string())];
+        assert_eq!(
+            books.filter_indexes(&filter("title", "title")),
+            vec![0, 1, 2]
+        );
+        assert_eq!(books.filter_indexes(&filter("author", "hack")), vec![2]);
+        assert_eq!(
+            books.filter_indexes(&filter("recommended_by", "alex")),
+            vec![1]
+        );
+        assert!(books.filter_indexes(&filter("notes", "")).is_empty());
+    }
 }
@@ -0,0 +1,346 @@
+use super::{
+    book::{field, Book},
+    book_records::BookRecords,
+};
+use std::collections::BTreeMap;
+
+/// A list as BibTeX `@book` entries, for reference managers.
+pub fn bibtex(books: &BookRecords) -> String {
+    let keys = citation_keys(books);
+    let entries: Vec<String> = books
+        .records()
+        .iter()
+        .zip(keys)
+        .map(|(book, key)| {
+            let mut fields = vec![
+                ("author", authors(book).join(" and ")),
+                ("title", book.title.clone()),
+            ];
+            fields.extend(
+                [
+                    ("year", year(book)),
+                    ("publisher", nonempty(book, field::PUBLISHER)),
+                    ("isbn", book.isbn().map(|i| i.to_string())),
+                ]
+                .into_iter()
+                .filter_map(|(name, value)| Some((name, value?))),
+            );
+
+            let fields: String = fields
+                .iter()
+                .map(|(name, value)| format!("  {name} = {{{}}},\n", escape_latex(value)))
+                .collect();
+            format!("@book{{{key},\n{fields}}}\n")
+        })
+        .collect();
+    entries.join("\n")
+}
+
+/// A list as RIS records, for reference managers.
+pub fn ris(books: &BookRecords) -> String {
+    let keys = citation_keys(books);
+    books
+        .records()
+        .iter()
+        .zip(keys)
+        .map(|(book, key)| {
+            let mut lines = vec![("TY", "BOOK".to_string()), ("ID", key)];
+            lines.extend(authors(book).iter().map(|a| ("AU", sorted_name(a))));
+            lines.push(("TI", book.title.clone()));
+            lines.extend(
+                [
+                    ("PY", year(book)),
+                    ("PB", nonempty(book, field::PUBLISHER)),
+                    ("SN", book.isbn().map(|i| i.to_string())),
+                ]
+                .into_iter()
+                .filter_map(|(tag, value)| Some((tag, value?))),
+            );
+            lines.push(("ER", String::new()));
+
+            lines
+                .iter()
+                .map(|(tag, value)| format!("{tag}  - {}\n", value.replace(['\r', '\n'], " ")))
+                .collect::<String>()
+        })
+        .collect()
+}
+
+/// A citation key for each book, such as `kafka1926castle-kvwp`, made of the author's surname, the year,
+/// the first word of the title and a short hash of the whole title. A key depends on the book alone,
+/// so it doesn't change when other books are added or the list is sorted.
+/// Books with the same author, year and title get `b`, `c`, ... on the end of all but the first one's key.
+pub fn citation_keys(books: &BookRecords) -> Vec<String> {
+    let mut seen: BTreeMap<String, usize> = BTreeMap::new();
+    books
+        .records()
+        .iter()
+        .map(|book| {
+            let surname = ascii_fold(&surname(&book.author));
+            let surname = if surname.is_empty() {
+                "anon".to_string()
+            } else {
+                surname
+            };
+            let year = year(book).unwrap_or_else(|| "nd".into());
+            let key = format!(
+                "{surname}{year}{}-{}",
+                first_word(&book.title),
+                title_hash(&book.title)
+            );
+            let count = seen.entry(key.clone()).or_default();
+            *count += 1;
+            match *count {
+                1 => key,
+                n => format!("{key}{}", suffix(n - 1)),
+            }
+        })
+        .collect()
+}
+
+/// Four letters and digits that differ between titles, ignoring case, spaces and punctuation.
+fn title_hash(title: &str) -> String {
+    let mut hash = crc32fast::hash(ascii_fold(title).as_bytes());
+    let mut chars = [0; 4];
+    for c in &mut chars {
+        *c = b"0123456789abcdefghijklmnopqrstuvwxyz"[(hash % 36) as usize];
+        hash /= 36;
+    }
+    String::from_utf8_lossy(&chars).into_owned()
+}
+
+/// The first word of a title that isn't an article, such as `castle` for `The Castle`.
+fn first_word(title: &str) -> String {
+    let words: Vec<String> = title
+        .split_whitespace()
+        .map(ascii_fold)
+        .filter(|w| !w.is_empty())
+        .collect();
+    words
+        .iter()
+        .find(|w| !["a", "an", "the"].contains(&w.as_str()))
+        .or(words.first())
+        .cloned()
+        .unwrap_or_default()
+}
+
+/// a, b, ..., z, aa, ab, ...
+fn suffix(n: usize) -> String {
+    let letter = char::from(b'a' + (n % 26) as u8);
+    match n / 26 {
+        0 => letter.to_string(),
+        m => format!("{}{letter}", suffix(m - 1)),
+    }
+}
+
+fn nonempty(book: &Book, column: &str) -> Option<String> {
+    book.field(column)
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+/// The year of publication, from the year field or the start of the published date.
+fn year(book: &Book) -> Option<String> {
+    let is_year = |s: &str| s.len() == 4 && s.chars().all(|c| c.is_ascii_digit());
+    nonempty(book, field::YEAR)
+        .filter(|y| is_year(y))
+        .or_else(|| {
+            nonempty(book, field::PUBLISHED)
+                .and_then(|p| p.get(..4).map(String::from))
+                .filter(|y| is_year(y))
+        })
+}
+
+/// The author followed by any additional authors.
+fn authors(book: &Book) -> Vec<String> {
+    let additional = nonempty(book, field::ADDITIONAL_AUTHORS).unwrap_or_default();
+    std::iter::once(book.author.trim())
+        .chain(additional.split(',').map(str::trim))
+        .filter(|a| !a.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// The last word of a name, or the part before the comma of a name like `Kafka, Franz`.
+fn surname(name: &str) -> String {
+    match name.split_once(',') {
+        Some((last, _)) => last.trim().to_string(),
+        None => name
+            .split_whitespace()
+            .last()
+            .unwrap_or_default()
+            .to_string(),
+    }
+}
+
+/// A name sorted by surname, such as `Kafka, Franz`, as RIS expects.
+fn sorted_name(name: &str) -> String {
+    if name.contains(',') {
+        return name.to_string();
+    }
+    match name.rsplit_once(' ') {
+        Some((first, last)) => format!("{}, {}", last.trim(), first.trim()),
+        None => name.to_string(),
+    }
+}
+
+/// Lowercase ASCII letters and digits only, with accents removed from common Latin letters.
+fn ascii_fold(s: &str) -> String {
+    s.to_lowercase()
+        .chars()
+        .filter_map(|c| {
+            let c = match c {
+                'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ą' => 'a',
+                'ç' | 'ć' | 'č' => 'c',
+                'ď' | 'đ' => 'd',
+                'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ę' | 'ě' => 'e',
+                'ì' | 'í' | 'î' | 'ï' | 'ī' => 'i',
+                'ł' => 'l',
+                'ñ' | 'ń' | 'ň' => 'n',
+                'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ő' => 'o',
+                'ř' => 'r',
+                'ś' | 'š' | 'ş' => 's',
+                'ť' => 't',
+                'ù' | 'ú' | 'û' | 'ü' | 'ū' | 'ů' | 'ű' => 'u',
+                'ý' | 'ÿ' => 'y',
+                'ź' | 'ż' | 'ž' => 'z',
+                c => c,
+            };
+            c.is_ascii_alphanumeric().then_some(c)
+        })
+        .collect()
+}
+
+/// Escape the characters that have a special meaning in LaTeX.
+fn escape_latex(s: &str) -> String {
+    let mut escaped = String::new();
+    for c in s.chars() {
+        match c {
+            '\\' => escaped.push_str("\\textbackslash{}"),
+            '~' => escaped.push_str("\\textasciitilde{}"),
+            '^' => escaped.push_str("\\textasciicircum{}"),
+            '{' | '}' | '&' | '%' | '$' | '#' | '_' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\r' | '\n' => escaped.push(' '),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+//
+//
+//
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn books_to_test() -> BookRecords {
+        let mut castle = Book::new("Franz Kafka".into(), "The Castle".into(), 1);
+        castle.custom.insert(field::YEAR.into(), "1926".into());
+        castle
+            .custom
+            .insert(field::PUBLISHER.into(), "Schocken & Sons".into());
+        castle
+            .custom
+            .insert(field::ISBN.into(), "0805211063".into());
+        castle.custom.insert(
+            field::ADDITIONAL_AUTHORS.into(),
+            "Willa Muir, Edwin Muir".into(),
+        );
+
+        let mut trial = Book::new("Franz Kafka".into(), "The Trial".into(), 1);
+        trial
+            .custom
+            .insert(field::PUBLISHED.into(), "1925-04-26".into());
+        let mut amerika = Book::new("Kafka, Franz".into(), "Amerika".into(), 1);
+        amerika.custom.insert(field::YEAR.into(), "1927".into());
+        let mut america = Book::new("Franz Kafka".into(), "America".into(), 1);
+        america.custom.insert(field::YEAR.into(), "1927".into());
+
+        vec![
+            castle,
+            trial,
+            amerika,
+            Book::new(
+                "Gabriel García Márquez".into(),
+                "Cien años de soledad".into(),
+                1,
+            ),
+            america,
+        ]
+        .into()
+    }
+
+    #[test]
+    fn test_citation_keys() {
+        let books = books_to_test();
+        let keys = citation_keys(&books);
+        assert_eq!(
+            keys,
+            vec![
+                "kafka1926castle-kvwp",
+                "kafka1925trial-6pg3",
+                "kafka1927amerika-0vvp",
+                "marquezndcien-ne6o",
+                "kafka1927america-o7vm"
+            ]
+        );
+
+        // keys don't depend on the order of the list
+        let mut reversed = books.records().clone();
+        reversed.reverse();
+        let mut reversed_keys = citation_keys(&reversed.into());
+        reversed_keys.reverse();
+        assert_eq!(reversed_keys, keys);
+
+        // or on the other books in it, even ones whose titles start with the same word
+        let mut added = books.clone();
+        let mut keep = Book::new("Franz Kafka".into(), "Castle Keep".into(), 1);
+        keep.custom.insert(field::YEAR.into(), "1926".into());
+        added.push(keep);
+        added.push(Book::new("Franz Kafka".into(), "The".into(), 1));
+        let added_keys = citation_keys(&added);
+        assert_eq!(added_keys[..5], keys);
+        assert!(added_keys[5].starts_with("kafka1926castle-"));
+        assert_ne!(added_keys[5], keys[0]);
+        assert!(added_keys[6].starts_with("kafkandthe-"));
+
+        // only the same book added twice gets a letter
+        let mut twice = books.clone();
+        twice.push(books.records()[0].clone());
+        let twice_keys = citation_keys(&twice);
+        assert_eq!(twice_keys[..5], keys);
+        assert_eq!(twice_keys[5], "kafka1926castle-kvwpb");
+    }
+
+    #[test]
+    fn test_bibtex() {
+        let bibtex = bibtex(&books_to_test());
+        assert!(bibtex.starts_with(
+            "@book{kafka1926castle-kvwp,\n  author = {Franz Kafka and Willa Muir and Edwin Muir},\n  title = {The Castle},\n  year = {1926},\n  publisher = {Schocken \\& Sons},\n  isbn = {9780805211061},\n}\n\n@book{kafka1925trial-6pg3,\n"
+        ));
+        assert!(bibtex.contains("@book{marquezndcien-ne6o,\n  author = {Gabriel García Márquez},\n  title = {Cien años de soledad},\n}\n"));
+        assert_eq!(bibtex.matches("@book{").count(), 5);
+
+        // characters with a special meaning in LaTeX are escaped
+        let revolution: BookRecords =
+            vec![Book::new("Anon".into(), "Ça ira ~ 1789 ^_^ \\o/".into(), 1)].into();
+        assert!(super::bibtex(&revolution)
+            .contains("  title = {Ça ira \\textasciitilde{} 1789 \\textasciicircum{}\\_\\textasciicircum{} \\textbackslash{}o/},\n"));
+    }
+
+    #[test]
+    fn test_ris() {
+        let ris = ris(&books_to_test());
+        assert!(ris.starts_with(
+            "TY  - BOOK\nID  - kafka1926castle-kvwp\nAU  - Kafka, Franz\nAU  - Muir, Willa\nAU  - Muir, Edwin\nTI  - The Castle\nPY  - 1926\nPB  - Schocken & Sons\nSN  - 9780805211061\nER  - \n"
+        ));
+        assert!(ris.contains(
+            "ID  - kafka1927amerika-0vvp\nAU  - Kafka, Franz\nTI  - Amerika\nPY  - 1927\nER  - \n"
+        ));
+        assert_eq!(ris.matches("ER  - \n").count(), 5);
+    }
+}
@@ -0,0 +1,152 @@
+use super::book::Book;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Something that happened to a book, kept in the reading history.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Event {
+    /// Chosen by `wych`.
+    Picked,
+    /// Marked as read with `book finish`.
+    Finished,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub time: DateTime<Utc>,
+    pub event: Event,
+    pub list: String,
+    pub author: String,
+    pub title: String,
+}
+
+/// Every book picked or finished, across all lists, oldest first.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct History {
+    entries: Vec<HistoryEntry>,
+}
+
+impl History {
+    /// Record that something happened to a book just now.
+    pub fn record(&mut self, event: Event, list: &str, book: &Book) {
+        self.record_at(Utc::now(), event, list, book);
+    }
+
+    pub fn record_at(&mut self, time: DateTime<Utc>, event: Event, list: &str, book: &Book) {
+        self.entries.push(HistoryEntry {
+            time,
+            event,
+            list: list.to_string(),
+            author: book.author.clone(),
+            title: book.title.clone(),
+        });
+    }
+
+    /// Remove the latest entry for an event that happened to a book, such as when it is undone.
+    /// Returns false if there is no such entry.
+    pub fn remove_latest(&mut self, event: Event, list: &str, book: &Book) -> bool {
+        let latest = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter(|(_, e)| {
+                e.event == event
+                    && e.list == list
+                    && e.author == book.author
+                    && e.title == book.title
+            })
+            .max_by_key(|(_, e)| e.time)
+            .map(|(i, _)| i);
+        latest.map(|i| self.entries.remove(i)).is_some()
+    }
+
+    pub fn entries(&self) -> &[HistoryEntry] {
+        &self.entries
+    }
+
+    /// Add the entries of another history that aren't already in this one, keeping oldest first.
+    pub fn merge(&mut self, other: History) {
+        for entry in other.entries {
+            if !self.entries.contains(&entry) {
+                self.entries.push(entry);
+            }
+        }
+        self.entries.sort_by_key(|e| e.time);
+    }
+
+    /// Up to `count` of the latest entries for an event, most recent first.
+    pub fn recent(&self, event: Event, count: usize) -> Vec<&HistoryEntry> {
+        let mut entries: Vec<&HistoryEntry> =
+            self.entries.iter().filter(|e| e.event == event).collect();
+        entries.sort_by_key(|e| std::cmp::Reverse(e.time));
+        entries.truncate(count);
+        entries
+    }
+}
+
+//
+//
+//
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_recent() {
+        let mut history = History::default();
+        let book = |title: &str| Book::new("Franz Kafka".into(), title.into(), 1);
+        let day = |d| Utc.with_ymd_and_hms(2024, 3, d, 12, 0, 0).unwrap();
+
+        history.record_at(day(2), Event::Picked, "books", &book("The Trial"));
+        history.record_at(day(1), Event::Picked, "books", &book("The Castle"));
+        history.record_at(day(3), Event::Finished, "books", &book("The Trial"));
+        history.record_at(day(4), Event::Picked, "books", &book("Amerika"));
+
+        let titles = |entries: Vec<&HistoryEntry>| {
+            entries.iter().map(|e| e.title.clone()).collect::<Vec<_>>()
+        };
+        assert_eq!(
+            titles(history.recent(Event::Picked, 2)),
+            vec!["Amerika", "The Trial"]
+        );
+        assert_eq!(
+            titles(history.recent(Event::Finished, 10)),
+            vec!["The Trial"]
+        );
+        assert_eq!(history.entries().len(), 4);
+    }
+
+    #[test]
+    fn test_merge() {
+        let book = Book::new("Franz Kafka".into(), "The Castle".into(), 1);
+        let day = |d| Utc.with_ymd_and_hms(2024, 3, d, 12, 0, 0).unwrap();
+        let mut history = History::default();
+        history.record_at(day(1), Event::Picked, "books", &book);
+        history.record_at(day(3), Event::Finished, "books", &book);
+
+        let mut other = History::default();
+        other.record_at(day(2), Event::Picked, "books2", &book);
+        other.record_at(day(3), Event::Finished, "books", &book);
+        history.merge(other);
+
+        let lists: Vec<&str> = history.entries().iter().map(|e| e.list.as_str()).collect();
+        assert_eq!(lists, vec!["books", "books2", "books"]);
+    }
+
+    #[test]
+    fn test_remove_latest() {
+        let book = Book::new("Franz Kafka".into(), "The Castle".into(), 1);
+        let day = |d| Utc.with_ymd_and_hms(2024, 3, d, 12, 0, 0).unwrap();
+        let mut history = History::default();
+        history.record_at(day(3), Event::Finished, "books", &book);
+        history.record_at(day(1), Event::Finished, "books", &book);
+        history.record_at(day(2), Event::Picked, "books", &book);
+
+        assert!(history.remove_latest(Event::Finished, "books", &book));
+        let times: Vec<_> = history.entries().iter().map(|e| e.time).collect();
+        assert_eq!(times, vec![day(1), day(2)]);
+        assert!(!history.remove_latest(Event::Finished, "books2", &book));
+    }
+}
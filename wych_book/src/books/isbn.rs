@@ -0,0 +1,198 @@
+use std::{error::Error, fmt::Display, str::FromStr};
+
+/// An International Standard Book Number with a valid check digit.
+/// ISBN-10s are converted to ISBN-13s, so both forms of the same ISBN are equal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Isbn {
+    digits: [u8; 13],
+}
+
+/// Why a string is not a valid ISBN.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IsbnError {
+    /// Not 10 or 13 digits long, ignoring hyphens and spaces.
+    Length(usize),
+    /// A character that isn't a digit, or an X anywhere but the end of an ISBN-10.
+    Character(char),
+    /// The last digit doesn't match the others.
+    CheckDigit { expected: char, found: char },
+    /// An ISBN-13 that doesn't start with 978 or 979.
+    Prefix,
+}
+
+impl Display for IsbnError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IsbnError::Length(length) => {
+                write!(f, "an ISBN has 10 or 13 digits, but this has {length}")
+            }
+            IsbnError::Character(c) => write!(f, "'{c}' is not allowed in an ISBN"),
+            IsbnError::CheckDigit { expected, found } => {
+                write!(f, "the check digit should be {expected}, not {found}")
+            }
+            IsbnError::Prefix => write!(f, "an ISBN-13 starts with 978 or 979"),
+        }
+    }
+}
+
+impl Error for IsbnError {}
+
+impl Isbn {
+    /// Parse an ISBN-10 or ISBN-13, ignoring any hyphens and spaces.
+    pub fn parse(s: &str) -> Result<Self, IsbnError> {
+        let characters: Vec<char> = s
+            .trim()
+            .chars()
+            .filter(|c| *c != '-' && !c.is_whitespace())
+            .collect();
+
+        match characters.len() {
+            10 => Isbn::parse_isbn10(&characters),
+            13 => Isbn::parse_isbn13(&characters),
+            length => Err(IsbnError::Length(length)),
+        }
+    }
+
+    fn parse_isbn10(characters: &[char]) -> Result<Self, IsbnError> {
+        let mut digits = [9, 7, 8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        for (i, c) in characters[..9].iter().enumerate() {
+            digits[i + 3] = digit(*c)?;
+        }
+
+        let expected = isbn10_check_digit(&digits[3..12]);
+        let found = characters[9].to_ascii_uppercase();
+        if found != 'X' {
+            digit(found)?;
+        }
+        if expected != found {
+            return Err(IsbnError::CheckDigit { expected, found });
+        }
+
+        digits[12] = isbn13_check_digit(&digits[..12]);
+        Ok(Isbn { digits })
+    }
+
+    fn parse_isbn13(characters: &[char]) -> Result<Self, IsbnError> {
+        let mut digits = [0; 13];
+        for (i, c) in characters.iter().enumerate() {
+            digits[i] = digit(*c)?;
+        }
+        if digits[..3] != [9, 7, 8] && digits[..3] != [9, 7, 9] {
+            return Err(IsbnError::Prefix);
+        }
+
+        let expected = isbn13_check_digit(&digits[..12]);
+        if expected != digits[12] {
+            return Err(IsbnError::CheckDigit {
+                expected: char::from(b'0' + expected),
+                found: characters[12],
+            });
+        }
+        Ok(Isbn { digits })
+    }
+
+    /// The ISBN-13 form, without hyphens.
+    pub fn to_isbn13(&self) -> String {
+        self.digits.iter().map(|d| char::from(b'0' + d)).collect()
+    }
+
+    /// The ISBN-10 form, without hyphens. Only ISBNs starting with 978 have one.
+    pub fn to_isbn10(&self) -> Option<String> {
+        if self.digits[..3] != [9, 7, 8] {
+            return None;
+        }
+        let body = &self.digits[3..12];
+        let mut isbn10: String = body.iter().map(|d| char::from(b'0' + d)).collect();
+        isbn10.push(isbn10_check_digit(body));
+        Some(isbn10)
+    }
+}
+
+fn digit(c: char) -> Result<u8, IsbnError> {
+    c.to_digit(10)
+        .map(|d| d as u8)
+        .ok_or(IsbnError::Character(c))
+}
+
+/// Check digit of the 9 digits of an ISBN-10 body, which is X for 10.
+fn isbn10_check_digit(body: &[u8]) -> char {
+    let sum: u32 = body
+        .iter()
+        .enumerate()
+        .map(|(i, d)| (10 - i as u32) * *d as u32)
+        .sum();
+    match (11 - sum % 11) % 11 {
+        10 => 'X',
+        check => char::from(b'0' + check as u8),
+    }
+}
+
+/// Check digit of the first 12 digits of an ISBN-13.
+fn isbn13_check_digit(body: &[u8]) -> u8 {
+    let sum: u32 = body
+        .iter()
+        .enumerate()
+        .map(|(i, d)| if i % 2 == 0 { *d as u32 } else { 3 * *d as u32 })
+        .sum();
+    ((10 - sum % 10) % 10) as u8
+}
+
+impl FromStr for Isbn {
+    type Err = IsbnError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Isbn::parse(s)
+    }
+}
+
+/// Shown in its ISBN-13 form, without hyphens.
+impl Display for Isbn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_isbn13())
+    }
+}
+
+//
+//
+//
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_isbn() {
+        let isbn = Isbn::parse("0-8052-1106-3").unwrap();
+        assert_eq!(isbn.to_isbn13(), "9780805211061");
+        assert_eq!(isbn.to_isbn10(), Some("0805211063".into()));
+        assert_eq!(isbn, "978-0-8052-1106-1".parse().unwrap());
+
+        // X is a check digit of 10
+        let isbn = Isbn::parse("0-8044-2957-x").unwrap();
+        assert_eq!(isbn.to_isbn10(), Some("080442957X".into()));
+        assert_eq!(isbn.to_string(), "9780804429573");
+
+        let isbn = Isbn::parse("979-10-90636-07-1").unwrap();
+        assert_eq!(isbn.to_isbn10(), None);
+    }
+
+    #[test]
+    fn test_isbn_errors() {
+        assert_eq!(Isbn::parse("12345"), Err(IsbnError::Length(5)));
+        assert_eq!(Isbn::parse("08052X1063"), Err(IsbnError::Character('X')));
+        assert_eq!(
+            Isbn::parse("0805211064"),
+            Err(IsbnError::CheckDigit {
+                expected: '3',
+                found: '4'
+            })
+        );
+        assert_eq!(
+            Isbn::parse("9780805211062"),
+            Err(IsbnError::CheckDigit {
+                expected: '1',
+                found: '2'
+            })
+        );
+        assert_eq!(Isbn::parse("1234567890128"), Err(IsbnError::Prefix));
+    }
+}
@@ -0,0 +1,180 @@
+use super::{book::Book, book_records::BookRecords};
+use serde::{Deserialize, Serialize};
+use std::{error::Error, fmt::Display};
+
+/// Max number of changes that can be undone.
+const MAX_ENTRIES: usize = 100;
+
+/// A reversible change to a list of books.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum Change {
+    Add {
+        index: usize,
+        book: Book,
+    },
+    Delete {
+        index: usize,
+        book: Book,
+    },
+    /// A book was removed by `book finish`, which also adds it to the reading history.
+    Finish {
+        index: usize,
+        book: Book,
+    },
+    Weight {
+        index: usize,
+        from: u8,
+        to: u8,
+    },
+    /// The fields of a book were changed, such as by `book enrich`.
+    Edit {
+        index: usize,
+        from: Book,
+        to: Book,
+    },
+    /// After sorting, the book at position `i` was previously at position `order[i]`.
+    Sort {
+        order: Vec<usize>,
+    },
+    /// Weights of all books before they were reset.
+    Reset {
+        weights: Vec<u8>,
+    },
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Change::Add { book, .. } => write!(f, "add {} by {}", book.title, book.author),
+            Change::Delete { book, .. } => write!(f, "delete {} by {}", book.title, book.author),
+            Change::Finish { book, .. } => write!(f, "finish {} by {}", book.title, book.author),
+            Change::Weight { index, from, to } => {
+                write!(f, "change weight of book {index} from {from} to {to}")
+            }
+            Change::Edit { to, .. } => write!(f, "edit {} by {}", to.title, to.author),
+            Change::Sort { .. } => write!(f, "sort list"),
+            Change::Reset { .. } => write!(f, "reset weights"),
+        }
+    }
+}
+
+/// A history of changes to a list, which can be undone and redone.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Journal {
+    undo: Vec<Change>,
+    redo: Vec<Change>,
+}
+
+impl Journal {
+    /// Record a new change. Any undone changes can no longer be redone.
+    pub fn record(&mut self, change: Change) {
+        self.undo.push(change);
+        if self.undo.len() > MAX_ENTRIES {
+            self.undo.remove(0);
+        }
+        self.redo.clear();
+    }
+
+    /// Revert the most recent change to the books.
+    /// Returns the change that was undone, or None if there is nothing to undo.
+    pub fn undo(&mut self, books: &mut BookRecords) -> Result<Option<Change>, Box<dyn Error>> {
+        let change = match self.undo.last() {
+            Some(change) => change.clone(),
+            None => return Ok(None),
+        };
+
+        books.revert(&change)?;
+        self.undo.pop();
+        self.redo.push(change.clone());
+        Ok(Some(change))
+    }
+
+    /// Apply the most recently undone change to the books again.
+    /// Returns the change that was redone, or None if there is nothing to redo.
+    pub fn redo(&mut self, books: &mut BookRecords) -> Result<Option<Change>, Box<dyn Error>> {
+        let change = match self.redo.last() {
+            Some(change) => change.clone(),
+            None => return Ok(None),
+        };
+
+        books.apply(&change)?;
+        self.redo.pop();
+        self.undo.push(change.clone());
+        Ok(Some(change))
+    }
+}
+
+//
+//
+//
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::books::book::Header;
+
+    fn books_to_test() -> BookRecords {
+        vec![
+            Book::new("Franz Kafka".into(), "The Castle".into(), 3),
+            Book::new("Yoko Ogawa".into(), "The Memory Police".into(), 1),
+            Book::new("Flann O'Brien".into(), "The Third Policeman".into(), 2),
+        ]
+        .into()
+    }
+
+    #[test]
+    fn test_undo_redo() {
+        let original = books_to_test();
+        let mut books = original.clone();
+        let mut journal = Journal::default();
+
+        journal.record(books.add_book("Bram Stoker", "Dracula"));
+        journal.record(books.remove_book("0").unwrap());
+        journal.record(books.finish_book("The Third Policeman").unwrap());
+        journal.record(books.change_weight("Dracula", 7).unwrap());
+        let mut edited = books.get(0).unwrap().clone();
+        edited.author = "Yōko Ogawa".into();
+        journal.record(books.edit_book(0, edited).unwrap());
+        journal.record(books.sort_by(Header::Title));
+        journal.record(books.reset_weights());
+        let changed = books.clone();
+
+        while journal.undo(&mut books).unwrap().is_some() {}
+        assert_eq!(books, original);
+
+        while journal.redo(&mut books).unwrap().is_some() {}
+        assert_eq!(books, changed);
+    }
+
+    #[test]
+    fn test_record_clears_redo() {
+        let mut books = books_to_test();
+        let mut journal = Journal::default();
+
+        journal.record(books.add_book("Bram Stoker", "Dracula"));
+        journal.undo(&mut books).unwrap();
+        journal.record(books.remove_book("0").unwrap());
+
+        assert!(journal.redo(&mut books).unwrap().is_none());
+        assert_eq!(books.records().len(), 2);
+    }
+
+    #[test]
+    fn test_undo_changed_list() {
+        let mut books = books_to_test();
+        let mut journal = Journal::default();
+
+        journal.record(books.remove_book("2").unwrap());
+        // list edited outside of the journal
+        books.remove_book("1");
+        assert!(journal.undo(&mut books).is_err());
+
+        let mut books = books_to_test();
+        journal.record(books.change_weight("0", 5).unwrap());
+        books.change_weight("0", 2);
+        assert!(journal.undo(&mut books).is_err());
+        // the failed change can still be undone once the list is fixed
+        books.change_weight("0", 5);
+        assert!(journal.undo(&mut books).unwrap().is_some());
+    }
+}
@@ -0,0 +1,144 @@
+use super::{book::Header, book_records::BookRecords};
+
+/// Header names and cell values of a list, with a column for every field.
+fn cells(books: &BookRecords) -> (Vec<String>, Vec<Vec<String>>) {
+    let headers = Header::headers()
+        .into_iter()
+        .map(String::from)
+        .chain(books.custom_columns().iter().cloned())
+        .collect();
+
+    let rows = books
+        .records()
+        .iter()
+        .map(|book| {
+            let custom = books
+                .custom_columns()
+                .iter()
+                .map(|c| book.custom.get(c).cloned().unwrap_or_default());
+            book.as_string_array().into_iter().chain(custom).collect()
+        })
+        .collect();
+
+    (headers, rows)
+}
+
+/// A list as a Markdown table, for pasting into wikis and documents.
+pub fn markdown_table(books: &BookRecords) -> String {
+    // pipes would end a cell, and a line break would end the table
+    let escape = |s: &str| s.replace('|', "\\|").replace(['\r', '\n'], " ");
+    let row = |cells: &[String]| {
+        let cells: Vec<String> = cells.iter().map(|c| escape(c)).collect();
+        format!("| {} |", cells.join(" | "))
+    };
+
+    let (headers, rows) = cells(books);
+    let divider = vec!["---".to_string(); headers.len()];
+    let mut lines = vec![row(&headers), row(&divider).replace(' ', "")];
+    lines.extend(rows.iter().map(|r| row(r)));
+    lines.join("\n") + "\n"
+}
+
+/// A list as an HTML table, with a header row and a row per book.
+pub fn html_table(books: &BookRecords) -> String {
+    let row = |tag: &str, cells: &[String]| {
+        let cells: String = cells
+            .iter()
+            .map(|c| format!("<{tag}>{}</{tag}>", escape_html(c)))
+            .collect();
+        format!("      <tr>{cells}</tr>\n")
+    };
+
+    let (headers, rows) = cells(books);
+    let body: String = rows.iter().map(|r| row("td", r)).collect();
+    format!(
+        "  <table>\n    <thead>\n{}    </thead>\n    <tbody>\n{body}    </tbody>\n  </table>\n",
+        row("th", &headers)
+    )
+}
+
+/// A standalone HTML page, with `body` under a heading of the page's title.
+pub fn html_document(title: &str, body: &str) -> String {
+    let title = escape_html(title);
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+  <meta charset="utf-8">
+  <meta name="viewport" content="width=device-width, initial-scale=1">
+  <title>{title}</title>
+  <style>
+    body {{ font-family: sans-serif; margin: 2em; }}
+    table {{ border-collapse: collapse; }}
+    th, td {{ border: 1px solid #ccc; padding: 0.4em 0.8em; text-align: left; }}
+    th {{ background: #f4f4f4; }}
+  </style>
+</head>
+<body>
+  <h1>{title}</h1>
+{body}</body>
+</html>
+"#
+    )
+}
+
+/// A list as a standalone HTML page, titled with the list's name.
+pub fn html_page(books: &BookRecords, name: &str) -> String {
+    html_document(name, &html_table(books))
+}
+
+/// Escape text so it can be placed in HTML content or attribute values.
+pub fn escape_html(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '&' => "&amp;".to_string(),
+            '<' => "&lt;".to_string(),
+            '>' => "&gt;".to_string(),
+            '"' => "&quot;".to_string(),
+            '\'' => "&#39;".to_string(),
+            c => c.to_string(),
+        })
+        .collect()
+}
+
+//
+//
+//
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::books::book::Book;
+
+    fn books_to_test() -> BookRecords {
+        let mut book = Book::new("Franz Kafka".into(), "The Castle".into(), 3);
+        book.custom
+            .insert("notes".into(), "Unfinished | <abandoned>\nby K".into());
+        vec![
+            book,
+            Book::new("Yōko Ogawa".into(), "The Memory Police".into(), 1),
+        ]
+        .into()
+    }
+
+    #[test]
+    fn test_markdown_table() {
+        let expected = "| author | title | weight | notes |
+|---|---|---|---|
+| Franz Kafka | The Castle | 3 | Unfinished \\| <abandoned> by K |
+| Yōko Ogawa | The Memory Police | 1 |  |
+";
+        assert_eq!(markdown_table(&books_to_test()), expected);
+    }
+
+    #[test]
+    fn test_html_page() {
+        let page = html_page(&books_to_test(), "Kafka & friends");
+        assert!(page.starts_with("<!DOCTYPE html>"));
+        assert!(page.contains("<title>Kafka &amp; friends</title>"));
+        assert!(
+            page.contains("<tr><th>author</th><th>title</th><th>weight</th><th>notes</th></tr>")
+        );
+        assert!(page.contains("<td>Unfinished | &lt;abandoned&gt;\nby K</td>"));
+        assert!(page.contains("<td>Yōko Ogawa</td><td>The Memory Police</td><td>1</td><td></td>"));
+    }
+}
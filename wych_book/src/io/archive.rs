@@ -0,0 +1,397 @@
+use super::{
+    config::WychConfig,
+    csv::{read_csv_bytes, write_csv_bytes},
+    files::write_atomic,
+};
+use crate::books::{book_records::BookRecords, history::History, journal::Journal};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{
+    error::Error,
+    fs::{self, File},
+    io::{Read, Write},
+    path::{Component, Path, PathBuf},
+};
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipArchive, ZipWriter};
+
+/// Version of the archive layout. Archives written by a newer version can't be imported.
+pub const ARCHIVE_VERSION: u32 = 1;
+
+const MANIFEST_FILE: &str = "manifest.json";
+const CONFIG_FILE: &str = "config.json";
+const HISTORY_FILE: &str = "history.json";
+const BACKUPS_DIR: &str = "backups";
+/// Largest that all of the files in an archive can be once decompressed,
+/// so that a corrupt or malicious archive can't use up all the memory.
+const MAX_UNCOMPRESSED_SIZE: u64 = 1024 * 1024 * 1024;
+
+/// The contents of files, by their paths relative to a directory, separated by `/`.
+pub type Files = Vec<(String, Vec<u8>)>;
+
+/// Describes what an archive holds. Kept in the archive as `manifest.json`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub version: u32,
+    pub created: DateTime<Utc>,
+    /// Version of wych_book that wrote the archive.
+    pub wych_book_version: String,
+    pub lists: Vec<ArchivedList>,
+    /// Files in the backups directory, relative to it.
+    pub backups: Vec<String>,
+}
+
+/// Where a list and its journal are kept in an archive.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchivedList {
+    pub name: String,
+    pub file: String,
+    pub journal: String,
+}
+
+/// A list, with its history of changes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkspaceList {
+    pub name: String,
+    pub books: BookRecords,
+    pub journal: Journal,
+}
+
+/// Everything kept in the wych_book directory: the config, every list, the reading history
+/// and the backups.
+#[derive(Debug)]
+pub struct Workspace {
+    pub config: WychConfig,
+    pub lists: Vec<WorkspaceList>,
+    pub history: History,
+    /// Everything in the backups directory.
+    pub backups: Files,
+}
+
+/// Write a workspace to a compressed archive. Returns the archive's manifest.
+pub fn write_archive(filename: &Path, workspace: &Workspace) -> Result<Manifest, Box<dyn Error>> {
+    let manifest = Manifest {
+        version: ARCHIVE_VERSION,
+        created: Utc::now(),
+        wych_book_version: env!("CARGO_PKG_VERSION").to_string(),
+        lists: workspace
+            .lists
+            .iter()
+            .map(|l| ArchivedList {
+                name: l.name.clone(),
+                file: format!("lists/{}.csv", l.name),
+                journal: format!("journal/{}.json", l.name),
+            })
+            .collect(),
+        backups: workspace.backups.iter().map(|(p, _)| p.clone()).collect(),
+    };
+
+    let mut files = vec![
+        (
+            MANIFEST_FILE.to_string(),
+            serde_json::to_vec_pretty(&manifest)?,
+        ),
+        (
+            CONFIG_FILE.to_string(),
+            serde_json::to_vec_pretty(&workspace.config)?,
+        ),
+        (
+            HISTORY_FILE.to_string(),
+            serde_json::to_vec_pretty(&workspace.history)?,
+        ),
+    ];
+    for (list, archived) in workspace.lists.iter().zip(&manifest.lists) {
+        files.push((archived.file.clone(), write_csv_bytes(&list.books)?));
+        files.push((archived.journal.clone(), serde_json::to_vec(&list.journal)?));
+    }
+    for (path, contents) in &workspace.backups {
+        files.push((format!("{BACKUPS_DIR}/{path}"), contents.clone()));
+    }
+
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    write_atomic(filename, |file| {
+        let mut zip = ZipWriter::new(file);
+        for (name, contents) in &files {
+            zip.start_file(name.as_str(), options)?;
+            zip.write_all(contents)?;
+        }
+        zip.finish()?;
+        Ok(())
+    })?;
+    Ok(manifest)
+}
+
+/// Read and check a workspace archive. Fails if the archive is from a newer version,
+/// is missing a file named in its manifest, or any of its lists can't be read.
+pub fn read_archive(filename: &Path) -> Result<(Manifest, Workspace), Box<dyn Error>> {
+    let file =
+        File::open(filename).map_err(|e| format!("Cannot open: {}, {e}", filename.display()))?;
+    let mut zip = ZipArchive::new(file)
+        .map_err(|e| format!("{} is not a wych archive, {e}", filename.display()))?;
+    let mut remaining = MAX_UNCOMPRESSED_SIZE;
+    let mut read_entry = |name: &str| {
+        let entry = zip
+            .by_name(name)
+            .map_err(|e| format!("Archive is missing {name}, {e}"))?;
+        let contents = read_limited(entry, remaining)
+            .map_err(|e| format!("Cannot read the archive's {name}, {e}"))?;
+        remaining -= contents.len() as u64;
+        Ok::<_, Box<dyn Error>>(contents)
+    };
+
+    let manifest: Manifest = serde_json::from_slice(&read_entry(MANIFEST_FILE)?)
+        .map_err(|e| format!("Cannot read the archive's manifest, {e}"))?;
+    if manifest.version > ARCHIVE_VERSION {
+        return Err(format!(
+            "Archive version {} is newer than this wych can read, please upgrade to wych_book {} or later",
+            manifest.version, manifest.wych_book_version
+        )
+        .into());
+    }
+
+    let config = serde_json::from_slice(&read_entry(CONFIG_FILE)?)
+        .map_err(|e| format!("Cannot read the archive's {CONFIG_FILE}, {e}"))?;
+    let history = serde_json::from_slice(&read_entry(HISTORY_FILE)?)
+        .map_err(|e| format!("Cannot read the archive's {HISTORY_FILE}, {e}"))?;
+
+    let mut lists = Vec::new();
+    for archived in &manifest.lists {
+        let name = Path::new(&archived.name);
+        if !is_relative_path(&archived.name) || name.components().count() != 1 {
+            return Err(format!("Archive has an invalid list name, {}", archived.name).into());
+        }
+        let books = read_csv_bytes(&read_entry(&archived.file)?, Path::new(&archived.file))?;
+        let journal = serde_json::from_slice(&read_entry(&archived.journal)?)
+            .map_err(|e| format!("Cannot read the archive's {}, {e}", archived.journal))?;
+        lists.push(WorkspaceList {
+            name: archived.name.clone(),
+            books,
+            journal,
+        });
+    }
+
+    let mut backups = Vec::new();
+    for path in &manifest.backups {
+        if !is_relative_path(path) {
+            return Err(
+                format!("Archive has a backup outside the backups directory, {path}").into(),
+            );
+        }
+        backups.push((path.clone(), read_entry(&format!("{BACKUPS_DIR}/{path}"))?));
+    }
+
+    let workspace = Workspace {
+        config,
+        lists,
+        history,
+        backups,
+    };
+    Ok((manifest, workspace))
+}
+
+/// Read all of a reader, failing if there are more than `limit` bytes, rather than reading them all first.
+fn read_limited(reader: impl Read, limit: u64) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut contents = Vec::new();
+    reader.take(limit + 1).read_to_end(&mut contents)?;
+    if contents.len() as u64 > limit {
+        return Err(format!("it is larger than {} MB", limit / 1024 / 1024).into());
+    }
+    Ok(contents)
+}
+
+/// Read every file under a directory, by its path relative to the directory.
+/// A missing directory has no files.
+pub fn read_files(dir: &Path) -> Result<Files, Box<dyn Error>> {
+    let mut files = Vec::new();
+    let mut dirs = vec![PathBuf::new()];
+    while let Some(relative) = dirs.pop() {
+        let full = dir.join(&relative);
+        if !full.try_exists()? {
+            continue;
+        }
+        for entry in fs::read_dir(full)? {
+            let entry = entry?;
+            let path = relative.join(entry.file_name());
+            if entry.file_type()?.is_dir() {
+                dirs.push(path);
+            } else {
+                let name = path
+                    .components()
+                    .map(|c| c.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                files.push((name, fs::read(entry.path())?));
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Write files read by `read_files` under a directory.
+/// Files that already exist are only overwritten if `overwrite` is set.
+pub fn write_files(
+    dir: &Path,
+    files: &[(String, Vec<u8>)],
+    overwrite: bool,
+) -> Result<(), Box<dyn Error>> {
+    for (path, contents) in files {
+        if !is_relative_path(path) {
+            return Err(format!("Cannot write {path} outside {}", dir.display()).into());
+        }
+        let filename = dir.join(path);
+        if !overwrite && filename.try_exists()? {
+            continue;
+        }
+        if let Some(parent) = filename.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        write_atomic(&filename, |file| Ok(file.write_all(contents)?))?;
+    }
+    Ok(())
+}
+
+/// Whether a path stays inside the directory it's relative to.
+fn is_relative_path(path: &str) -> bool {
+    let path = Path::new(path);
+    path.components().next().is_some()
+        && path.components().all(|c| matches!(c, Component::Normal(_)))
+}
+
+//
+//
+//
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::books::{book::Book, history::Event};
+    use tempdir::TempDir;
+
+    fn workspace_to_test() -> Workspace {
+        let castle = Book::new("Franz Kafka".into(), "The Castle".into(), 3);
+        let mut books = BookRecords::default();
+        let mut journal = Journal::default();
+        journal.record(books.add(castle.clone()));
+
+        let mut history = History::default();
+        history.record(Event::Picked, "books", &castle);
+
+        Workspace {
+            config: WychConfig::default(),
+            lists: vec![
+                WorkspaceList {
+                    name: "books".into(),
+                    books,
+                    journal,
+                },
+                WorkspaceList {
+                    name: "to buy".into(),
+                    books: BookRecords::default(),
+                    journal: Journal::default(),
+                },
+            ],
+            history,
+            backups: vec![(
+                "lists/books/2024-01-01T00-00-00-000.csv".into(),
+                b"author,title,weight\n".to_vec(),
+            )],
+        }
+    }
+
+    #[test]
+    fn test_write_read_archive() {
+        let temp_dir = TempDir::new("wych_book_tests").unwrap();
+        let filename = temp_dir.path().join("wych.zip");
+        let workspace = workspace_to_test();
+
+        let manifest = write_archive(&filename, &workspace).unwrap();
+        assert_eq!(manifest.version, ARCHIVE_VERSION);
+        assert_eq!(manifest.lists[1].file, "lists/to buy.csv");
+
+        let (read_manifest, read_workspace) = read_archive(&filename).unwrap();
+        assert_eq!(read_manifest, manifest);
+        assert_eq!(read_workspace.lists, workspace.lists);
+        assert_eq!(read_workspace.history, workspace.history);
+        assert_eq!(read_workspace.backups, workspace.backups);
+    }
+
+    #[test]
+    fn test_read_limited() {
+        assert_eq!(read_limited(&b"castle"[..], 6).unwrap(), b"castle");
+        assert!(read_limited(&b"castles"[..], 6).is_err());
+        assert!(read_limited(std::io::repeat(0), 1024).is_err());
+    }
+
+    #[test]
+    fn test_read_archive_errors() {
+        let temp_dir = TempDir::new("wych_book_tests").unwrap();
+        let filename = temp_dir.path().join("wych.zip");
+        fs::write(&filename, "author,title,weight\n").unwrap();
+        assert!(read_archive(&filename).is_err());
+
+        let write_zip = |files: &[(&str, String)]| {
+            let mut zip = ZipWriter::new(File::create(&filename).unwrap());
+            for (name, contents) in files {
+                zip.start_file(*name, SimpleFileOptions::default()).unwrap();
+                zip.write_all(contents.as_bytes()).unwrap();
+            }
+            zip.finish().unwrap();
+        };
+        let mut manifest = write_archive(&filename, &workspace_to_test()).unwrap();
+        let config = serde_json::to_string(&WychConfig::default()).unwrap();
+        let history = "{\"entries\":[]}".to_string();
+
+        // a list named in the manifest is missing
+        write_zip(&[
+            (MANIFEST_FILE, serde_json::to_string(&manifest).unwrap()),
+            (CONFIG_FILE, config.clone()),
+            (HISTORY_FILE, history.clone()),
+        ]);
+        assert!(read_archive(&filename).is_err());
+
+        // a newer version
+        manifest.version = ARCHIVE_VERSION + 1;
+        manifest.lists.clear();
+        manifest.backups.clear();
+        write_zip(&[
+            (MANIFEST_FILE, serde_json::to_string(&manifest).unwrap()),
+            (CONFIG_FILE, config.clone()),
+            (HISTORY_FILE, history.clone()),
+        ]);
+        assert!(read_archive(&filename).is_err());
+
+        // a backup outside the backups directory
+        manifest.version = ARCHIVE_VERSION;
+        manifest.backups = vec!["../config.json".into()];
+        write_zip(&[
+            (MANIFEST_FILE, serde_json::to_string(&manifest).unwrap()),
+            (CONFIG_FILE, config),
+            (HISTORY_FILE, history),
+        ]);
+        assert!(read_archive(&filename).is_err());
+    }
+
+    #[test]
+    fn test_read_write_files() {
+        let temp_dir = TempDir::new("wych_book_tests").unwrap();
+        let dir = temp_dir.path().join("backups");
+        assert!(read_files(&dir).unwrap().is_empty());
+
+        let files = vec![
+            ("config/1.json".to_string(), b"{}".to_vec()),
+            (
+                "lists/books/1.csv".to_string(),
+                b"author,title,weight\n".to_vec(),
+            ),
+        ];
+        write_files(&dir, &files, false).unwrap();
+        assert_eq!(read_files(&dir).unwrap(), files);
+
+        // existing files are kept unless overwriting
+        write_files(&dir, &[("config/1.json".into(), b"[]".to_vec())], false).unwrap();
+        assert_eq!(fs::read(dir.join("config/1.json")).unwrap(), b"{}");
+        write_files(&dir, &[("config/1.json".into(), b"[]".to_vec())], true).unwrap();
+        assert_eq!(fs::read(dir.join("config/1.json")).unwrap(), b"[]");
+
+        assert!(write_files(&dir, &[("../escaped".into(), Vec::new())], true).is_err());
+    }
+}
@@ -0,0 +1,250 @@
+use super::{files::write_atomic, paths::WychPaths};
+use crate::search::IndexSearch;
+use chrono::Utc;
+use std::{error::Error, fmt::Display, fs, io::Write, path::PathBuf};
+
+const LISTS_DIR: &str = "lists";
+const CONFIG_DIR: &str = "config";
+const CONFIG_NAME: &str = "config.json";
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%dT%H-%M-%S-%3f";
+
+/// Timestamped snapshots of a single list or config file, ordered from oldest to newest.
+#[derive(Debug)]
+pub struct Snapshots {
+    name: String,
+    dir: PathBuf,
+    extension: &'static str,
+    snapshots: Vec<String>,
+}
+
+impl Snapshots {
+    pub fn for_list(paths: &WychPaths, name: &str) -> Result<Self, Box<dyn Error>> {
+        let dir = paths.backups_dir().join(LISTS_DIR).join(name);
+        Snapshots::from_dir(name, dir, "csv")
+    }
+
+    pub fn for_config(paths: &WychPaths) -> Result<Self, Box<dyn Error>> {
+        let dir = paths.backups_dir().join(CONFIG_DIR);
+        Snapshots::from_dir(CONFIG_NAME, dir, "json")
+    }
+
+    /// Get the snapshots of every list that has been backed up.
+    pub fn for_all_lists(paths: &WychPaths) -> Result<Vec<Self>, Box<dyn Error>> {
+        let dir = paths.backups_dir().join(LISTS_DIR);
+        if !dir.try_exists()? {
+            return Ok(Vec::new());
+        }
+
+        let mut names = Vec::new();
+        for entry in fs::read_dir(&dir)? {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                names.push(entry.file_name().to_string_lossy().to_string());
+            }
+        }
+        names.sort();
+
+        names
+            .iter()
+            .map(|n| Snapshots::for_list(paths, n))
+            .collect()
+    }
+
+    /// Move the snapshots of a list when the list is renamed, alongside any the new name already has.
+    pub fn rename_list(paths: &WychPaths, from: &str, to: &str) -> Result<(), Box<dyn Error>> {
+        let from = Snapshots::for_list(paths, from)?;
+        if !from.dir.try_exists()? {
+            return Ok(());
+        }
+
+        let to = Snapshots::for_list(paths, to)?;
+        fs::create_dir_all(&to.dir)?;
+        for snapshot in &from.snapshots {
+            fs::rename(from.path(snapshot), to.path(snapshot))?;
+        }
+        if fs::read_dir(&from.dir)?.next().is_none() {
+            fs::remove_dir(&from.dir)?;
+        }
+        Ok(())
+    }
+
+    /// The list a backup is of, from its path relative to the backups directory, such as `lists/books/...`.
+    pub fn list_of(path: &str) -> Option<&str> {
+        path.strip_prefix(LISTS_DIR)?
+            .strip_prefix('/')?
+            .split('/')
+            .next()
+    }
+
+    fn from_dir(name: &str, dir: PathBuf, extension: &'static str) -> Result<Self, Box<dyn Error>> {
+        let mut snapshots = Vec::new();
+
+        if dir.try_exists()? {
+            for entry in fs::read_dir(&dir)? {
+                let path = entry?.path();
+                if path.extension().is_some_and(|e| e == extension) {
+                    if let Some(stem) = path.file_stem() {
+                        snapshots.push(stem.to_string_lossy().to_string());
+                    }
+                }
+            }
+        }
+        // timestamps sort chronologically
+        snapshots.sort();
+
+        Ok(Snapshots {
+            name: name.to_string(),
+            dir,
+            extension,
+            snapshots,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn snapshots(&self) -> &Vec<String> {
+        &self.snapshots
+    }
+
+    pub fn path(&self, snapshot: &str) -> PathBuf {
+        self.dir.join(format!("{snapshot}.{}", self.extension))
+    }
+
+    /// Save `contents` as a new snapshot, then remove the oldest snapshots so that at most `retention` are kept.
+    /// Nothing is saved if the contents are unchanged since the latest snapshot.
+    /// Returns the name of the new snapshot, if one was taken.
+    pub fn take(
+        &mut self,
+        contents: &[u8],
+        retention: usize,
+    ) -> Result<Option<String>, Box<dyn Error>> {
+        if retention == 0 {
+            return Ok(None);
+        }
+
+        if let Some(latest) = self.snapshots.last() {
+            if fs::read(self.path(latest))? == contents {
+                return Ok(None);
+            }
+        }
+
+        fs::create_dir_all(&self.dir)?;
+        let mut snapshot = Utc::now().format(TIMESTAMP_FORMAT).to_string();
+        if self.snapshots.last().is_some_and(|l| *l >= snapshot) {
+            // more than one snapshot in the same millisecond
+            snapshot = format!("{}-1", self.snapshots.last().unwrap());
+        }
+
+        write_atomic(&self.path(&snapshot), |f| Ok(f.write_all(contents)?))?;
+        self.snapshots.push(snapshot.clone());
+        self.prune(retention)?;
+
+        Ok(Some(snapshot))
+    }
+
+    /// Remove the oldest snapshots so that at most `retention` are kept.
+    pub fn prune(&mut self, retention: usize) -> Result<(), Box<dyn Error>> {
+        while self.snapshots.len() > retention {
+            let oldest = self.snapshots.remove(0);
+            fs::remove_file(self.path(&oldest))?;
+        }
+        Ok(())
+    }
+
+    /// Find a snapshot by name or index.
+    pub fn find(&self, input: &str) -> Result<String, Box<dyn Error>> {
+        match self.get_from_input(input) {
+            Some((_, snapshot)) => Ok(snapshot.clone()),
+            None => Err(format!("No snapshot {input} for {}", self.name).into()),
+        }
+    }
+
+    /// Read a snapshot, chosen by name or index.
+    /// Returns the name of the snapshot along with its contents.
+    pub fn read(&self, input: &str) -> Result<(String, Vec<u8>), Box<dyn Error>> {
+        let snapshot = self.find(input)?;
+        let contents = fs::read(self.path(&snapshot))?;
+        Ok((snapshot, contents))
+    }
+}
+
+impl Display for Snapshots {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let snapshots = self
+            .snapshots
+            .iter()
+            .enumerate()
+            .fold(String::new(), |acc, (i, s)| format!("{acc}- {i}: {s}\n"));
+
+        write!(f, "{}:\n{}", self.name, snapshots)
+    }
+}
+
+impl IndexSearch for Snapshots {
+    type Item = String;
+
+    fn get_collection(&self) -> &Vec<Self::Item> {
+        &self.snapshots
+    }
+
+    fn is_equal(&self, item: &Self::Item, input: &str) -> bool {
+        item == input
+    }
+}
+
+//
+//
+//
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+
+    fn set_up_snapshots(temp_dir: &TempDir) -> Snapshots {
+        Snapshots::from_dir("books", temp_dir.path().join("backups"), "csv").unwrap()
+    }
+
+    #[test]
+    fn test_take_snapshots() {
+        let temp_dir = TempDir::new("wych_book_tests").unwrap();
+        let mut snapshots = set_up_snapshots(&temp_dir);
+
+        assert!(snapshots.take(b"one", 2).unwrap().is_some());
+        // unchanged since the last snapshot
+        assert!(snapshots.take(b"one", 2).unwrap().is_none());
+
+        snapshots.take(b"two", 2).unwrap();
+        snapshots.take(b"three", 2).unwrap();
+
+        // oldest snapshot is removed
+        let snapshots = set_up_snapshots(&temp_dir);
+        assert_eq!(snapshots.snapshots().len(), 2);
+        let oldest = snapshots.path(&snapshots.snapshots()[0]);
+        assert_eq!(fs::read_to_string(oldest).unwrap(), "two");
+
+        // no snapshots taken when retention is zero
+        let mut snapshots = set_up_snapshots(&temp_dir);
+        assert!(snapshots.take(b"four", 0).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_read_snapshot() {
+        let temp_dir = TempDir::new("wych_book_tests").unwrap();
+        let mut snapshots = set_up_snapshots(&temp_dir);
+
+        let first = snapshots.take(b"one", 5).unwrap().unwrap();
+        let second = snapshots.take(b"two", 5).unwrap().unwrap();
+
+        assert_eq!(
+            snapshots.read("0").unwrap(),
+            (first.clone(), b"one".to_vec())
+        );
+        assert_eq!(snapshots.read(&second).unwrap(), (second, b"two".to_vec()));
+        assert_eq!(snapshots.find(&first).unwrap(), first);
+
+        assert!(snapshots.read("10").is_err());
+        assert!(snapshots.read("not-a-snapshot").is_err());
+    }
+}
@@ -0,0 +1,246 @@
+use super::import::{set_fields, shelf_name, Importer};
+use crate::books::{
+    book::{field, Book},
+    book_records::BookRecords,
+};
+use rusqlite::{Connection, OpenFlags, OptionalExtension};
+use std::{collections::BTreeMap, error::Error, io::Write, path::Path};
+use tempfile::NamedTempFile;
+
+/// The database file at the top of every Calibre library.
+pub const CALIBRE_DATABASE: &str = "metadata.db";
+
+/// Imports the books in a Calibre library, from its `metadata.db` or the library directory.
+/// Each book's tags are also its shelves, so mapping shelves to lists picks books by tag.
+pub struct Calibre;
+
+impl Calibre {
+    fn read(connection: &Connection) -> Result<BookRecords, Box<dyn Error>> {
+        let authors = Calibre::links(
+            connection,
+            "SELECT l.book, a.name FROM books_authors_link l
+             JOIN authors a ON a.id = l.author ORDER BY l.id",
+        )?;
+        let tags = Calibre::links(
+            connection,
+            "SELECT l.book, t.name FROM books_tags_link l
+             JOIN tags t ON t.id = l.tag ORDER BY t.name",
+        )?;
+        let series = Calibre::series(connection)?;
+        let isbns = Calibre::links(
+            connection,
+            "SELECT book, val FROM identifiers WHERE type = 'isbn'",
+        )?;
+        let pages = Calibre::pages(connection)?;
+
+        let mut statement =
+            connection.prepare("SELECT id, title, pubdate FROM books ORDER BY id")?;
+        let rows = statement.query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, Option<String>>(2)?,
+            ))
+        })?;
+
+        let mut books = Vec::new();
+        for row in rows {
+            let (id, title, pubdate) = row?;
+            let no_values = Vec::new();
+            let book_authors = authors.get(&id).unwrap_or(&no_values);
+            let book_tags = tags.get(&id).unwrap_or(&no_values);
+
+            let author = book_authors.first().cloned().unwrap_or_default();
+            let mut book = Book::new(author, title, 1);
+            let shelves: Vec<String> = book_tags.iter().map(|t| shelf_name(t)).collect();
+
+            set_fields(
+                &mut book,
+                [
+                    (
+                        field::ADDITIONAL_AUTHORS,
+                        book_authors.get(1..).map(|a| a.join(", ")),
+                    ),
+                    (field::ISBN, isbns.get(&id).and_then(|i| i.first().cloned())),
+                    (field::PAGES, pages.get(&id).cloned()),
+                    (field::PUBLISHED, pubdate.and_then(|d| publication_date(&d))),
+                    (field::SERIES, series.get(&id).cloned()),
+                    (field::SHELF, Some(shelves.join(", "))),
+                    (field::TAGS, Some(book_tags.join(", "))),
+                ],
+            );
+            books.push(book);
+        }
+
+        Ok(BookRecords::from(books))
+    }
+
+    /// Collect the values a query returns for each book id.
+    fn links(
+        connection: &Connection,
+        query: &str,
+    ) -> Result<BTreeMap<i64, Vec<String>>, Box<dyn Error>> {
+        let mut links: BTreeMap<i64, Vec<String>> = BTreeMap::new();
+        let mut statement = connection.prepare(query)?;
+        let rows = statement.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        for row in rows {
+            let (book, value) = row?;
+            links.entry(book).or_default().push(value);
+        }
+        Ok(links)
+    }
+
+    /// Each book's series and its place in it, such as `Discworld #8`.
+    fn series(connection: &Connection) -> Result<BTreeMap<i64, String>, Box<dyn Error>> {
+        let mut statement = connection.prepare(
+            "SELECT l.book, s.name, b.series_index FROM books_series_link l
+             JOIN series s ON s.id = l.series JOIN books b ON b.id = l.book",
+        )?;
+        let rows = statement.query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, f64>(2)?,
+            ))
+        })?;
+
+        let mut series = BTreeMap::new();
+        for row in rows {
+            let (book, name, index) = row?;
+            // f64 only prints a fraction when there is one, e.g. 8 or 8.5
+            series.insert(book, format!("{name} #{index}"));
+        }
+        Ok(series)
+    }
+
+    /// Page counts from the `#pages` custom column that the Count Pages plugin adds, if there is one.
+    fn pages(connection: &Connection) -> Result<BTreeMap<i64, String>, Box<dyn Error>> {
+        let column: Option<i64> = connection
+            .query_row(
+                "SELECT id FROM custom_columns WHERE label = 'pages'",
+                [],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        let Some(column) = column else {
+            return Ok(BTreeMap::new());
+        };
+        let query = format!("SELECT book, CAST(value AS TEXT) FROM custom_column_{column}");
+        let links = Calibre::links(connection, &query)?;
+        Ok(links
+            .into_iter()
+            .filter_map(|(book, values)| Some((book, values.into_iter().next()?)))
+            .collect())
+    }
+}
+
+/// The date part of a Calibre timestamp. Calibre stores an unknown date as the year 101.
+fn publication_date(timestamp: &str) -> Option<String> {
+    let date = timestamp.get(..10)?;
+    (!date.starts_with("0101")).then(|| date.to_string())
+}
+
+impl Importer for Calibre {
+    fn import(&self, contents: &[u8], _source: &Path) -> Result<BookRecords, Box<dyn Error>> {
+        // SQLite can only open a database from a file
+        let mut file = NamedTempFile::new()?;
+        file.write_all(contents)?;
+        file.flush()?;
+        self.import_file(file.path())
+    }
+
+    fn import_file(&self, filename: &Path) -> Result<BookRecords, Box<dyn Error>> {
+        let filename = if filename.is_dir() {
+            filename.join(CALIBRE_DATABASE)
+        } else {
+            filename.to_path_buf()
+        };
+
+        // read-only, so a library that Calibre has open is never changed
+        let connection = Connection::open_with_flags(
+            &filename,
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )
+        .map_err(|e| format!("Cannot open Calibre library {}, {e}", filename.display()))?;
+
+        Calibre::read(&connection)
+            .map_err(|e| format!("Cannot read Calibre library {}, {e}", filename.display()).into())
+    }
+}
+
+//
+//
+//
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+
+    // the parts of Calibre's schema that are imported
+    const LIBRARY: &str = "
+        CREATE TABLE books (id INTEGER PRIMARY KEY, title TEXT NOT NULL, pubdate TIMESTAMP, series_index REAL NOT NULL DEFAULT 1.0);
+        CREATE TABLE authors (id INTEGER PRIMARY KEY, name TEXT NOT NULL);
+        CREATE TABLE books_authors_link (id INTEGER PRIMARY KEY, book INTEGER NOT NULL, author INTEGER NOT NULL);
+        CREATE TABLE tags (id INTEGER PRIMARY KEY, name TEXT NOT NULL);
+        CREATE TABLE books_tags_link (id INTEGER PRIMARY KEY, book INTEGER NOT NULL, tag INTEGER NOT NULL);
+        CREATE TABLE series (id INTEGER PRIMARY KEY, name TEXT NOT NULL);
+        CREATE TABLE books_series_link (id INTEGER PRIMARY KEY, book INTEGER NOT NULL, series INTEGER NOT NULL);
+        CREATE TABLE identifiers (id INTEGER PRIMARY KEY, book INTEGER NOT NULL, type TEXT NOT NULL, val TEXT NOT NULL);
+        CREATE TABLE custom_columns (id INTEGER PRIMARY KEY, label TEXT NOT NULL, name TEXT NOT NULL);
+        CREATE TABLE custom_column_1 (id INTEGER PRIMARY KEY, book INTEGER, value INTEGER NOT NULL);
+
+        INSERT INTO books VALUES (1, 'Good Omens', '1990-05-01 00:00:00+00:00', 1.0);
+        INSERT INTO books VALUES (2, 'Guards! Guards!', '0101-01-01 00:00:00+00:00', 8.0);
+        INSERT INTO authors VALUES (1, 'Terry Pratchett'), (2, 'Neil Gaiman');
+        INSERT INTO books_authors_link VALUES (1, 1, 1), (2, 1, 2), (3, 2, 1);
+        INSERT INTO tags VALUES (1, 'To Read'), (2, 'Fantasy');
+        INSERT INTO books_tags_link VALUES (1, 2, 1), (2, 2, 2);
+        INSERT INTO series VALUES (1, 'Discworld');
+        INSERT INTO books_series_link VALUES (1, 2, 1);
+        INSERT INTO identifiers VALUES (1, 1, 'isbn', '9780060853983'), (2, 1, 'amazon', 'B000');
+        INSERT INTO custom_columns VALUES (1, 'pages', 'Pages');
+        INSERT INTO custom_column_1 VALUES (1, 2, 288);
+    ";
+
+    #[test]
+    fn test_calibre_import() {
+        let temp_dir = TempDir::new("wych_book_tests").unwrap();
+        let connection = Connection::open(temp_dir.path().join(CALIBRE_DATABASE)).unwrap();
+        connection.execute_batch(LIBRARY).unwrap();
+
+        let books = Calibre.import_file(temp_dir.path()).unwrap();
+        assert_eq!(books.records().len(), 2);
+
+        let good_omens = books.get(0).unwrap();
+        assert_eq!(good_omens.author, "Terry Pratchett");
+        assert_eq!(
+            good_omens.field(field::ADDITIONAL_AUTHORS),
+            Some("Neil Gaiman".into())
+        );
+        assert_eq!(good_omens.field(field::ISBN), Some("9780060853983".into()));
+        assert_eq!(
+            good_omens.field(field::PUBLISHED),
+            Some("1990-05-01".into())
+        );
+        assert_eq!(good_omens.field(field::TAGS), None);
+
+        let guards = books.get(1).unwrap();
+        assert_eq!(guards.field(field::ADDITIONAL_AUTHORS), None);
+        assert_eq!(guards.field(field::PUBLISHED), None);
+        assert_eq!(guards.field(field::SERIES), Some("Discworld #8".into()));
+        assert_eq!(guards.field(field::PAGES), Some("288".into()));
+        assert_eq!(guards.field(field::TAGS), Some("Fantasy, To Read".into()));
+        assert_eq!(guards.field(field::SHELF), Some("fantasy, to-read".into()));
+    }
+
+    #[test]
+    fn test_calibre_errors() {
+        let temp_dir = TempDir::new("wych_book_tests").unwrap();
+        let result = Calibre.import_file(temp_dir.path());
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("Cannot open Calibre library"));
+    }
+}
@@ -1,116 +1,538 @@
-use super::csv::{self, read_csv_file, write_csv_file};
-use crate::search::IndexSearch;
+#[cfg(feature = "sqlite")]
+use super::sqlite::SqliteStore;
+use super::{
+    archive::{self, Workspace, WorkspaceList},
+    backup::Snapshots,
+    csv::{read_csv_bytes, write_csv_bytes},
+    encryption::{Keyring, ListKey, Secret},
+    files::{self, write_atomic, DirectoryLock},
+    history::{read_history, write_history},
+    openlibrary::OPEN_LIBRARY_URL,
+    paths::WychPaths,
+    store::{CsvStore, ListStore},
+};
+use crate::{
+    books::{book_records::BookRecords, journal::Journal},
+    search::IndexSearch,
+};
 use serde::{Deserialize, Serialize};
 use std::{
+    collections::{BTreeMap, BTreeSet},
     error::Error,
     fmt::Display,
-    fs::File,
+    fs::{self, File},
     io::{Read, Write},
     path::Path,
 };
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct WychConfig {
     default_list: String,
     all_lists: Vec<String>,
+    /// Number of snapshots to keep of each list and of the config file.
+    #[serde(default = "default_backup_retention")]
+    backup_retention: usize,
+    #[serde(default)]
+    storage: Storage,
+    /// Server used to look up book details, if not Open Library itself.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    open_library_url: Option<String>,
+    /// How the key of each encrypted list is made.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    encrypted: BTreeMap<String, ListKey>,
+}
+
+/// Where book lists are kept.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Storage {
+    /// A CSV file per list, in the lists directory.
+    #[default]
+    Csv,
+    /// All lists in a single SQLite database. Requires the `sqlite` feature.
+    Sqlite,
 }
 
-const CONFIG_DIR: &str = "/.config/wych_book/";
-const CONFIG_FILE: &str = "config.json";
-const LISTS_DIR: &str = "lists/";
+const DEFAULT_BACKUP_RETENTION: usize = 10;
 
-pub fn get_config() -> Result<WychConfig, Box<dyn Error>> {
-    read_config(&config_file())
+fn default_backup_retention() -> usize {
+    DEFAULT_BACKUP_RETENTION
 }
 
-pub fn save_config(config: &mut WychConfig) -> Result<(), Box<dyn Error>> {
-    config.validate_config();
-    write_config(&config_file(), config)
+/// Read the config, or start with an empty one if the directory doesn't have one yet.
+pub fn get_config(paths: &WychPaths) -> Result<WychConfig, Box<dyn Error>> {
+    let filename = paths.config_file();
+    if !filename.try_exists()? {
+        return Ok(WychConfig::default());
+    }
+    read_config(&filename)
 }
 
-pub fn config_file() -> String {
-    let mut config = wych_directory();
-    config.push_str(CONFIG_FILE);
-    config
+pub fn save_config(
+    paths: &WychPaths,
+    config: &mut WychConfig,
+    store: &dyn ListStore,
+) -> Result<(), Box<dyn Error>> {
+    config.validate_config(store)?;
+    write_config(&paths.config_file(), config)
 }
 
-pub fn csv_file(name: &str) -> String {
-    let mut file = wych_directory();
-    file.push_str(LISTS_DIR);
-    file.push_str(name);
-    file.push_str(".csv");
-    file
+/// The store holding all book lists, as chosen in the config.
+pub fn list_store(
+    paths: &WychPaths,
+    config: &WychConfig,
+) -> Result<Box<dyn ListStore>, Box<dyn Error>> {
+    match config.storage {
+        Storage::Csv => {
+            let keyring = Keyring::new(config.encrypted.clone());
+            Ok(Box::new(csv_store(paths).with_keyring(keyring)))
+        }
+        #[cfg(feature = "sqlite")]
+        Storage::Sqlite => Ok(Box::new(SqliteStore::open(&paths.database_file())?)),
+        #[cfg(not(feature = "sqlite"))]
+        Storage::Sqlite => Err(
+            "Lists are stored in SQLite, but this build doesn't include the `sqlite` feature"
+                .into(),
+        ),
+    }
 }
 
-pub fn does_list_exist(name: &str) -> bool {
-    let filename = csv_file(name);
-    Path::new(&filename)
-        .try_exists()
-        .expect("Cannot check existence of {filename}")
+fn csv_store(paths: &WychPaths) -> CsvStore {
+    CsvStore::new(&paths.lists_dir(), &paths.journal_dir())
 }
 
-impl WychConfig {
-    pub fn default_csv(&self) -> String {
-        csv_file(&self.default_list)
+/// Copy every CSV list, with its journal, and the config file into the SQLite database, then switch to SQLite storage.
+/// Lists already in the database are left alone. The CSV files are kept, and are no longer used.
+/// Returns the names of the lists that were copied.
+#[cfg(feature = "sqlite")]
+pub fn migrate_to_sqlite(
+    paths: &WychPaths,
+    config: &mut WychConfig,
+) -> Result<Vec<String>, Box<dyn Error>> {
+    if config.storage == Storage::Sqlite {
+        return Err("Lists are already stored in SQLite".into());
+    }
+    if config.all_lists.iter().any(|l| config.is_encrypted(l)) {
+        return Err("Encrypted lists can't be stored in SQLite, decrypt them first".into());
+    }
+
+    let csv_store = csv_store(paths);
+    let mut sqlite_store = SqliteStore::open(&paths.database_file())?;
+    let mut migrated = Vec::new();
+
+    for name in csv_store.list()? {
+        if sqlite_store.exists(&name)? {
+            continue;
+        }
+        sqlite_store.save(&name, &csv_store.load(&name)?)?;
+        sqlite_store.save_journal(&name, &csv_store.load_journal(&name)?)?;
+        migrated.push(name);
     }
 
+    config.storage = Storage::Sqlite;
+    Ok(migrated)
+}
+
+/// Lock the lists directory for the duration of a command, so that concurrent commands can't overwrite each other's changes.
+pub fn lock_lists(paths: &WychPaths) -> Result<DirectoryLock, Box<dyn Error>> {
+    files::lock_directory(&paths.lists_dir())
+}
+
+impl WychConfig {
     pub fn get_default(&self) -> &str {
         &self.default_list
     }
 
-    pub fn set_default(&mut self, input: &str) -> Result<(), Box<dyn Error>> {
+    pub fn set_default(
+        &mut self,
+        store: &dyn ListStore,
+        input: &str,
+    ) -> Result<(), Box<dyn Error>> {
         let new_default = if let Some((_, list)) = self.get_from_input(input) {
             list
         } else {
             return Ok(());
         };
 
-        if !does_list_exist(new_default) {
+        if !store.exists(new_default)? {
             return Err("Provided list does not exist".into());
         }
         self.default_list = new_default.to_string();
         Ok(())
     }
 
-    pub fn print_lists(&self) {
-        println!("{self}");
+    pub fn storage(&self) -> Storage {
+        self.storage
     }
 
-    pub fn add_new_empty_list(&mut self, name: &str) -> Result<(), Box<dyn Error>> {
-        if does_list_exist(name) {
-            println!("List already exists");
+    pub fn open_library_url(&self) -> &str {
+        self.open_library_url.as_deref().unwrap_or(OPEN_LIBRARY_URL)
+    }
+
+    /// Use another server to look up book details, or Open Library itself if None.
+    pub fn set_open_library_url(&mut self, url: Option<String>) {
+        self.open_library_url = url;
+    }
+
+    pub fn backup_retention(&self) -> usize {
+        self.backup_retention
+    }
+
+    /// Set the number of snapshots to keep. Zero turns off backups.
+    pub fn set_backup_retention(&mut self, retention: usize) {
+        self.backup_retention = retention;
+    }
+
+    /// Take a snapshot of a list, if it exists and backups are turned on.
+    pub fn backup_list(
+        &self,
+        paths: &WychPaths,
+        store: &dyn ListStore,
+        name: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        if self.backup_retention == 0 || !store.exists(name)? {
+            return Ok(());
+        }
+
+        let contents = write_csv_bytes(&store.load(name)?)?;
+        let mut snapshots = Snapshots::for_list(paths, name)?;
+        if let Some(latest) = snapshots.snapshots().last() {
+            // encrypted snapshots differ every time, so compare what they hold
+            if unseal(store, name, fs::read(snapshots.path(latest))?)? == contents {
+                return Ok(());
+            }
+        }
+        snapshots.take(&seal(store, name, contents)?, self.backup_retention)?;
+        Ok(())
+    }
+
+    /// Take a snapshot of the config file, if it exists and backups are turned on.
+    pub fn backup_config(&self, paths: &WychPaths) -> Result<(), Box<dyn Error>> {
+        let filename = paths.config_file();
+        if self.backup_retention == 0 || !filename.try_exists()? {
             return Ok(());
         }
 
-        let filename = csv_file(name);
-        let result = csv::create_blank_file(&filename);
+        Snapshots::for_config(paths)?.take(&fs::read(filename)?, self.backup_retention)?;
+        Ok(())
+    }
 
-        if result.is_ok() {
+    /// Replace a list with one of its snapshots, chosen by name or index.
+    /// The current list is backed up first, if it can be read, so the restore can itself be undone.
+    pub fn restore_list(
+        &mut self,
+        paths: &WychPaths,
+        store: &mut dyn ListStore,
+        name: &str,
+        snapshot: &str,
+    ) -> Result<String, Box<dyn Error>> {
+        let mut snapshots = Snapshots::for_list(paths, name)?;
+        let (restored, contents) = snapshots.read(snapshot)?;
+        let contents = unseal(store, name, contents)?;
+        let books = read_csv_bytes(&contents, &snapshots.path(&restored))?;
+
+        if let Ok(current) = store.load(name) {
+            let contents = seal(store, name, write_csv_bytes(&current)?)?;
+            snapshots.take(&contents, self.backup_retention)?;
+        }
+        store.save(name, &books)?;
+        store.save_journal(name, &Journal::default())?;
+
+        if !self.all_lists.iter().any(|l| l == name) {
             self.all_lists.push(name.to_string());
+        }
+        Ok(restored)
+    }
+
+    /// Replace the config with one of its snapshots, chosen by name or index.
+    /// The current config is backed up first.
+    pub fn restore_config(
+        &mut self,
+        paths: &WychPaths,
+        snapshot: &str,
+    ) -> Result<String, Box<dyn Error>> {
+        let snapshots = Snapshots::for_config(paths)?;
+        let restored = snapshots.find(snapshot)?;
+        let restored_config = read_config(&snapshots.path(&restored))?;
+
+        self.backup_config(paths)?;
+        *self = restored_config;
+        Ok(restored)
+    }
+
+    /// Everything in the wych_book directory, to be written to an archive.
+    pub fn workspace(
+        &self,
+        paths: &WychPaths,
+        store: &dyn ListStore,
+    ) -> Result<Workspace, Box<dyn Error>> {
+        let mut lists = Vec::new();
+        for name in &self.all_lists {
+            lists.push(WorkspaceList {
+                name: name.clone(),
+                books: store.load(name)?,
+                journal: store.load_journal(name)?,
+            });
+        }
 
-            if self.default_list.is_empty() {
-                self.set_default(name)?;
+        Ok(Workspace {
+            config: self.clone(),
+            lists,
+            history: read_history(&paths.history_file())?,
+            backups: archive::read_files(&paths.backups_dir())?,
+        })
+    }
+
+    /// Bring in the lists, reading history and backups of an archived workspace.
+    /// Replacing deletes any list that isn't in the archive, and takes the archive's settings
+    /// apart from where lists are stored. Merging adds each list's new books, and any new lists,
+    /// and keeps the current settings. Returns the names of the lists that were changed.
+    /// Lists encrypted in the archive stay encrypted with the same keys.
+    pub fn import_workspace(
+        &mut self,
+        paths: &WychPaths,
+        store: &mut dyn ListStore,
+        mut workspace: Workspace,
+        replace: bool,
+    ) -> Result<Vec<String>, Box<dyn Error>> {
+        let archived = &workspace.config;
+        if store.keyring().is_none() && archived.all_lists.iter().any(|l| archived.is_encrypted(l))
+        {
+            return Err("The archive has encrypted lists, which can't be stored in SQLite".into());
+        }
+        let mut history = read_history(&paths.history_file())?;
+        let mut changed = Vec::new();
+
+        if replace {
+            for name in store.list()? {
+                if !workspace.lists.iter().any(|l| l.name == name) {
+                    store.delete(&name)?;
+                }
+            }
+            let keys = self.replace_keys(paths, store, &workspace)?;
+            let storage = self.storage;
+            *self = workspace.config;
+            self.storage = storage;
+            self.encrypted = keys;
+            history = workspace.history;
+        } else {
+            let skipped = self.merge_keys(paths, store, &workspace)?;
+            workspace
+                .backups
+                .retain(|(path, _)| Snapshots::list_of(path).is_none_or(|l| !skipped.contains(l)));
+            history.merge(workspace.history);
+        }
+
+        for list in workspace.lists {
+            if !replace && store.exists(&list.name)? {
+                let mut books = store.load(&list.name)?;
+                let changes = books.merge(list.books);
+                if changes.is_empty() {
+                    continue;
+                }
+                let mut journal = store.load_journal(&list.name)?;
+                changes.into_iter().for_each(|c| journal.record(c));
+                store.save(&list.name, &books)?;
+                store.save_journal(&list.name, &journal)?;
+            } else {
+                store.save(&list.name, &list.books)?;
+                store.save_journal(&list.name, &list.journal)?;
+            }
+
+            if !self.all_lists.contains(&list.name) {
+                self.all_lists.push(list.name.clone());
+            }
+            changed.push(list.name);
+        }
+
+        write_history(&paths.history_file(), &history)?;
+        archive::write_files(&paths.backups_dir(), &workspace.backups, replace)?;
+        if self.backup_retention > 0 {
+            let mut all_snapshots = Snapshots::for_all_lists(paths)?;
+            all_snapshots.push(Snapshots::for_config(paths)?);
+            for mut snapshots in all_snapshots {
+                snapshots.prune(self.backup_retention)?;
+            }
+        }
+        Ok(changed)
+    }
+
+    pub fn is_encrypted(&self, name: &str) -> bool {
+        self.encrypted.contains_key(name)
+    }
+
+    /// Take the keys of an archive that is replacing the workspace. The backups of lists whose key
+    /// changes are encrypted again with the new key, and lists kept only in their backups,
+    /// which the archive doesn't have, keep their keys. Returns the keys to keep.
+    fn replace_keys(
+        &self,
+        paths: &WychPaths,
+        store: &mut dyn ListStore,
+        workspace: &Workspace,
+    ) -> Result<BTreeMap<String, ListKey>, Box<dyn Error>> {
+        let archived = &workspace.config;
+        let mut keys = archived.encrypted.clone();
+        let mut backups = Vec::new();
+        for snapshots in Snapshots::for_all_lists(paths)? {
+            let name = snapshots.name();
+            let in_archive =
+                workspace.lists.iter().any(|l| l.name == name) || archived.is_encrypted(name);
+            match self.encrypted.get(name) {
+                Some(key) if !in_archive => {
+                    keys.insert(name.to_string(), key.clone());
+                }
+                key if key != archived.encrypted.get(name) => {
+                    for snapshot in snapshots.snapshots() {
+                        let path = snapshots.path(snapshot);
+                        let contents = unseal(store, name, fs::read(&path)?)?;
+                        backups.push((name.to_string(), path, contents));
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        if let Some(keyring) = store.keyring_mut() {
+            *keyring = Keyring::new(keys.clone());
+        }
+        for (name, path, contents) in backups {
+            let contents = seal(store, &name, contents)?;
+            write_atomic(&path, |file| Ok(file.write_all(&contents)?))?;
+        }
+        Ok(keys)
+    }
+
+    /// Take the keys of lists in an archive that are new to the workspace.
+    /// Returns the lists whose backups in the archive have a different key to the workspace's,
+    /// and so can't be added to its backups.
+    fn merge_keys(
+        &mut self,
+        paths: &WychPaths,
+        store: &mut dyn ListStore,
+        workspace: &Workspace,
+    ) -> Result<BTreeSet<String>, Box<dyn Error>> {
+        let archived = &workspace.config;
+        let mut names: BTreeSet<&str> = workspace.lists.iter().map(|l| l.name.as_str()).collect();
+        names.extend(archived.encrypted.keys().map(String::as_str));
+        names.extend(
+            workspace
+                .backups
+                .iter()
+                .filter_map(|(p, _)| Snapshots::list_of(p)),
+        );
+
+        let mut skipped = BTreeSet::new();
+        for name in names {
+            let key = archived.encrypted.get(name);
+            let is_new = !store.exists(name)?
+                && !self.is_encrypted(name)
+                && Snapshots::for_list(paths, name)?.snapshots().is_empty();
+            if is_new {
+                if let (Some(key), Some(keyring)) = (key, store.keyring_mut()) {
+                    keyring.add(name, key.clone());
+                    self.encrypted.insert(name.to_string(), key.clone());
+                }
+            } else if key != self.encrypted.get(name) {
+                skipped.insert(name.to_string());
             }
         }
-        result
+        Ok(skipped)
+    }
+
+    /// Encrypt a list, chosen by name or index, along with its journal and backups.
+    /// Only lists kept as CSV files can be encrypted. Returns the name of the list.
+    pub fn encrypt_list(
+        &mut self,
+        paths: &WychPaths,
+        store: &mut dyn ListStore,
+        input: &str,
+        secret: &Secret,
+    ) -> Result<String, Box<dyn Error>> {
+        let name = match self.get_from_input(input) {
+            Some((_, name)) => name.clone(),
+            None => return Err("Cannot encrypt a non-existent list".into()),
+        };
+        if self.is_encrypted(&name) {
+            return Err(format!("{name} is already encrypted").into());
+        }
+        if store.keyring().is_none() {
+            return Err("Only lists stored as CSV files can be encrypted".into());
+        }
+
+        let (key, cipher) = ListKey::new(secret)?;
+        reseal(paths, store, &name, |keyring| {
+            keyring.insert(&name, key.clone(), cipher)
+        })?;
+        self.encrypted.insert(name.clone(), key);
+        Ok(name)
+    }
+
+    /// Decrypt a list, chosen by name or index, along with its journal and backups.
+    /// Returns the name of the list.
+    pub fn decrypt_list(
+        &mut self,
+        paths: &WychPaths,
+        store: &mut dyn ListStore,
+        input: &str,
+    ) -> Result<String, Box<dyn Error>> {
+        let name = match self.get_from_input(input) {
+            Some((_, name)) if self.is_encrypted(name) => name.clone(),
+            Some((_, name)) => return Err(format!("{name} is not encrypted").into()),
+            None => return Err("Cannot decrypt a non-existent list".into()),
+        };
+
+        reseal(paths, store, &name, |keyring| keyring.remove(&name))?;
+        self.encrypted.remove(&name);
+        Ok(name)
+    }
+
+    pub fn print_lists(&self) {
+        println!("{self}");
+    }
+
+    pub fn add_new_empty_list(
+        &mut self,
+        store: &mut dyn ListStore,
+        name: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        if store.exists(name)? {
+            println!("List already exists");
+            return Ok(());
+        }
+
+        store.save(name, &BookRecords::default())?;
+        self.all_lists.push(name.to_string());
+
+        if self.default_list.is_empty() {
+            self.set_default(store, name)?;
+        }
+        Ok(())
     }
 
-    pub fn copy_csv_list(
+    pub fn copy_list(
         &mut self,
+        store: &mut dyn ListStore,
         from: &str,
         to: &str,
         overwrite: bool,
     ) -> Result<(), Box<dyn Error>> {
-        if !does_list_exist(from) {
+        if !store.exists(from)? {
             return Err("Cannot copy a non-existent list".into());
         }
-        if does_list_exist(to) && !overwrite {
+        if store.exists(to)? && !overwrite {
             println!("List {to} already exists, use -o to overwrite.");
             return Ok(());
         }
 
-        let from_list = read_csv_file(&csv_file(from))?;
-        write_csv_file(&csv_file(to), &from_list)?;
+        let from_list = store.load(from)?;
+        if let Some(key) = self.encrypted.get(from).cloned() {
+            if let Some(keyring) = store.keyring_mut() {
+                keyring.copy(from, to)?;
+            }
+            self.encrypted.insert(to.to_string(), key);
+        }
+        store.save(to, &from_list)?;
+        store.save_journal(to, &Journal::default())?;
 
         let list_name = to.to_string();
         if !self.all_lists.contains(&list_name) {
@@ -120,8 +542,50 @@ impl WychConfig {
         Ok(())
     }
 
-    pub fn delete_list(&mut self, input: &str) -> Result<(), Box<dyn Error>> {
-        self.validate_config();
+    /// Rename a list, chosen by name or index. Its history of changes and backups move with it.
+    pub fn rename_list(
+        &mut self,
+        paths: &WychPaths,
+        store: &mut dyn ListStore,
+        input: &str,
+        to: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        let (index, name) = if let Some((index, list_name)) = self.get_from_input(input) {
+            (index, list_name.clone())
+        } else {
+            return Err("Cannot rename a non-existent list".into());
+        };
+
+        // an encrypted list's files are bound to its name, so they're encrypted again under the new one
+        let files = match self.is_encrypted(&name) {
+            true => Some(ListFiles::read(paths, store, &name)?),
+            false => None,
+        };
+        store.rename(&name, to)?;
+        Snapshots::rename_list(paths, &name, to)?;
+        if let Some(files) = files {
+            files.write(paths, store, to)?;
+        }
+
+        if let Some(key) = self.encrypted.remove(&name) {
+            self.encrypted.insert(to.to_string(), key);
+        }
+        self.all_lists[index] = to.to_string();
+        if self.default_list == name {
+            self.default_list = to.to_string();
+        }
+        Ok(())
+    }
+
+    /// Delete a list, chosen by name or index. An encrypted list keeps its key while it has backups,
+    /// so that it can still be restored.
+    pub fn delete_list(
+        &mut self,
+        paths: &WychPaths,
+        store: &mut dyn ListStore,
+        input: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        self.validate_config(store)?;
 
         let (index, name) = if let Some((index, list_name)) = self.get_from_input(input) {
             (index, list_name)
@@ -133,32 +597,53 @@ impl WychConfig {
             return Err("Cannot delete default list".into());
         }
 
-        let filename = csv_file(name);
-        std::fs::remove_file(filename)?;
-        self.all_lists.remove(index);
+        store.delete(name)?;
+        let name = self.all_lists.remove(index);
+        if Snapshots::for_list(paths, &name)?.snapshots().is_empty() {
+            self.encrypted.remove(&name);
+            if let Some(keyring) = store.keyring_mut() {
+                keyring.remove(&name);
+            }
+        }
         Ok(())
     }
 
     /// Check if all the lists in the config file actually exist and remove any that don't.
-    fn validate_config(&mut self) {
-        let existent_lists: Vec<String> = self
-            .all_lists
-            .iter()
-            .filter(|l| does_list_exist(l))
-            .map(|l| l.to_string())
-            .collect();
+    fn validate_config(&mut self, store: &dyn ListStore) -> Result<(), Box<dyn Error>> {
+        let mut existent_lists = Vec::new();
+        for list in &self.all_lists {
+            if store.exists(list)? {
+                existent_lists.push(list.to_string());
+            }
+        }
 
         if self.all_lists.len() != existent_lists.len() {
             self.all_lists = existent_lists;
         }
+        let all_lists = &self.all_lists;
+        self.encrypted.retain(|name, _| all_lists.contains(name));
 
-        if !does_list_exist(&self.default_list) {
+        if !store.exists(&self.default_list)? {
             self.default_list = if self.all_lists.is_empty() {
                 String::new()
             } else {
                 self.all_lists[0].clone()
             }
         }
+        Ok(())
+    }
+}
+
+impl Default for WychConfig {
+    fn default() -> Self {
+        WychConfig {
+            default_list: String::new(),
+            all_lists: Vec::new(),
+            backup_retention: DEFAULT_BACKUP_RETENTION,
+            storage: Storage::default(),
+            open_library_url: None,
+            encrypted: BTreeMap::new(),
+        }
     }
 }
 
@@ -168,12 +653,17 @@ impl Display for WychConfig {
             .all_lists
             .iter()
             .enumerate()
-            .fold(String::new(), |acc, (i, l)| format!("{acc}- {i}: {l}\n"));
+            .fold(String::new(), |acc, (i, l)| {
+                match self.encrypted.contains_key(l) {
+                    true => format!("{acc}- {i}: {l} (encrypted)\n"),
+                    false => format!("{acc}- {i}: {l}\n"),
+                }
+            });
 
         write!(
             f,
-            "Default List: {}\nAll Lists:\n{}",
-            self.default_list, lists
+            "Default List: {}\nBackups Kept: {}\nAll Lists:\n{}",
+            self.default_list, self.backup_retention, lists
         )
     }
 }
@@ -190,17 +680,82 @@ impl IndexSearch for WychConfig {
     }
 }
 
-fn wych_directory() -> String {
-    let home = home::home_dir().expect("You really should set your home directory");
-    let mut home = home.to_str().expect("Should be valid str").to_string();
-    home.push_str(CONFIG_DIR);
-    home
+/// A list's backup as kept on disk, encrypted if the list is.
+fn seal(store: &dyn ListStore, name: &str, contents: Vec<u8>) -> Result<Vec<u8>, Box<dyn Error>> {
+    match store.keyring() {
+        Some(keyring) => keyring.seal(name, contents),
+        None => Ok(contents),
+    }
+}
+
+fn unseal(store: &dyn ListStore, name: &str, contents: Vec<u8>) -> Result<Vec<u8>, Box<dyn Error>> {
+    match store.keyring() {
+        Some(keyring) => keyring.unseal(name, contents),
+        None => Ok(contents),
+    }
+}
+
+/// Rewrite a list, its journal and its backups after changing how the list is encrypted.
+fn reseal(
+    paths: &WychPaths,
+    store: &mut dyn ListStore,
+    name: &str,
+    change: impl FnOnce(&mut Keyring),
+) -> Result<(), Box<dyn Error>> {
+    let files = ListFiles::read(paths, store, name)?;
+    match store.keyring_mut() {
+        Some(keyring) => change(keyring),
+        None => return Err("Only lists stored as CSV files can be encrypted".into()),
+    }
+    files.write(paths, store, name)
+}
+
+/// A list, its journal and its backups, decrypted.
+struct ListFiles {
+    books: BookRecords,
+    journal: Journal,
+    backups: Vec<(String, Vec<u8>)>,
+}
+
+impl ListFiles {
+    fn read(paths: &WychPaths, store: &dyn ListStore, name: &str) -> Result<Self, Box<dyn Error>> {
+        let snapshots = Snapshots::for_list(paths, name)?;
+        let mut backups = Vec::new();
+        for snapshot in snapshots.snapshots() {
+            let contents = fs::read(snapshots.path(snapshot))?;
+            backups.push((snapshot.clone(), unseal(store, name, contents)?));
+        }
+        Ok(ListFiles {
+            books: store.load(name)?,
+            journal: store.load_journal(name)?,
+            backups,
+        })
+    }
+
+    /// Write the files as a list's, encrypted as the list is, over the ones it has.
+    fn write(
+        self,
+        paths: &WychPaths,
+        store: &mut dyn ListStore,
+        name: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        store.save(name, &self.books)?;
+        store.save_journal(name, &self.journal)?;
+        let snapshots = Snapshots::for_list(paths, name)?;
+        for (snapshot, contents) in self.backups {
+            let contents = seal(store, name, contents)?;
+            write_atomic(&snapshots.path(&snapshot), |file| {
+                Ok(file.write_all(&contents)?)
+            })?;
+        }
+        Ok(())
+    }
 }
 
-fn read_config(filename: &str) -> Result<WychConfig, Box<dyn Error>> {
+fn read_config(filename: &Path) -> Result<WychConfig, Box<dyn Error>> {
     let file = File::open(filename);
     if let Err(e) = file {
-        return Err(format!("Cannot open: {filename}, {e}").into());
+        return Err(format!("Cannot open: {}, {e}", filename.display()).into());
     };
 
     let mut json = String::new();
@@ -210,11 +765,9 @@ fn read_config(filename: &str) -> Result<WychConfig, Box<dyn Error>> {
     Ok(deserialized)
 }
 
-fn write_config(filename: &str, config: &WychConfig) -> Result<(), Box<dyn Error>> {
-    let mut file = File::create(filename)?;
+fn write_config(filename: &Path, config: &WychConfig) -> Result<(), Box<dyn Error>> {
     let serialized = serde_json::to_string(config)?;
-    write!(file, "{serialized}")?;
-    Ok(())
+    write_atomic(filename, |file| Ok(write!(file, "{serialized}")?))
 }
 
 //
@@ -223,29 +776,31 @@ fn write_config(filename: &str, config: &WychConfig) -> Result<(), Box<dyn Error
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::fs;
-    use serial_test::serial;
+    use crate::{
+        books::book::Book,
+        io::{encryption, store::MemoryStore},
+    };
     use tempdir::TempDir;
 
-    fn set_up_home_dir() -> TempDir {
+    fn set_up_paths() -> (TempDir, WychPaths) {
         let temp_dir = TempDir::new("wych_book_tests").unwrap();
-        let lists_dir = temp_dir.path().join(".config/wych_book/lists/");
-        fs::create_dir_all(lists_dir).unwrap();
+        let paths = WychPaths::new(temp_dir.path());
+        fs::create_dir_all(paths.lists_dir()).unwrap();
 
-        std::env::set_var("HOME", temp_dir.path().to_str().unwrap());
-
-        temp_dir
+        (temp_dir, paths)
     }
 
     #[test]
     fn test_read_config() {
         let filename = concat!(env!("CARGO_MANIFEST_DIR"), "/resources/test/config.json");
-        let result = read_config(filename).unwrap();
+        let result = read_config(Path::new(filename)).unwrap();
         assert_eq!(result.default_list, "books".to_string());
         assert_eq!(
             result.all_lists,
             vec!["books".to_string(), "books2".into(), "books3".into()]
         );
+        assert_eq!(result.backup_retention, DEFAULT_BACKUP_RETENTION);
+        assert_eq!(result.storage, Storage::Csv);
     }
 
     #[test]
@@ -254,124 +809,507 @@ mod tests {
             env!("CARGO_MANIFEST_DIR"),
             "/resources/test/unknown_config.json"
         );
-        assert!(read_config(&filename).is_err());
+        assert!(read_config(Path::new(filename)).is_err());
 
         let filename = concat!(env!("CARGO_MANIFEST_DIR"), "/resources/test/books.csv");
-        assert!(read_config(&filename).is_err());
+        assert!(read_config(Path::new(filename)).is_err());
     }
 
     #[test]
     fn test_write_config() {
         let temp_dir = TempDir::new("wych_book_tests").unwrap();
-        let file_path = temp_dir.path().join("new_config.json");
-        let filename = file_path.to_str().unwrap();
+        let filename = temp_dir.path().join("new_config.json");
 
         let config = WychConfig {
             default_list: "BOOKS".into(),
             all_lists: vec!["BOOKS".to_string()],
+            ..Default::default()
         };
 
-        let result = write_config(filename, &config);
+        let result = write_config(&filename, &config);
         assert!(result.is_ok());
 
-        let read_result = read_config(filename).unwrap();
+        let read_result = read_config(&filename).unwrap();
         assert_eq!(read_result.all_lists, config.all_lists);
         assert_eq!(read_result.default_list, config.default_list);
     }
 
     #[test]
-    #[serial]
+    fn test_get_config() {
+        let (_temp_dir, paths) = set_up_paths();
+        let mut store = csv_store(&paths);
+
+        // a new directory starts with an empty config
+        let mut config = get_config(&paths).unwrap();
+        assert!(config.all_lists.is_empty());
+
+        config.add_new_empty_list(&mut store, "books").unwrap();
+        save_config(&paths, &mut config, &store).unwrap();
+        assert_eq!(get_config(&paths).unwrap().get_default(), "books");
+    }
+
+    #[test]
     fn test_create_new_list() {
         let list_name = String::from("books");
         let mut config = WychConfig {
             default_list: list_name.clone(),
             all_lists: vec![list_name.clone()],
+            ..Default::default()
         };
 
-        let _temp_dir = set_up_home_dir();
+        let (_temp_dir, paths) = set_up_paths();
+        let mut store = csv_store(&paths);
 
         let new_list = "new_list";
-        assert!(config.add_new_empty_list(&new_list).is_ok());
-        assert!(does_list_exist(&new_list));
+        assert!(config.add_new_empty_list(&mut store, new_list).is_ok());
+        assert!(store.exists(new_list).unwrap());
         assert!(config.all_lists.contains(&new_list.to_string()));
 
         // try create again
-        assert!(config.add_new_empty_list(&new_list).is_ok());
+        assert!(config.add_new_empty_list(&mut store, new_list).is_ok());
     }
 
     #[test]
-    #[serial]
     fn test_validate_config() {
-        let _temp_dir = set_up_home_dir();
+        let (_temp_dir, paths) = set_up_paths();
+        let mut store = csv_store(&paths);
 
         let list_name = String::from("books");
         let mut config = WychConfig {
             default_list: String::new(),
             all_lists: vec![String::from("does_not_exist")],
+            ..Default::default()
         };
 
-        let _ = config.add_new_empty_list(&list_name);
-        config.validate_config();
+        let _ = config.add_new_empty_list(&mut store, &list_name);
+        config.validate_config(&store).unwrap();
         assert!(config.all_lists.len() == 1);
         assert_eq!(config.default_list, list_name);
     }
 
     #[test]
-    #[serial]
-    fn test_copy_csv_list() {
-        let _temp_dir = set_up_home_dir();
-        let mut config = WychConfig {
-            default_list: String::new(),
-            all_lists: Vec::new(),
-        };
+    fn test_copy_list() {
+        let (_temp_dir, paths) = set_up_paths();
+        let mut store = csv_store(&paths);
+        let mut config = WychConfig::default();
 
         let name = "books";
-        let _ = config.add_new_empty_list(name);
+        let _ = config.add_new_empty_list(&mut store, name);
         assert_eq!(name.to_string(), config.default_list);
 
         let name2 = "books2";
-        let result = config.copy_csv_list(name, name2, false);
+        let result = config.copy_list(&mut store, name, name2, false);
         assert!(result.is_ok());
         assert_eq!(config.all_lists, vec![name.to_string(), name2.to_string()]);
 
         // from list doesn't exist
-        let result = config.copy_csv_list("non-existent", name2, false);
+        let result = config.copy_list(&mut store, "non-existent", name2, false);
         assert!(result.is_err());
 
         // already exists and overwrite is false
-        let result = config.copy_csv_list(name, name2, false);
+        let result = config.copy_list(&mut store, name, name2, false);
         assert!(result.is_ok());
         assert_eq!(config.all_lists.len(), 2);
 
         // already exists and overwrite is true
-        let result = config.copy_csv_list(name, name2, true);
+        let result = config.copy_list(&mut store, name, name2, true);
         assert!(result.is_ok());
         assert_eq!(config.all_lists.len(), 2);
     }
 
     #[test]
-    #[serial]
+    fn test_rename_list() {
+        let (_temp_dir, paths) = set_up_paths();
+        let mut store = MemoryStore::default();
+        let mut config = WychConfig::default();
+        config.add_new_empty_list(&mut store, "books").unwrap();
+        config.add_new_empty_list(&mut store, "books2").unwrap();
+        config.backup_list(&paths, &store, "books").unwrap();
+
+        assert!(config
+            .rename_list(&paths, &mut store, "blah", "books3")
+            .is_err());
+        assert!(config
+            .rename_list(&paths, &mut store, "books", "books2")
+            .is_err());
+
+        config
+            .rename_list(&paths, &mut store, "0", "renamed")
+            .unwrap();
+        assert_eq!(config.all_lists, vec!["renamed", "books2"]);
+        assert_eq!(config.default_list, "renamed");
+        assert!(store.exists("renamed").unwrap());
+        assert!(!store.exists("books").unwrap());
+
+        // backups move with the list
+        let snapshots = Snapshots::for_list(&paths, "renamed").unwrap();
+        assert_eq!(snapshots.snapshots().len(), 1);
+        assert!(Snapshots::for_list(&paths, "books")
+            .unwrap()
+            .snapshots()
+            .is_empty());
+        config
+            .restore_list(&paths, &mut store, "renamed", "0")
+            .unwrap();
+    }
+
+    #[test]
     fn test_delete_list() {
-        let _temp_dir = set_up_home_dir();
-        let mut config = WychConfig {
-            default_list: String::new(),
-            all_lists: Vec::new(),
-        };
+        let (_temp_dir, paths) = set_up_paths();
+        let mut store = csv_store(&paths);
+        let mut config = WychConfig::default();
         let name = "books";
-        let _ = config.add_new_empty_list(name);
+        let _ = config.add_new_empty_list(&mut store, name);
         let name2 = "books2";
-        let _ = config.add_new_empty_list(name2);
+        let _ = config.add_new_empty_list(&mut store, name2);
 
         // List does not exist
-        assert!(config.delete_list("blah").is_err());
+        assert!(config.delete_list(&paths, &mut store, "blah").is_err());
 
         // can't delete default list
-        assert!(config.delete_list(name).is_err());
+        assert!(config.delete_list(&paths, &mut store, name).is_err());
 
         // happy path delete
         assert_eq!(config.all_lists.len(), 2);
-        assert!(config.delete_list(name2).is_ok());
-        assert_eq!(does_list_exist(name2), false);
+        assert!(config.delete_list(&paths, &mut store, name2).is_ok());
+        assert!(!store.exists(name2).unwrap());
         assert_eq!(config.all_lists.len(), 1);
     }
+
+    #[test]
+    fn test_backup_and_restore_list() {
+        let (_temp_dir, paths) = set_up_paths();
+        let mut store = MemoryStore::default();
+        let mut config = WychConfig::default();
+        let name = "books";
+        config.add_new_empty_list(&mut store, name).unwrap();
+        config.backup_list(&paths, &store, name).unwrap();
+
+        let books = vec![Book::new("Franz Kafka".into(), "The Castle".into(), 3)];
+        store.save(name, &books.into()).unwrap();
+        config.backup_list(&paths, &store, name).unwrap();
+
+        // restoring the empty list also backs up the current one
+        let books = vec![Book::new(
+            "Yoko Ogawa".into(),
+            "The Memory Police".into(),
+            1,
+        )];
+        store.save(name, &books.into()).unwrap();
+        config.restore_list(&paths, &mut store, name, "0").unwrap();
+        assert!(store.load(name).unwrap().records().is_empty());
+        assert_eq!(
+            Snapshots::for_list(&paths, name).unwrap().snapshots().len(),
+            3
+        );
+
+        // a deleted list can be restored
+        config.add_new_empty_list(&mut store, "books2").unwrap();
+        config.backup_list(&paths, &store, "books2").unwrap();
+        config.delete_list(&paths, &mut store, "books2").unwrap();
+        config
+            .restore_list(&paths, &mut store, "books2", "0")
+            .unwrap();
+        assert!(store.exists("books2").unwrap());
+        assert!(config.all_lists.contains(&"books2".to_string()));
+    }
+
+    #[test]
+    fn test_import_workspace() {
+        let (_temp_dir, paths) = set_up_paths();
+        let castle = Book::new("Franz Kafka".into(), "The Castle".into(), 3);
+        let trial = Book::new("Franz Kafka".into(), "The Trial".into(), 1);
+
+        let mut store = MemoryStore::default();
+        let mut config = WychConfig::default();
+        config.add_new_empty_list(&mut store, "books").unwrap();
+        config.add_new_empty_list(&mut store, "books2").unwrap();
+        store.save("books", &vec![castle.clone()].into()).unwrap();
+        config.backup_list(&paths, &store, "books").unwrap();
+        let workspace = config.workspace(&paths, &store).unwrap();
+        assert_eq!(workspace.lists.len(), 2);
+        assert_eq!(workspace.backups.len(), 1);
+
+        // merging adds new books and lists, and keeps the current settings
+        let mut other_store = MemoryStore::default();
+        let mut other = WychConfig::default();
+        other.add_new_empty_list(&mut other_store, "mine").unwrap();
+        other_store.save("mine", &vec![trial].into()).unwrap();
+        other.add_new_empty_list(&mut other_store, "books").unwrap();
+        other_store.save("books", &vec![castle].into()).unwrap();
+
+        let changed = other
+            .import_workspace(
+                &paths,
+                &mut other_store,
+                config.workspace(&paths, &store).unwrap(),
+                false,
+            )
+            .unwrap();
+        assert_eq!(changed, vec!["books2"]);
+        assert_eq!(other.all_lists, vec!["mine", "books", "books2"]);
+        assert_eq!(other.get_default(), "mine");
+
+        // replacing removes lists that aren't in the archive
+        let changed = other
+            .import_workspace(&paths, &mut other_store, workspace, true)
+            .unwrap();
+        assert_eq!(changed, vec!["books", "books2"]);
+        assert_eq!(other.all_lists, vec!["books", "books2"]);
+        assert_eq!(other.get_default(), "books");
+        assert!(!other_store.exists("mine").unwrap());
+        assert_eq!(
+            other_store.load("books").unwrap(),
+            store.load("books").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_import_workspace_prunes_backups() {
+        let (_temp_dir, paths) = set_up_paths();
+        let mut store = MemoryStore::default();
+        let mut config = WychConfig::default();
+        config.add_new_empty_list(&mut store, "books").unwrap();
+        for title in ["The Castle", "The Trial", "Amerika"] {
+            let book = Book::new("Franz Kafka".into(), title.into(), 1);
+            store.save("books", &vec![book].into()).unwrap();
+            config.backup_list(&paths, &store, "books").unwrap();
+        }
+        let mut workspace = config.workspace(&paths, &store).unwrap();
+        workspace.config.set_backup_retention(2);
+
+        // replacing keeps as many backups as the archive's settings do
+        let (_other_dir, other_paths) = set_up_paths();
+        let mut other_store = MemoryStore::default();
+        let mut other = WychConfig::default();
+        other
+            .import_workspace(&other_paths, &mut other_store, workspace, true)
+            .unwrap();
+        let snapshots = Snapshots::for_list(&other_paths, "books").unwrap();
+        assert_eq!(snapshots.snapshots().len(), 2);
+
+        // merging keeps as many as the current settings do
+        other.set_backup_retention(1);
+        other
+            .import_workspace(
+                &other_paths,
+                &mut other_store,
+                config.workspace(&paths, &store).unwrap(),
+                false,
+            )
+            .unwrap();
+        let snapshots = Snapshots::for_list(&other_paths, "books").unwrap();
+        assert_eq!(snapshots.snapshots().len(), 1);
+    }
+
+    #[test]
+    fn test_encrypt_and_decrypt_list() {
+        let (_temp_dir, paths) = set_up_paths();
+        let mut config = WychConfig::default();
+        let mut store = list_store(&paths, &config).unwrap();
+        let books: BookRecords =
+            vec![Book::new("Franz Kafka".into(), "The Castle".into(), 3)].into();
+        config.add_new_empty_list(&mut *store, "books").unwrap();
+        config.backup_list(&paths, &*store, "books").unwrap();
+        store.save("books", &books).unwrap();
+
+        let secret = Secret::Passphrase("correct horse".into());
+        assert!(config
+            .encrypt_list(&paths, &mut *store, "missing", &secret)
+            .is_err());
+        config
+            .encrypt_list(&paths, &mut *store, "0", &secret)
+            .unwrap();
+        assert!(config.is_encrypted("books"));
+        assert!(config.to_string().contains("books (encrypted)"));
+        assert!(config
+            .encrypt_list(&paths, &mut *store, "books", &secret)
+            .is_err());
+
+        // the list and its backups are encrypted on disk, but read as before
+        let csv_store = csv_store(&paths);
+        assert!(csv_store.load("books").is_err());
+        let snapshots = Snapshots::for_list(&paths, "books").unwrap();
+        let snapshot = fs::read(snapshots.path(&snapshots.snapshots()[0])).unwrap();
+        assert!(encryption::is_encrypted(&snapshot));
+        assert_eq!(store.load("books").unwrap(), books);
+
+        // taking a backup of an unchanged list is skipped, even though it's encrypted
+        config.backup_list(&paths, &*store, "books").unwrap();
+        config.backup_list(&paths, &*store, "books").unwrap();
+        assert_eq!(
+            Snapshots::for_list(&paths, "books")
+                .unwrap()
+                .snapshots()
+                .len(),
+            2
+        );
+        config
+            .restore_list(&paths, &mut *store, "books", "0")
+            .unwrap();
+        assert!(store.load("books").unwrap().records().is_empty());
+
+        // copies and renamed lists stay encrypted
+        config
+            .copy_list(&mut *store, "books", "copy", false)
+            .unwrap();
+        config
+            .rename_list(&paths, &mut *store, "copy", "moved")
+            .unwrap();
+        assert!(config.is_encrypted("moved"));
+        assert!(csv_store.load("moved").is_err());
+        assert!(store.load("moved").unwrap().records().is_empty());
+
+        // a renamed list's files are bound to its new name, backups included
+        config
+            .rename_list(&paths, &mut *store, "books", "renamed")
+            .unwrap();
+        config
+            .restore_list(&paths, &mut *store, "renamed", "0")
+            .unwrap();
+        config
+            .rename_list(&paths, &mut *store, "renamed", "books")
+            .unwrap();
+        assert!(store.load_journal("books").is_ok());
+
+        // a deleted list keeps its key while its backups need it
+        config.backup_list(&paths, &*store, "moved").unwrap();
+        config.delete_list(&paths, &mut *store, "moved").unwrap();
+        assert!(config.is_encrypted("moved"));
+        config
+            .restore_list(&paths, &mut *store, "moved", "0")
+            .unwrap();
+        assert!(csv_store.load("moved").is_err());
+        assert!(store.load("moved").unwrap().records().is_empty());
+
+        config.decrypt_list(&paths, &mut *store, "books").unwrap();
+        assert!(!config.is_encrypted("books"));
+        assert!(csv_store.load("books").unwrap().records().is_empty());
+        assert!(config.decrypt_list(&paths, &mut *store, "books").is_err());
+    }
+
+    #[test]
+    fn test_import_encrypted_workspace() {
+        let (temp_dir, paths) = set_up_paths();
+        let key_file = temp_dir.path().join("wych.key");
+        fs::write(&key_file, "correct horse").unwrap();
+        let books: BookRecords =
+            vec![Book::new("Franz Kafka".into(), "The Castle".into(), 3)].into();
+        let mut config = WychConfig::default();
+        let mut store = list_store(&paths, &config).unwrap();
+        config.add_new_empty_list(&mut *store, "books").unwrap();
+        store.save("books", &books).unwrap();
+        config
+            .encrypt_list(&paths, &mut *store, "books", &Secret::KeyFile(key_file))
+            .unwrap();
+        config.backup_list(&paths, &*store, "books").unwrap();
+
+        // replacing and merging into a new list both keep it encrypted, with its backups
+        for replace in [true, false] {
+            let (_other_dir, other_paths) = set_up_paths();
+            let mut other = WychConfig::default();
+            let mut other_store = list_store(&other_paths, &other).unwrap();
+            other
+                .import_workspace(
+                    &other_paths,
+                    &mut *other_store,
+                    config.workspace(&paths, &*store).unwrap(),
+                    replace,
+                )
+                .unwrap();
+            assert!(other.is_encrypted("books"));
+            assert!(csv_store(&other_paths).load("books").is_err());
+            assert_eq!(other_store.load("books").unwrap(), books);
+            other
+                .restore_list(&other_paths, &mut *other_store, "books", "0")
+                .unwrap();
+            assert_eq!(other_store.load("books").unwrap(), books);
+        }
+
+        // replacing a list with another key encrypts its backups again with the archive's key
+        let (other_dir, other_paths) = set_up_paths();
+        let other_key = other_dir.path().join("other.key");
+        fs::write(&other_key, "battery staple").unwrap();
+        let mut other = WychConfig::default();
+        let mut other_store = list_store(&other_paths, &other).unwrap();
+        other
+            .add_new_empty_list(&mut *other_store, "books")
+            .unwrap();
+        other
+            .encrypt_list(
+                &other_paths,
+                &mut *other_store,
+                "books",
+                &Secret::KeyFile(other_key),
+            )
+            .unwrap();
+        other
+            .backup_list(&other_paths, &*other_store, "books")
+            .unwrap();
+        other
+            .import_workspace(
+                &other_paths,
+                &mut *other_store,
+                config.workspace(&paths, &*store).unwrap(),
+                true,
+            )
+            .unwrap();
+        assert_eq!(other.encrypted, config.encrypted);
+        let snapshots = Snapshots::for_list(&other_paths, "books").unwrap();
+        assert_eq!(snapshots.snapshots().len(), 2);
+        for snapshot in snapshots.snapshots() {
+            let contents = fs::read(snapshots.path(snapshot)).unwrap();
+            assert!(unseal(&*other_store, "books", contents).is_ok());
+        }
+
+        // merging into a list with another key leaves out the archive's backups of it
+        let (_other_dir, other_paths) = set_up_paths();
+        let mut other = WychConfig::default();
+        let mut other_store = list_store(&other_paths, &other).unwrap();
+        other
+            .add_new_empty_list(&mut *other_store, "books")
+            .unwrap();
+        other
+            .backup_list(&other_paths, &*other_store, "books")
+            .unwrap();
+        other
+            .import_workspace(
+                &other_paths,
+                &mut *other_store,
+                config.workspace(&paths, &*store).unwrap(),
+                false,
+            )
+            .unwrap();
+        assert!(!other.is_encrypted("books"));
+        assert_eq!(other_store.load("books").unwrap(), books);
+        let snapshots = Snapshots::for_list(&other_paths, "books").unwrap();
+        assert_eq!(snapshots.snapshots().len(), 1);
+        other
+            .restore_list(&other_paths, &mut *other_store, "books", "0")
+            .unwrap();
+        assert!(other_store.load("books").unwrap().records().is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "sqlite")]
+    fn test_migrate_to_sqlite() {
+        let (_temp_dir, paths) = set_up_paths();
+        let mut csv_store = csv_store(&paths);
+        let mut config = WychConfig::default();
+
+        let books = vec![Book::new("Franz Kafka".into(), "The Castle".into(), 3)].into();
+        config.add_new_empty_list(&mut csv_store, "books").unwrap();
+        csv_store.save("books", &books).unwrap();
+        config.add_new_empty_list(&mut csv_store, "books2").unwrap();
+        save_config(&paths, &mut config, &csv_store).unwrap();
+
+        let migrated = migrate_to_sqlite(&paths, &mut config).unwrap();
+        assert_eq!(migrated, vec!["books", "books2"]);
+        assert_eq!(config.storage, Storage::Sqlite);
+        assert!(migrate_to_sqlite(&paths, &mut config).is_err());
+
+        let store = list_store(&paths, &config).unwrap();
+        assert_eq!(store.list().unwrap(), vec!["books", "books2"]);
+        assert_eq!(store.load("books").unwrap(), books);
+    }
 }
@@ -1,47 +1,230 @@
 use csv;
 
+use super::files::write_atomic;
 use crate::books::{
     book::{Book, Header},
     book_records::BookRecords,
 };
-use std::{error::Error, fs::File};
+use std::{
+    error::Error,
+    fmt::Display,
+    fs::File,
+    io::{Read, Write},
+    path::{Path, PathBuf},
+};
+
+/// Name of the field holding a book's user-defined columns, which can't also be a column.
+const RESERVED_COLUMN: &str = "custom";
+
+/// A row of a CSV file which could not be read as a `Book`.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct RowWarning {
+    pub file: PathBuf,
+    pub line: Option<u64>,
+    pub column: Option<String>,
+    pub message: String,
+}
+
+impl RowWarning {
+    pub(crate) fn new(filename: &Path, headers: &csv::StringRecord, error: &csv::Error) -> Self {
+        let line = error.position().map(|p| p.line());
+
+        let (column, message) = match error.kind() {
+            csv::ErrorKind::Deserialize { err, .. } => {
+                let column = err
+                    .field()
+                    .and_then(|i| headers.get(i as usize))
+                    .map(|h| h.to_string());
+                (column, err.kind().to_string())
+            }
+            _ => (None, error.to_string()),
+        };
+
+        RowWarning {
+            file: filename.to_path_buf(),
+            line,
+            column,
+            message,
+        }
+    }
+}
+
+impl Display for RowWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.file.display())?;
+        if let Some(line) = self.line {
+            write!(f, ", line {line}")?;
+        }
+        if let Some(column) = &self.column {
+            write!(f, ", column '{column}'")?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+/// The lines of skipped rows, such as `lines 3, 7`, to point the user at what to fix.
+pub fn skipped_lines(warnings: &[RowWarning]) -> String {
+    let lines: Vec<String> = warnings
+        .iter()
+        .map(|w| w.line.map_or("?".to_string(), |l| l.to_string()))
+        .collect();
+    match lines.len() {
+        1 => format!("line {}", lines[0]),
+        _ => format!("lines {}", lines.join(", ")),
+    }
+}
+
+/// Read a list of books, matching fields to columns by header name.
+/// Returns an error describing the first row that cannot be read.
+pub fn read_csv_file(filename: &Path) -> Result<BookRecords, Box<dyn Error>> {
+    let (book_records, warnings) = read_books(filename, false)?;
+
+    if let Some(warning) = warnings.first() {
+        return Err(warning.to_string().into());
+    }
+    Ok(book_records)
+}
+
+/// Read a list of books, skipping any rows that cannot be read.
+/// Returns the books that were read, along with a warning for each skipped row.
+pub fn read_csv_file_lenient(
+    filename: &Path,
+) -> Result<(BookRecords, Vec<RowWarning>), Box<dyn Error>> {
+    read_books(filename, true)
+}
+
+/// Read a list of books from CSV contents held in memory.
+/// `source` is only used to describe where any bad rows came from.
+pub fn read_csv_bytes(contents: &[u8], source: &Path) -> Result<BookRecords, Box<dyn Error>> {
+    let (book_records, warnings) = read_books_from(contents, source, false)?;
 
-pub fn read_csv_file(filename: &str) -> Result<BookRecords, Box<dyn Error>> {
+    if let Some(warning) = warnings.first() {
+        return Err(warning.to_string().into());
+    }
+    Ok(book_records)
+}
+
+/// Read a list of books from CSV contents held in memory, skipping any rows that cannot be read.
+pub fn read_csv_bytes_lenient(
+    contents: &[u8],
+    source: &Path,
+) -> Result<(BookRecords, Vec<RowWarning>), Box<dyn Error>> {
+    read_books_from(contents, source, true)
+}
+
+/// Write a list of books as CSV contents held in memory.
+pub fn write_csv_bytes(books: &BookRecords) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut contents = Vec::new();
+    write_books(&mut contents, books)?;
+    Ok(contents)
+}
+
+fn read_books(
+    filename: &Path,
+    lenient: bool,
+) -> Result<(BookRecords, Vec<RowWarning>), Box<dyn Error>> {
     let file = File::open(filename);
     if let Err(e) = file {
-        return Err(format!("Cannot open {filename}, {e}").into());
+        return Err(format!("Cannot open {}, {e}", filename.display()).into());
     };
 
-    let mut reader = csv::Reader::from_reader(file.unwrap());
+    read_books_from(file.unwrap(), filename, lenient)
+}
+
+fn read_books_from<R: Read>(
+    source: R,
+    filename: &Path,
+    lenient: bool,
+) -> Result<(BookRecords, Vec<RowWarning>), Box<dyn Error>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::Headers)
+        .from_reader(source);
+    let headers = reader.headers()?.clone();
+    if headers.iter().any(|h| h == RESERVED_COLUMN) {
+        return Err(format!("{}: {}", filename.display(), reserved_column_error()).into());
+    }
+
     let mut book_records = BookRecords::default();
+    let mut warnings = Vec::new();
+
+    // Any column that isn't a standard header is kept as a user-defined column
+    let custom_columns: Vec<(usize, String)> = headers
+        .iter()
+        .enumerate()
+        .filter(|(_, h)| !Header::headers().contains(h))
+        .map(|(i, h)| (i, h.to_string()))
+        .collect();
+    custom_columns
+        .iter()
+        .for_each(|(_, c)| book_records.add_custom_column(c));
+    book_records.set_column_order(headers.iter().map(str::to_string).collect());
 
     for result in reader.records() {
-        let record = result?;
+        let book = result.and_then(|record| {
+            // only standard fields are trimmed, user-defined values are kept as written
+            let mut standard: csv::StringRecord = record
+                .iter()
+                .enumerate()
+                .map(|(i, v)| match custom_columns.iter().any(|(c, _)| *c == i) {
+                    true => v,
+                    false => v.trim(),
+                })
+                .collect();
+            standard.set_position(record.position().cloned());
 
-        let author = record[0].to_string();
-        let title = record[1].to_string();
-        let weight: u8 = record[2].parse()?;
+            let mut book: Book = standard.deserialize(Some(&headers))?;
+            for (i, column) in &custom_columns {
+                let value = record.get(*i).unwrap_or_default();
+                if !value.is_empty() {
+                    book.custom.insert(column.clone(), value.to_string());
+                }
+            }
+            Ok(book)
+        });
 
-        book_records.push(Book::new(author, title, weight))
+        match book {
+            Ok(book) => book_records.push(book),
+            Err(e) => {
+                warnings.push(RowWarning::new(filename, &headers, &e));
+                if !lenient {
+                    break;
+                }
+            }
+        }
     }
 
-    Ok(book_records)
+    Ok((book_records, warnings))
+}
+
+pub fn write_csv_file(filename: &Path, books: &BookRecords) -> Result<(), Box<dyn Error>> {
+    write_atomic(filename, |file| write_books(file, books))
 }
 
-pub fn write_csv_file(filename: &str, books: &BookRecords) -> Result<(), Box<dyn Error>> {
-    let mut writer = csv::Writer::from_path(filename)?;
+fn write_books<W: Write>(file: W, books: &BookRecords) -> Result<(), Box<dyn Error>> {
+    let mut writer = csv::Writer::from_writer(file);
 
-    writer.write_record(Header::headers())?;
+    let columns = books.columns();
+    if columns.iter().any(|c| c == RESERVED_COLUMN) {
+        return Err(reserved_column_error().into());
+    }
+    writer.write_record(&columns)?;
 
     for record in books.records() {
-        writer.write_record(&record.as_string_array())?;
+        let row = columns.iter().map(|c| record.field(c).unwrap_or_default());
+        writer.write_record(row)?;
     }
 
     writer.flush()?;
     Ok(())
 }
 
-pub fn create_blank_file(filename: &str) -> Result<(), Box<dyn Error>> {
+fn reserved_column_error() -> String {
+    format!(
+        "A column can't be called '{RESERVED_COLUMN}', as the name is reserved, please rename it"
+    )
+}
+
+pub fn create_blank_file(filename: &Path) -> Result<(), Box<dyn Error>> {
     let empty = BookRecords::default();
     write_csv_file(filename, &empty)
 }
@@ -57,7 +240,7 @@ mod tests {
     #[test]
     fn test_reading_csv_file() {
         let filename = concat!(env!("CARGO_MANIFEST_DIR"), "/resources/test/books.csv");
-        let result = read_csv_file(&filename).unwrap();
+        let result = read_csv_file(Path::new(filename)).unwrap();
         assert_eq!(5, result.records().len());
 
         let record = Book::new(
@@ -73,31 +256,153 @@ mod tests {
     #[test]
     fn test_csv_file_errors() {
         let filename = concat!(env!("CARGO_MANIFEST_DIR"), "/resources/test/bad_file.csv");
-        assert!(read_csv_file(&filename).is_err());
+        assert!(read_csv_file(Path::new(filename)).is_err());
 
         let temp_dir = TempDir::new("wych_book_tests").unwrap();
-        let file_path = temp_dir.path().join("new_books.csv");
-        let filename = file_path.to_str().unwrap();
+        let filename = temp_dir.path().join("new_books.csv");
 
-        let mut writer = csv::Writer::from_path(filename).unwrap();
+        let mut writer = csv::Writer::from_path(&filename).unwrap();
         writer.write_record(vec!["bad", "data"]).unwrap();
 
-        let result = read_csv_file(filename).unwrap();
+        let result = read_csv_file(&filename).unwrap();
         assert!(result.records().is_empty());
     }
 
     #[test]
     fn test_write_csv_file() {
         let temp_dir = TempDir::new("wych_book_tests").unwrap();
-        let file_path = temp_dir.path().join("new_books.csv");
-        let filename = file_path.to_str().unwrap();
+        let filename = temp_dir.path().join("new_books.csv");
 
         let records = vec![Book::new("Franz Kakfa".into(), "The Castle".into(), 1)].into();
-        let result = write_csv_file(filename, &records);
+        let result = write_csv_file(&filename, &records);
         assert!(result.is_ok());
 
-        let read_result = read_csv_file(filename).unwrap();
+        let read_result = read_csv_file(&filename).unwrap();
         assert_eq!(read_result.records().len(), 1);
         assert_eq!(read_result.get(0), records.get(0));
     }
+
+    #[test]
+    fn test_csv_bytes() {
+        let records: BookRecords =
+            vec![Book::new("Franz Kakfa".into(), "The Castle".into(), 1)].into();
+        let contents = write_csv_bytes(&records).unwrap();
+        assert_eq!(
+            String::from_utf8(contents.clone()).unwrap(),
+            "author,title,weight\nFranz Kakfa,The Castle,1\n"
+        );
+        assert_eq!(
+            read_csv_bytes(&contents, Path::new("books")).unwrap(),
+            records
+        );
+    }
+
+    fn write_test_file(dir: &TempDir, contents: &str) -> PathBuf {
+        let filename = dir.path().join("books.csv");
+        std::fs::write(&filename, contents).unwrap();
+        filename
+    }
+
+    #[test]
+    fn test_reading_reordered_columns() {
+        let temp_dir = TempDir::new("wych_book_tests").unwrap();
+        let filename = write_test_file(
+            &temp_dir,
+            "weight, title, author\n3, The Castle, Franz Kafka\n",
+        );
+
+        let result = read_csv_file(&filename).unwrap();
+        let record = Book::new("Franz Kafka".into(), "The Castle".into(), 3);
+        assert_eq!(record, *result.get(0).unwrap());
+    }
+
+    #[test]
+    fn test_bad_row_diagnostics() {
+        let temp_dir = TempDir::new("wych_book_tests").unwrap();
+        let filename = write_test_file(
+            &temp_dir,
+            "author,title,weight\nFranz Kafka,The Castle,1\nYoko Ogawa,The Memory Police,heavy\n",
+        );
+
+        let error = read_csv_file(&filename).unwrap_err().to_string();
+        assert!(error.contains("books.csv"));
+        assert!(error.contains("line 3"));
+        assert!(error.contains("column 'weight'"));
+
+        let (result, warnings) = read_csv_file_lenient(&filename).unwrap();
+        assert_eq!(result.records().len(), 1);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].line, Some(3));
+        assert_eq!(warnings[0].column, Some("weight".to_string()));
+    }
+
+    #[test]
+    fn test_custom_columns_round_trip() {
+        let temp_dir = TempDir::new("wych_book_tests").unwrap();
+        let contents = "recommended_by,author,title,weight,notes\n\
+            Sam,Franz Kafka,The Castle,2,Unfinished novel\n\
+            ,Yoko Ogawa,The Memory Police,1,\n";
+        let filename = write_test_file(&temp_dir, contents);
+
+        let result = read_csv_file(&filename).unwrap();
+        assert_eq!(result.custom_columns(), &vec!["recommended_by", "notes"]);
+        let book = result.get(0).unwrap();
+        assert_eq!(book.custom.get("recommended_by").unwrap(), "Sam");
+        assert_eq!(book.custom.get("notes").unwrap(), "Unfinished novel");
+
+        write_csv_file(&filename, &result).unwrap();
+        let read_result = read_csv_file(&filename).unwrap();
+        assert_eq!(read_result, result);
+
+        let written = std::fs::read_to_string(&filename).unwrap();
+        assert!(written.starts_with("recommended_by,author,title,weight,notes\n"));
+
+        // new columns go at the end
+        let mut books = read_result;
+        books.add_custom_column("shelf");
+        let written = String::from_utf8(write_csv_bytes(&books).unwrap()).unwrap();
+        assert!(written.starts_with("recommended_by,author,title,weight,notes,shelf\n"));
+    }
+
+    #[test]
+    fn test_reserved_column() {
+        let contents = b"author,title,weight,custom\nFranz Kafka,The Castle,1,x\n";
+        let error = read_csv_bytes(contents, Path::new("books.csv")).unwrap_err();
+        assert!(error.to_string().contains("'custom'"));
+
+        let mut books = BookRecords::default();
+        books.add_custom_column("custom");
+        assert!(write_csv_bytes(&books).is_err());
+    }
+
+    #[test]
+    fn test_custom_values_keep_spaces() {
+        let temp_dir = TempDir::new("wych_book_tests").unwrap();
+        let filename = write_test_file(
+            &temp_dir,
+            "author, title, weight, notes
+ Franz Kafka , The Castle, 2,  indented
+",
+        );
+
+        let result = read_csv_file(&filename).unwrap();
+        let book = result.get(0).unwrap();
+        assert_eq!(book.author, "Franz Kafka");
+        assert_eq!(book.weight, 2);
+        assert_eq!(book.custom.get("notes").unwrap(), "  indented");
+    }
+
+    #[test]
+    fn test_empty_custom_values() {
+        let contents = b"author,title,weight,notes\nYoko Ogawa,The Memory Police,1,\n";
+        let result = read_csv_bytes(contents, Path::new("books.csv")).unwrap();
+
+        // an empty cell is the same as no value, as in the other stores
+        let book = result.get(0).unwrap();
+        assert!(book.custom.is_empty());
+        assert_eq!(result.custom_columns(), &vec!["notes"]);
+
+        let written = write_csv_bytes(&result).unwrap();
+        assert_eq!(written, contents);
+    }
 }
@@ -0,0 +1,365 @@
+use argon2::Argon2;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    error::Error,
+    fmt::Debug,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Start of every encrypted file, naming the version of the format.
+const MAGIC: &[u8] = b"WYCHENC1";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+/// Contents encrypted when a key is made, to check a passphrase before using it.
+const CHECK: &[u8] = b"wych_book";
+
+/// Environment variable holding the passphrase of encrypted lists, instead of asking for it.
+pub const PASSPHRASE_VAR: &str = "WYCH_BOOK_PASSPHRASE";
+
+/// What an encrypted list is unlocked with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Secret {
+    Passphrase(String),
+    /// A file whose contents are the secret, such as one kept on a USB stick.
+    KeyFile(PathBuf),
+}
+
+impl Secret {
+    fn bytes(&self) -> Result<Vec<u8>, Box<dyn Error>> {
+        match self {
+            Secret::Passphrase(passphrase) => Ok(passphrase.as_bytes().to_vec()),
+            Secret::KeyFile(path) => {
+                let key = fs::read(path)
+                    .map_err(|e| format!("Cannot read key file {}, {e}", path.display()))?;
+                if key.is_empty() {
+                    return Err(format!("Key file {} is empty", path.display()).into());
+                }
+                Ok(key)
+            }
+        }
+    }
+}
+
+/// How the key of an encrypted list is made, kept in the config. Holds no secrets.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ListKey {
+    /// Base64 salt the key is derived with.
+    salt: String,
+    /// Base64 `CHECK` encrypted with the key.
+    check: String,
+    /// Key file the list is unlocked with, or None for a passphrase.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    key_file: Option<PathBuf>,
+}
+
+impl ListKey {
+    /// Make a new key from a secret. Returns the key's description, and the cipher it unlocks.
+    pub fn new(secret: &Secret) -> Result<(Self, Cipher), Box<dyn Error>> {
+        let salt: [u8; SALT_LEN] = rand::random();
+        let cipher = Cipher::derive(&secret.bytes()?, &salt)?;
+        let key = ListKey {
+            salt: STANDARD.encode(salt),
+            check: STANDARD.encode(cipher.encrypt(CHECK, &[])?),
+            // kept as a full path, so it's found whichever directory wych is run from
+            key_file: match secret {
+                Secret::KeyFile(path) => Some(path.canonicalize()?),
+                Secret::Passphrase(_) => None,
+            },
+        };
+        Ok((key, cipher))
+    }
+
+    pub fn key_file(&self) -> Option<&Path> {
+        self.key_file.as_deref()
+    }
+
+    /// The cipher a secret unlocks. Fails if it's the wrong secret.
+    pub fn cipher(&self, secret: &Secret) -> Result<Cipher, Box<dyn Error>> {
+        let salt = STANDARD.decode(&self.salt)?;
+        let cipher = Cipher::derive(&secret.bytes()?, &salt)?;
+        match cipher.decrypt(&STANDARD.decode(&self.check)?, &[]) {
+            Ok(check) if check == CHECK => Ok(cipher),
+            _ => Err(match secret {
+                Secret::Passphrase(_) => "wrong passphrase",
+                Secret::KeyFile(_) => "wrong key file",
+            }
+            .into()),
+        }
+    }
+
+    /// Unlock a list with its key file, or with the passphrase in `PASSPHRASE_VAR`,
+    /// or by asking for the passphrase.
+    pub fn unlock(&self, name: &str) -> Result<Cipher, Box<dyn Error>> {
+        let secret = match (&self.key_file, std::env::var(PASSPHRASE_VAR)) {
+            (Some(path), _) => Secret::KeyFile(path.clone()),
+            (None, Ok(passphrase)) => Secret::Passphrase(passphrase),
+            (None, Err(_)) => Secret::Passphrase(prompt_passphrase(name)?),
+        };
+        self.cipher(&secret)
+            .map_err(|e| format!("Cannot unlock {name}, {e}").into())
+    }
+}
+
+/// Ask for a list's passphrase without showing it.
+pub fn prompt_passphrase(name: &str) -> Result<String, Box<dyn Error>> {
+    rpassword::prompt_password(format!("Passphrase for {name}: ")).map_err(|e| {
+        format!("Cannot ask for the passphrase of {name}, set {PASSPHRASE_VAR} instead, {e}").into()
+    })
+}
+
+/// Encrypts and decrypts the files of a list, with ChaCha20-Poly1305
+/// and a key derived from its secret by Argon2.
+#[derive(Clone)]
+pub struct Cipher {
+    cipher: ChaCha20Poly1305,
+}
+
+impl Cipher {
+    fn derive(secret: &[u8], salt: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let mut key = [0u8; 32];
+        Argon2::default()
+            .hash_password_into(secret, salt, &mut key)
+            .map_err(|e| format!("Cannot derive key, {e}"))?;
+        Ok(Cipher {
+            cipher: ChaCha20Poly1305::new(Key::from_slice(&key)),
+        })
+    }
+
+    /// Encrypt contents with a new random nonce, which is kept at the start of the result.
+    /// `associated` isn't encrypted, but the contents can only be decrypted along with it.
+    pub fn encrypt(&self, contents: &[u8], associated: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+        let nonce: [u8; NONCE_LEN] = rand::random();
+        let payload = Payload {
+            msg: contents,
+            aad: associated,
+        };
+        let encrypted = self
+            .cipher
+            .encrypt(Nonce::from_slice(&nonce), payload)
+            .map_err(|e| format!("Cannot encrypt, {e}"))?;
+        Ok([&nonce[..], &encrypted].concat())
+    }
+
+    pub fn decrypt(&self, contents: &[u8], associated: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+        if contents.len() < NONCE_LEN {
+            return Err("Encrypted contents are too short".into());
+        }
+        let (nonce, encrypted) = contents.split_at(NONCE_LEN);
+        let payload = Payload {
+            msg: encrypted,
+            aad: associated,
+        };
+        self.cipher
+            .decrypt(Nonce::from_slice(nonce), payload)
+            .map_err(|_| {
+                "Cannot decrypt, the contents are damaged, the key is wrong or they belong elsewhere"
+                    .into()
+            })
+    }
+}
+
+impl Debug for Cipher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Cipher")
+    }
+}
+
+/// Whether file contents were written encrypted by a `Keyring`.
+pub fn is_encrypted(contents: &[u8]) -> bool {
+    contents.starts_with(MAGIC)
+}
+
+/// The keys of every encrypted list, each unlocked the first time it's needed.
+#[derive(Debug, Clone, Default)]
+pub struct Keyring {
+    keys: BTreeMap<String, ListKey>,
+    unlocked: RefCell<BTreeMap<String, Cipher>>,
+}
+
+impl Keyring {
+    pub fn new(keys: BTreeMap<String, ListKey>) -> Self {
+        Keyring {
+            keys,
+            unlocked: RefCell::default(),
+        }
+    }
+
+    pub fn is_encrypted(&self, name: &str) -> bool {
+        self.keys.contains_key(name)
+    }
+
+    /// Encrypt a list from now on, with an unlocked key.
+    pub fn insert(&mut self, name: &str, key: ListKey, cipher: Cipher) {
+        self.keys.insert(name.to_string(), key);
+        self.unlocked.get_mut().insert(name.to_string(), cipher);
+    }
+
+    /// Encrypt a list from now on, with a key that is unlocked when it's first needed.
+    pub fn add(&mut self, name: &str, key: ListKey) {
+        self.keys.insert(name.to_string(), key);
+        self.unlocked.get_mut().remove(name);
+    }
+
+    /// Stop encrypting a list.
+    pub fn remove(&mut self, name: &str) {
+        self.keys.remove(name);
+        self.unlocked.get_mut().remove(name);
+    }
+
+    pub fn rename(&mut self, from: &str, to: &str) {
+        if let Some(key) = self.keys.remove(from) {
+            self.keys.insert(to.to_string(), key);
+        }
+        if let Some(cipher) = self.unlocked.get_mut().remove(from) {
+            self.unlocked.get_mut().insert(to.to_string(), cipher);
+        }
+    }
+
+    /// Encrypt another list with the same key as `from`.
+    pub fn copy(&mut self, from: &str, to: &str) -> Result<(), Box<dyn Error>> {
+        let Some(key) = self.keys.get(from).cloned() else {
+            return Ok(());
+        };
+        let cipher = self.cipher(from)?;
+        self.insert(to, key, cipher);
+        Ok(())
+    }
+
+    fn cipher(&self, name: &str) -> Result<Cipher, Box<dyn Error>> {
+        if let Some(cipher) = self.unlocked.borrow().get(name) {
+            return Ok(cipher.clone());
+        }
+        let key = self
+            .keys
+            .get(name)
+            .ok_or_else(|| format!("{name} is encrypted, but has no key in the config"))?;
+        let cipher = key.unlock(name)?;
+        self.unlocked
+            .borrow_mut()
+            .insert(name.to_string(), cipher.clone());
+        Ok(cipher)
+    }
+
+    /// The contents of one of a list's files as they should be kept on disk,
+    /// which is encrypted if the list is. The list's name is bound to the contents,
+    /// so they can't be passed off as another list's file that shares the key.
+    pub fn seal(&self, name: &str, contents: Vec<u8>) -> Result<Vec<u8>, Box<dyn Error>> {
+        if !self.is_encrypted(name) {
+            return Ok(contents);
+        }
+        let encrypted = self.cipher(name)?.encrypt(&contents, &associated(name))?;
+        Ok([MAGIC, &encrypted].concat())
+    }
+
+    /// The contents of one of a list's files as read from disk, decrypted if they're encrypted.
+    /// Fails if the list is encrypted but the contents aren't, as they can't be trusted.
+    pub fn unseal(&self, name: &str, contents: Vec<u8>) -> Result<Vec<u8>, Box<dyn Error>> {
+        match contents.strip_prefix(MAGIC) {
+            Some(encrypted) => self
+                .cipher(name)?
+                .decrypt(encrypted, &associated(name))
+                .map_err(|e| format!("Cannot read {name}, {e}").into()),
+            None if self.is_encrypted(name) => Err(format!(
+                "Cannot read {name}, it is encrypted but this file isn't, so it may have been replaced"
+            )
+            .into()),
+            None => Ok(contents),
+        }
+    }
+}
+
+/// The data a list's encrypted files are bound to: the format's header and the list's name.
+fn associated(name: &str) -> Vec<u8> {
+    [MAGIC, name.as_bytes()].concat()
+}
+
+//
+//
+//
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+
+    #[test]
+    fn test_list_key() {
+        let secret = Secret::Passphrase("correct horse".into());
+        let (key, cipher) = ListKey::new(&secret).unwrap();
+        assert_eq!(key.key_file(), None);
+
+        let encrypted = cipher.encrypt(b"author,title,weight\n", b"books").unwrap();
+        let unlocked = key.cipher(&secret).unwrap();
+        assert_eq!(
+            unlocked.decrypt(&encrypted, b"books").unwrap(),
+            b"author,title,weight\n"
+        );
+        assert!(unlocked.decrypt(&encrypted, b"other").is_err());
+
+        assert!(key
+            .cipher(&Secret::Passphrase("wrong horse".into()))
+            .is_err());
+        let json = serde_json::to_string(&key).unwrap();
+        assert!(!json.contains("correct horse"));
+    }
+
+    #[test]
+    fn test_key_file() {
+        let temp_dir = TempDir::new("wych_book_tests").unwrap();
+        let path = temp_dir.path().join("wych.key");
+        fs::write(&path, "a long random key").unwrap();
+
+        let (key, _) = ListKey::new(&Secret::KeyFile(path.clone())).unwrap();
+        assert_eq!(key.key_file(), Some(path.canonicalize().unwrap().as_path()));
+        assert!(key.unlock("books").is_ok());
+
+        // the path is kept in full
+        fs::create_dir(temp_dir.path().join("usb")).unwrap();
+        let roundabout = temp_dir.path().join("usb").join("..").join("wych.key");
+        let (key, _) = ListKey::new(&Secret::KeyFile(roundabout)).unwrap();
+        assert_eq!(key.key_file(), Some(path.canonicalize().unwrap().as_path()));
+        assert!(key.unlock("books").is_ok());
+
+        fs::write(&path, "another key").unwrap();
+        assert!(key.unlock("books").is_err());
+        fs::write(&path, "").unwrap();
+        assert!(ListKey::new(&Secret::KeyFile(path)).is_err());
+    }
+
+    #[test]
+    fn test_keyring() {
+        let (key, cipher) = ListKey::new(&Secret::Passphrase("correct horse".into())).unwrap();
+        let mut keyring = Keyring::default();
+        keyring.insert("secret", key, cipher);
+
+        let contents = b"author,title,weight\n".to_vec();
+        assert_eq!(keyring.seal("books", contents.clone()).unwrap(), contents);
+        let sealed = keyring.seal("secret", contents.clone()).unwrap();
+        assert!(is_encrypted(&sealed));
+        assert_ne!(keyring.seal("secret", contents.clone()).unwrap(), sealed);
+        assert_eq!(keyring.unseal("secret", sealed.clone()).unwrap(), contents);
+        assert_eq!(keyring.unseal("books", contents.clone()).unwrap(), contents);
+        assert!(keyring.unseal("secret", contents.clone()).is_err());
+
+        // another list's file can't be passed off as this one's, even with the same key
+        keyring.copy("secret", "copy").unwrap();
+        assert!(keyring.unseal("copy", sealed.clone()).is_err());
+        let copied = keyring.seal("copy", contents.clone()).unwrap();
+        assert_eq!(keyring.unseal("copy", copied).unwrap(), contents);
+        keyring.rename("copy", "moved");
+        assert!(keyring.is_encrypted("moved") && !keyring.is_encrypted("copy"));
+
+        // damaged
+        let mut damaged = sealed;
+        let last = damaged.len() - 1;
+        damaged[last] ^= 1;
+        assert!(keyring.unseal("secret", damaged).is_err());
+    }
+}
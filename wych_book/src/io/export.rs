@@ -0,0 +1,194 @@
+use super::{import::Importer, org, todo_txt};
+use crate::books::{
+    book_records::{BookRecords, MAX_WEIGHT},
+    citation, render,
+};
+use std::{error::Error, path::Path};
+
+/// File formats a list of books can be exported to, or imported from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Toml,
+    /// Org-mode, with a heading for each book.
+    Org,
+    /// todo.txt, with a line for each book.
+    TodoTxt,
+    /// Export only.
+    Markdown,
+    /// Export only, as a standalone page.
+    Html,
+    /// Export only, with a citation key for each book.
+    Bibtex,
+    /// Export only.
+    Ris,
+}
+
+impl Format {
+    pub fn from(s: &str) -> Result<Self, Box<dyn Error>> {
+        match s.to_lowercase().as_str() {
+            "json" => Ok(Format::Json),
+            "toml" => Ok(Format::Toml),
+            "org" => Ok(Format::Org),
+            "todo" | "todotxt" | "todo.txt" | "txt" => Ok(Format::TodoTxt),
+            "md" | "markdown" => Ok(Format::Markdown),
+            "html" | "htm" => Ok(Format::Html),
+            "bib" | "bibtex" => Ok(Format::Bibtex),
+            "ris" => Ok(Format::Ris),
+            _ => Err(format!(
+                "Unknown format {s}, valid formats are: json, toml, org, todotxt, md, html, bibtex, ris"
+            )
+            .into()),
+        }
+    }
+
+    /// Guess the format of a file from its extension.
+    pub fn from_path(filename: &Path) -> Result<Self, Box<dyn Error>> {
+        match filename.extension() {
+            Some(extension) => Format::from(&extension.to_string_lossy()),
+            None => Err(format!("Cannot tell the format of {}", filename.display()).into()),
+        }
+    }
+}
+
+/// Write a list of books, including all of their fields, in the given format.
+/// `name` is the list's name, which is used as the title of an HTML page or Org-mode file.
+pub fn export_books(
+    books: &BookRecords,
+    name: &str,
+    format: Format,
+) -> Result<String, Box<dyn Error>> {
+    match format {
+        Format::Json => Ok(serde_json::to_string_pretty(books)?),
+        Format::Toml => Ok(toml::to_string(books)?),
+        Format::Org => Ok(org::org_document(books, name)),
+        Format::TodoTxt => Ok(todo_txt::todo_txt(books)),
+        Format::Markdown => Ok(render::markdown_table(books)),
+        Format::Html => Ok(render::html_page(books, name)),
+        Format::Bibtex => Ok(citation::bibtex(books)),
+        Format::Ris => Ok(citation::ris(books)),
+    }
+}
+
+/// Read a list of books written by `export_books`.
+pub fn import_books(contents: &str, format: Format) -> Result<BookRecords, Box<dyn Error>> {
+    let mut books: BookRecords = match format {
+        Format::Json => serde_json::from_str(contents)?,
+        Format::Toml => toml::from_str(contents)?,
+        Format::Org => org::read_org(contents)?,
+        Format::TodoTxt => todo_txt::read_todo_txt(contents)?,
+        Format::Markdown | Format::Html | Format::Bibtex | Format::Ris => {
+            return Err("Markdown, HTML, BibTeX and RIS can be exported, but not imported".into())
+        }
+    };
+
+    // weights above the maximum are lowered to it, as when changing a book's weight
+    for index in 0..books.records().len() {
+        if let Some(book) = books.get_mut(index) {
+            book.weight = book.weight.min(MAX_WEIGHT);
+        }
+    }
+
+    // make sure every field of every book has a column
+    let columns: Vec<String> = books
+        .records()
+        .iter()
+        .flat_map(|b| b.custom.keys().cloned())
+        .collect();
+    columns.iter().for_each(|c| books.add_custom_column(c));
+    Ok(books)
+}
+
+impl Importer for Format {
+    fn import(&self, contents: &[u8], source: &Path) -> Result<BookRecords, Box<dyn Error>> {
+        let contents = std::str::from_utf8(contents)
+            .map_err(|e| format!("{} is not valid UTF-8, {e}", source.display()))?;
+        import_books(contents, *self)
+    }
+
+    fn updates_books(&self) -> bool {
+        true
+    }
+}
+
+//
+//
+//
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::books::book::Book;
+
+    fn books_to_test() -> BookRecords {
+        let mut book = Book::new("Franz Kafka".into(), "The Castle".into(), 3);
+        book.custom
+            .insert("notes".into(), "Unfinished, \"abandoned\"".into());
+        let mut books: BookRecords = vec![
+            book,
+            Book::new("Yōko Ogawa".into(), "The Memory Police".into(), 1),
+        ]
+        .into();
+        books.add_custom_column("recommended_by");
+        books
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let books = books_to_test();
+
+        for format in [Format::Json, Format::Toml] {
+            let exported = export_books(&books, "books", format).unwrap();
+            assert_eq!(import_books(&exported, format).unwrap(), books);
+        }
+    }
+
+    #[test]
+    fn test_import_json() {
+        let json = r#"{"books": [{"author": "Franz Kafka", "title": "The Castle", "weight": 2, "custom": {"notes": "Unfinished"}}]}"#;
+        let books = import_books(json, Format::Json).unwrap();
+        assert_eq!(books.get(0).unwrap().weight, 2);
+        assert_eq!(books.custom_columns(), &vec!["notes"]);
+
+        assert!(import_books("{}", Format::Json)
+            .unwrap()
+            .records()
+            .is_empty());
+        assert!(import_books(r#"{"books": [{"author": "A"}]}"#, Format::Json).is_err());
+    }
+
+    #[test]
+    fn test_import_clamps_weights() {
+        let json = r#"{"books": [{"author": "Franz Kafka", "title": "The Castle", "weight": 80}]}"#;
+        let books = import_books(json, Format::Json).unwrap();
+        assert_eq!(books.get(0).unwrap().weight, MAX_WEIGHT);
+
+        let toml = "[[books]]\nauthor = \"Franz Kafka\"\ntitle = \"The Castle\"\nweight = 80\n";
+        let books = import_books(toml, Format::Toml).unwrap();
+        assert_eq!(books.get(0).unwrap().weight, MAX_WEIGHT);
+    }
+
+    #[test]
+    fn test_format() {
+        assert_eq!(Format::from("JSON").unwrap(), Format::Json);
+        assert_eq!(
+            Format::from_path(Path::new("list.toml")).unwrap(),
+            Format::Toml
+        );
+        assert_eq!(
+            Format::from_path(Path::new("list.md")).unwrap(),
+            Format::Markdown
+        );
+        assert_eq!(
+            Format::from_path(Path::new("reading.bib")).unwrap(),
+            Format::Bibtex
+        );
+        assert_eq!(Format::from("RIS").unwrap(), Format::Ris);
+        assert_eq!(
+            Format::from_path(Path::new("todo.txt")).unwrap(),
+            Format::TodoTxt
+        );
+        assert!(Format::from("yaml").is_err());
+        assert!(import_books("", Format::Html).is_err());
+        assert!(Format::from_path(Path::new("list")).is_err());
+    }
+}
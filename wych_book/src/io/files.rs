@@ -0,0 +1,153 @@
+use fs4::fs_std::FileExt;
+use std::{
+    error::Error,
+    fs::{self, File, OpenOptions, Permissions},
+    path::Path,
+};
+use tempfile::Builder;
+
+const LOCK_FILE: &str = ".lock";
+
+/// Write to a temporary file next to `filename`, then rename it over `filename`.
+/// If writing fails, or the process is killed part way through, the original file is left untouched.
+/// The new file keeps the permissions of the one it replaces, or follows the umask if there wasn't one.
+pub fn write_atomic<F>(filename: &Path, write: F) -> Result<(), Box<dyn Error>>
+where
+    F: FnOnce(&mut File) -> Result<(), Box<dyn Error>>,
+{
+    let dir = match filename.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+
+    // a new file gets the same permissions as one made by `File::create`, following the umask
+    let existing = fs::metadata(filename).map(|m| m.permissions()).ok();
+    let mut builder = Builder::new();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        builder.permissions(
+            existing
+                .clone()
+                .unwrap_or_else(|| Permissions::from_mode(0o666)),
+        );
+    }
+
+    let mut temp_file = builder
+        .tempfile_in(dir)
+        .map_err(|e| format!("Cannot create temporary file in {}, {e}", dir.display()))?;
+    write(temp_file.as_file_mut())?;
+    if let Some(permissions) = existing {
+        temp_file.as_file().set_permissions(permissions)?;
+    }
+    temp_file.as_file().sync_all()?;
+
+    temp_file
+        .persist(filename)
+        .map_err(|e| format!("Cannot write {}, {}", filename.display(), e.error))?;
+    Ok(())
+}
+
+/// An advisory lock on a directory, which is released when dropped.
+#[derive(Debug)]
+pub struct DirectoryLock {
+    _file: File,
+}
+
+/// Take an exclusive advisory lock on a directory, creating the directory if needed.
+/// Fails immediately if another process already holds the lock.
+pub fn lock_directory(dir: &Path) -> Result<DirectoryLock, Box<dyn Error>> {
+    fs::create_dir_all(dir)?;
+
+    let filename = dir.join(LOCK_FILE);
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&filename)
+        .map_err(|e| format!("Cannot open lock file {}, {e}", filename.display()))?;
+
+    if !file.try_lock_exclusive()? {
+        return Err(format!(
+            "{} is locked by another wych command, try again once it has finished",
+            dir.display()
+        )
+        .into());
+    }
+
+    Ok(DirectoryLock { _file: file })
+}
+
+//
+//
+//
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempdir::TempDir;
+
+    #[test]
+    fn test_write_atomic() {
+        let temp_dir = TempDir::new("wych_book_tests").unwrap();
+        let filename = temp_dir.path().join("books.csv");
+
+        let result = write_atomic(&filename, |f| Ok(write!(f, "first")?));
+        assert!(result.is_ok());
+        assert_eq!(fs::read_to_string(&filename).unwrap(), "first");
+
+        // a failed write leaves the original file alone
+        let result = write_atomic(&filename, |f| {
+            write!(f, "second")?;
+            Err("Failed part way".into())
+        });
+        assert!(result.is_err());
+        assert_eq!(fs::read_to_string(&filename).unwrap(), "first");
+
+        // no temporary files are left behind
+        assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_write_atomic_keeps_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = TempDir::new("wych_book_tests").unwrap();
+        let filename = temp_dir.path().join("books.csv");
+        fs::write(&filename, "first").unwrap();
+        fs::set_permissions(&filename, fs::Permissions::from_mode(0o644)).unwrap();
+
+        write_atomic(&filename, |f| Ok(write!(f, "second")?)).unwrap();
+        let mode = fs::metadata(&filename).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o644);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_write_atomic_follows_umask() {
+        use std::os::unix::fs::PermissionsExt;
+
+        // a new file gets the same permissions as one made by `File::create`
+        let temp_dir = TempDir::new("wych_book_tests").unwrap();
+        let created = temp_dir.path().join("created.csv");
+        File::create(&created).unwrap();
+        let filename = temp_dir.path().join("books.csv");
+
+        write_atomic(&filename, |f| Ok(write!(f, "first")?)).unwrap();
+        let mode = |path| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode(&filename), mode(&created));
+    }
+
+    #[test]
+    fn test_lock_directory() {
+        let temp_dir = TempDir::new("wych_book_tests").unwrap();
+        let dir = temp_dir.path().join("lists");
+
+        let lock = lock_directory(&dir).unwrap();
+        assert!(lock_directory(&dir).is_err());
+
+        drop(lock);
+        assert!(lock_directory(&dir).is_ok());
+    }
+}
@@ -0,0 +1,140 @@
+use super::import::{read_rows, set_fields, Importer};
+use crate::books::{
+    book::{field, Book},
+    book_records::BookRecords,
+};
+use serde::Deserialize;
+use std::{error::Error, path::Path};
+
+/// Imports a Goodreads library export (My Books > Import and export).
+pub struct Goodreads;
+
+/// The columns used from a row of a Goodreads library export.
+#[derive(Debug, Deserialize)]
+struct GoodreadsRow {
+    #[serde(rename = "Title")]
+    title: String,
+    #[serde(rename = "Author")]
+    author: String,
+    #[serde(rename = "Additional Authors", default)]
+    additional_authors: String,
+    #[serde(rename = "ISBN", default)]
+    isbn: String,
+    #[serde(rename = "ISBN13", default)]
+    isbn13: String,
+    #[serde(rename = "Publisher", default)]
+    publisher: String,
+    #[serde(rename = "Year Published", default)]
+    year: String,
+    #[serde(rename = "Number of Pages", default)]
+    pages: String,
+    #[serde(rename = "Exclusive Shelf")]
+    shelf: String,
+    #[serde(rename = "My Rating", default)]
+    rating: String,
+}
+
+impl From<GoodreadsRow> for Book {
+    fn from(row: GoodreadsRow) -> Self {
+        let mut book = Book::new(row.author, row.title, 1);
+
+        // Goodreads writes ISBNs as ="0141439513", so that spreadsheets keep any leading zeros
+        let isbn = [row.isbn, row.isbn13]
+            .map(|i| i.trim_start_matches('=').trim_matches('"').to_string())
+            .into_iter()
+            .find(|i| !i.is_empty());
+        let rating = Some(row.rating).filter(|r| r.trim() != "0");
+
+        set_fields(
+            &mut book,
+            [
+                (field::ADDITIONAL_AUTHORS, Some(row.additional_authors)),
+                (field::ISBN, isbn),
+                (field::PAGES, Some(row.pages)),
+                (field::PUBLISHER, Some(row.publisher)),
+                (field::YEAR, Some(row.year)),
+                (field::SHELF, Some(row.shelf)),
+                (field::RATING, rating),
+            ],
+        );
+        book
+    }
+}
+
+impl Importer for Goodreads {
+    fn import(&self, contents: &[u8], source: &Path) -> Result<BookRecords, Box<dyn Error>> {
+        let rows: Vec<GoodreadsRow> = read_rows(csv::Reader::from_reader(contents), source)?;
+        Ok(BookRecords::from(
+            rows.into_iter().map(Book::from).collect::<Vec<_>>(),
+        ))
+    }
+
+    fn default_shelf(&self) -> Option<&str> {
+        Some("to-read")
+    }
+}
+
+//
+//
+//
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXPORT: &str = "Book Id,Title,Author,Author l-f,Additional Authors,ISBN,ISBN13,My Rating,Average Rating,Publisher,Number of Pages,Exclusive Shelf
+1,The Castle,Franz Kafka,\"Kafka, Franz\",\"Willa Muir, Edwin Muir\",\"=\"\"0805211063\"\"\",\"=\"\"9780805211061\"\"\",0,3.9,Schocken,352,to-read
+2,The Memory Police,Yōko Ogawa,\"Ogawa, Yōko\",,=\"\",\"=\"\"9781101911815\"\"\",4,3.6,Pantheon,274,read
+";
+
+    const EXPORT_WITH_YEAR: &str = "Book Id,Title,Author,Publisher,Year Published,Exclusive Shelf
+1,The Castle,Franz Kafka,Schocken,1998,to-read
+2,The Memory Police,Yōko Ogawa,Pantheon,,read
+";
+
+    #[test]
+    fn test_goodreads_import() {
+        let books = Goodreads
+            .import(EXPORT.as_bytes(), Path::new("export.csv"))
+            .unwrap();
+
+        let castle = books.get(0).unwrap();
+        assert_eq!(castle.title, "The Castle");
+        assert_eq!(castle.weight, 1);
+        assert_eq!(castle.field(field::ISBN), Some("0805211063".into()));
+        assert_eq!(castle.field(field::PAGES), Some("352".into()));
+        assert_eq!(castle.field(field::PUBLISHER), Some("Schocken".into()));
+        assert_eq!(castle.field(field::YEAR), None);
+        assert_eq!(
+            castle.field(field::ADDITIONAL_AUTHORS),
+            Some("Willa Muir, Edwin Muir".into())
+        );
+        assert_eq!(castle.field(field::RATING), None);
+
+        let memory_police = books.get(1).unwrap();
+        assert_eq!(
+            memory_police.field(field::ISBN),
+            Some("9781101911815".into())
+        );
+        assert_eq!(memory_police.field(field::SHELF), Some("read".into()));
+        assert_eq!(memory_police.field(field::RATING), Some("4".into()));
+    }
+
+    #[test]
+    fn test_goodreads_year() {
+        let books = Goodreads
+            .import(EXPORT_WITH_YEAR.as_bytes(), Path::new("export.csv"))
+            .unwrap();
+        assert_eq!(
+            books.get(0).unwrap().field(field::YEAR),
+            Some("1998".into())
+        );
+        assert_eq!(books.get(1).unwrap().field(field::YEAR), None);
+    }
+
+    #[test]
+    fn test_goodreads_errors() {
+        let missing_shelf = "Title,Author\nThe Castle,Franz Kafka\n";
+        let result = Goodreads.import(missing_shelf.as_bytes(), Path::new("export.csv"));
+        assert!(result.unwrap_err().to_string().contains("line 2"));
+    }
+}
@@ -0,0 +1,44 @@
+use super::files::write_atomic;
+use crate::books::history::History;
+use std::{error::Error, fs, io::Write, path::Path};
+
+/// Read the reading history. A missing history file is read as an empty history.
+pub fn read_history(filename: &Path) -> Result<History, Box<dyn Error>> {
+    if !filename.try_exists()? {
+        return Ok(History::default());
+    }
+
+    let json = fs::read_to_string(filename)
+        .map_err(|e| format!("Cannot open: {}, {e}", filename.display()))?;
+    Ok(serde_json::from_str(&json)?)
+}
+
+pub fn write_history(filename: &Path, history: &History) -> Result<(), Box<dyn Error>> {
+    let serialized = serde_json::to_string_pretty(history)?;
+    write_atomic(filename, |file| Ok(write!(file, "{serialized}")?))
+}
+
+//
+//
+//
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::books::{book::Book, history::Event};
+    use tempdir::TempDir;
+
+    #[test]
+    fn test_read_write_history() {
+        let temp_dir = TempDir::new("wych_book_tests").unwrap();
+        let filename = temp_dir.path().join("history.json");
+        assert_eq!(read_history(&filename).unwrap(), History::default());
+
+        let mut history = History::default();
+        let book = Book::new("Franz Kafka".into(), "The Castle".into(), 1);
+        history.record(Event::Picked, "books", &book);
+        history.record(Event::Finished, "books", &book);
+        write_history(&filename, &history).unwrap();
+
+        assert_eq!(read_history(&filename).unwrap(), history);
+    }
+}
@@ -0,0 +1,321 @@
+use super::{
+    csv::RowWarning, export::Format, goodreads::Goodreads, kindle::Kindle,
+    librarything::LibraryThing, mapping::ColumnMapping, plain_text::PlainText,
+    storygraph::StoryGraph,
+};
+use crate::books::{
+    book::{field, Book},
+    book_records::{BookRecords, MAX_WEIGHT},
+};
+use serde::de::DeserializeOwned;
+use std::{collections::BTreeMap, error::Error, fs, path::Path};
+
+/// Reads a list of books from a file written by another application.
+pub trait Importer {
+    /// Read the books in `contents`, which came from the file `source`.
+    fn import(&self, contents: &[u8], source: &Path) -> Result<BookRecords, Box<dyn Error>>;
+
+    /// Read the books in a file.
+    fn import_file(&self, filename: &Path) -> Result<BookRecords, Box<dyn Error>> {
+        let contents =
+            fs::read(filename).map_err(|e| format!("Cannot open {}, {e}", filename.display()))?;
+        self.import(&contents, filename)
+    }
+
+    /// Shelf whose books go into the current list when no shelves are mapped to lists.
+    /// If `None`, every book goes into the current list.
+    fn default_shelf(&self) -> Option<&str> {
+        None
+    }
+
+    /// Whether the file is a list exported by `wych` and edited elsewhere, so books already
+    /// in the list are updated from it rather than skipped.
+    fn updates_books(&self) -> bool {
+        false
+    }
+}
+
+/// Formats that books can be imported from.
+pub const IMPORT_FORMATS: [&str; 10] = [
+    "json",
+    "toml",
+    "org",
+    "todotxt",
+    "text",
+    "goodreads",
+    "storygraph",
+    "librarything",
+    "kindle",
+    "calibre",
+];
+
+/// The importer for a format name, such as `goodreads`.
+pub fn importer(format: &str) -> Result<Box<dyn Importer>, Box<dyn Error>> {
+    match format.to_lowercase().as_str() {
+        "json" => Ok(Box::new(Format::Json)),
+        "toml" => Ok(Box::new(Format::Toml)),
+        "org" => Ok(Box::new(Format::Org)),
+        "todotxt" | "todo.txt" => Ok(Box::new(Format::TodoTxt)),
+        "text" => Ok(Box::new(PlainText::default())),
+        "goodreads" => Ok(Box::new(Goodreads)),
+        "storygraph" => Ok(Box::new(StoryGraph)),
+        "librarything" => Ok(Box::new(LibraryThing)),
+        "kindle" => Ok(Box::new(Kindle { highlights: false })),
+        #[cfg(feature = "sqlite")]
+        "calibre" => Ok(Box::new(super::calibre::Calibre)),
+        #[cfg(not(feature = "sqlite"))]
+        "calibre" => {
+            Err("Calibre libraries are SQLite databases, but this build doesn't include the `sqlite` feature".into())
+        }
+        _ => Err(format!(
+            "Unknown format {format}, valid formats are: {}",
+            IMPORT_FORMATS.join(", ")
+        )
+        .into()),
+    }
+}
+
+/// The importer for a column-mapping file, see `ColumnMapping`.
+pub fn mapping_importer(filename: &Path) -> Result<Box<dyn Importer>, Box<dyn Error>> {
+    Ok(Box::new(ColumnMapping::from_file(filename)?))
+}
+
+/// How imported books are sorted into lists and weighted.
+#[derive(Debug, Clone, Default)]
+pub struct ImportOptions {
+    /// List that the books on each shelf go into. Books on other shelves are skipped.
+    pub shelves: BTreeMap<String, String>,
+    /// Initial weight of the books on each shelf. Books on other shelves start with a weight of 1.
+    pub shelf_weights: BTreeMap<String, u8>,
+    /// Use a book's rating as its initial weight, if it has been rated.
+    pub rating_weights: bool,
+}
+
+/// Sort imported books into lists by their shelf, and set their initial weights.
+/// Books with no shelf, or every book if no shelves are mapped, go into `current_list`.
+/// Returns the books for each list, keyed by list name.
+pub fn sort_into_lists(
+    imported: &BookRecords,
+    importer: &dyn Importer,
+    current_list: &str,
+    options: &ImportOptions,
+) -> BTreeMap<String, BookRecords> {
+    let mut shelves = options.shelves.clone();
+    if let (true, Some(shelf)) = (shelves.is_empty(), importer.default_shelf()) {
+        shelves.insert(shelf.to_string(), current_list.to_string());
+    }
+
+    let mut lists: BTreeMap<String, Vec<Book>> = BTreeMap::new();
+    for book in imported.records() {
+        let book_shelves = book_shelves(book);
+        let list = if book_shelves.is_empty() || shelves.is_empty() {
+            Some(current_list)
+        } else {
+            book_shelves
+                .iter()
+                .find_map(|s| shelves.get(s).map(String::as_str))
+        };
+
+        if let Some(list) = list {
+            let mut book = book.clone();
+            if let Some(weight) = initial_weight(&book, &book_shelves, options) {
+                book.weight = weight;
+            }
+            lists.entry(list.to_string()).or_default().push(book);
+        }
+    }
+
+    lists
+        .into_iter()
+        .map(|(list, books)| (list, BookRecords::from(books)))
+        .collect()
+}
+
+/// The shelves a book is on, separated by commas.
+fn book_shelves(book: &Book) -> Vec<String> {
+    book.field(field::SHELF)
+        .map(|s| s.split(',').map(|s| s.trim().to_string()).collect())
+        .unwrap_or_default()
+}
+
+/// The weight a book should start with, if the options give it one.
+fn initial_weight(book: &Book, shelves: &[String], options: &ImportOptions) -> Option<u8> {
+    let rating = book
+        .field(field::RATING)
+        .and_then(|r| r.parse::<f32>().ok())
+        .filter(|r| *r > 0.0);
+
+    match rating {
+        Some(rating) if options.rating_weights => Some((rating.round() as u8).clamp(1, MAX_WEIGHT)),
+        _ => shelves
+            .iter()
+            .find_map(|s| options.shelf_weights.get(s))
+            .map(|w| (*w).min(MAX_WEIGHT)),
+    }
+}
+
+/// Books already in the list that imported books have changed, with the changes made, and their indexes.
+/// Each book takes the imported weight and fields, and keeps any fields the import left out,
+/// except for its status, which is cleared if the imported book has none.
+pub fn updated_books(books: &BookRecords, imported: &BookRecords) -> Vec<(usize, Book)> {
+    let mut updated: Vec<(usize, Book)> = Vec::new();
+    for book in imported.records() {
+        let Some(index) = books.position(book) else {
+            continue;
+        };
+        let existing = &books.records()[index];
+        let mut changed = existing.clone();
+        changed.weight = book.weight;
+        changed.custom.remove(field::STATUS);
+        changed.custom.extend(book.custom.clone());
+
+        if changed != *existing && !updated.iter().any(|(i, _)| *i == index) {
+            updated.push((index, changed));
+        }
+    }
+    updated
+}
+
+/// Deserialize every row of a CSV or TSV file, failing on the first bad row.
+pub(crate) fn read_rows<T: DeserializeOwned>(
+    mut reader: csv::Reader<&[u8]>,
+    source: &Path,
+) -> Result<Vec<T>, Box<dyn Error>> {
+    let headers = reader.headers()?.clone();
+    reader
+        .records()
+        .map(|result| {
+            result
+                .and_then(|record| record.deserialize(Some(&headers)))
+                .map_err(|e| RowWarning::new(source, &headers, &e).to_string().into())
+        })
+        .collect()
+}
+
+/// Set each user-defined field of a book that has a non-blank value.
+pub(crate) fn set_fields<const N: usize>(book: &mut Book, fields: [(&str, Option<String>); N]) {
+    for (name, value) in fields {
+        if let Some(value) = value.filter(|v| !v.trim().is_empty()) {
+            book.custom
+                .insert(name.to_string(), value.trim().to_string());
+        }
+    }
+}
+
+/// Turn a shelf or collection name, such as `To read`, into the `to-read` form Goodreads uses.
+pub(crate) fn shelf_name(name: &str) -> String {
+    name.trim().to_lowercase().replace(' ', "-")
+}
+
+//
+//
+//
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn book(title: &str, shelf: Option<&str>, rating: Option<&str>) -> Book {
+        let mut book = Book::new("Author".into(), title.into(), 1);
+        set_fields(
+            &mut book,
+            [
+                (field::SHELF, shelf.map(String::from)),
+                (field::RATING, rating.map(String::from)),
+            ],
+        );
+        book
+    }
+
+    fn imported() -> BookRecords {
+        BookRecords::from(vec![
+            book("Unshelved", None, None),
+            book("Wanted", Some("to-read"), None),
+            book("Finished", Some("read"), Some("4.5")),
+            book("Owned", Some("your-library, wishlist"), None),
+        ])
+    }
+
+    fn titles(books: &BookRecords) -> Vec<&str> {
+        books.records().iter().map(|b| b.title.as_str()).collect()
+    }
+
+    #[test]
+    fn test_sort_into_default_shelf() {
+        let lists = sort_into_lists(&imported(), &Goodreads, "books", &ImportOptions::default());
+        assert_eq!(lists.len(), 1);
+        assert_eq!(titles(&lists["books"]), vec!["Unshelved", "Wanted"]);
+
+        // without a default shelf, everything goes into the current list
+        let lists = sort_into_lists(
+            &imported(),
+            &Format::Json,
+            "books",
+            &ImportOptions::default(),
+        );
+        assert_eq!(lists["books"].records().len(), 4);
+    }
+
+    #[test]
+    fn test_sort_into_mapped_shelves() {
+        let options = ImportOptions {
+            shelves: BTreeMap::from([
+                ("read".into(), "finished".into()),
+                ("wishlist".into(), "books".into()),
+            ]),
+            shelf_weights: BTreeMap::from([("wishlist".into(), 20)]),
+            rating_weights: true,
+        };
+        let lists = sort_into_lists(&imported(), &Goodreads, "books", &options);
+
+        assert_eq!(titles(&lists["books"]), vec!["Unshelved", "Owned"]);
+        assert_eq!(lists["books"].get(1).unwrap().weight, MAX_WEIGHT);
+        assert_eq!(titles(&lists["finished"]), vec!["Finished"]);
+        assert_eq!(lists["finished"].get(0).unwrap().weight, 5);
+    }
+
+    #[test]
+    fn test_updated_books() {
+        let mut castle = Book::new("Franz Kafka".into(), "The Castle".into(), 3);
+        castle
+            .custom
+            .insert(field::NOTES.into(), "Unfinished novel".into());
+        castle.custom.insert(field::STATUS.into(), "picked".into());
+        let books: BookRecords = vec![
+            castle,
+            Book::new("Yoko Ogawa".into(), "The Memory Police".into(), 1),
+        ]
+        .into();
+
+        let mut finished = Book::new("franz kafka".into(), "The Castle".into(), 5);
+        finished
+            .custom
+            .insert(field::STATUS.into(), "finished".into());
+        let imported: BookRecords = vec![
+            finished,
+            Book::new("Yoko Ogawa".into(), "The Memory Police".into(), 1),
+            Book::new("Bram Stoker".into(), "Dracula".into(), 1),
+        ]
+        .into();
+
+        let updated = updated_books(&books, &imported);
+        assert_eq!(updated.len(), 1);
+        let (index, castle) = &updated[0];
+        assert_eq!((*index, castle.weight), (0, 5));
+        assert_eq!(castle.author, "Franz Kafka");
+        assert_eq!(castle.field(field::STATUS), Some("finished".into()));
+        assert_eq!(castle.field(field::NOTES), Some("Unfinished novel".into()));
+
+        // a book that is no longer finished loses its status
+        let imported: BookRecords =
+            vec![Book::new("Franz Kafka".into(), "The Castle".into(), 3)].into();
+        let (_, castle) = &updated_books(&books, &imported)[0];
+        assert_eq!(castle.field(field::STATUS), None);
+    }
+
+    #[test]
+    fn test_importer() {
+        assert!(importer("StoryGraph").is_ok());
+        let error = importer("csv").err().unwrap();
+        assert!(error.to_string().contains("librarything"));
+    }
+}
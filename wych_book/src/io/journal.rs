@@ -0,0 +1,47 @@
+use super::files::write_atomic;
+use crate::books::journal::Journal;
+use std::{error::Error, fs, io::Write, path::Path};
+
+/// Read a journal of changes to a list. A missing journal file is read as an empty journal.
+pub fn read_journal(filename: &Path) -> Result<Journal, Box<dyn Error>> {
+    if !filename.try_exists()? {
+        return Ok(Journal::default());
+    }
+
+    let json = fs::read_to_string(filename)
+        .map_err(|e| format!("Cannot open: {}, {e}", filename.display()))?;
+    Ok(serde_json::from_str(&json)?)
+}
+
+pub fn write_journal(filename: &Path, journal: &Journal) -> Result<(), Box<dyn Error>> {
+    if let Some(dir) = filename.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    let serialized = serde_json::to_string(journal)?;
+    write_atomic(filename, |file| Ok(write!(file, "{serialized}")?))
+}
+
+//
+//
+//
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::books::book_records::BookRecords;
+    use tempdir::TempDir;
+
+    #[test]
+    fn test_read_write_journal() {
+        let temp_dir = TempDir::new("wych_book_tests").unwrap();
+        let filename = temp_dir.path().join("journal").join("books.json");
+        assert_eq!(read_journal(&filename).unwrap(), Journal::default());
+
+        let mut books = BookRecords::default();
+        let mut journal = Journal::default();
+        journal.record(books.add_book("Franz Kafka", "The Castle"));
+
+        write_journal(&filename, &journal).unwrap();
+        assert_eq!(read_journal(&filename).unwrap(), journal);
+    }
+}
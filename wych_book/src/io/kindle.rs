@@ -0,0 +1,252 @@
+use super::{import::Importer, librarything::unsort_name, scan::UNKNOWN_AUTHOR};
+use crate::books::{
+    book::{field, Book},
+    book_records::BookRecords,
+};
+use std::{error::Error, path::Path};
+
+/// Name of the file a Kindle keeps its highlights in, in its documents folder.
+pub const CLIPPINGS_FILE: &str = "My Clippings.txt";
+
+/// Line between clippings.
+const SEPARATOR: &str = "==========";
+
+/// Imports the books in a Kindle's `My Clippings.txt`.
+pub struct Kindle {
+    /// Keep each book's highlights and notes in its notes field.
+    pub highlights: bool,
+}
+
+impl Kindle {
+    /// Whether a file is named like a Kindle's clippings file.
+    pub fn is_clippings_file(filename: &Path) -> bool {
+        filename
+            .file_name()
+            .is_some_and(|f| f.to_string_lossy().eq_ignore_ascii_case(CLIPPINGS_FILE))
+    }
+}
+
+/// Split a clipping's first line, such as `The Castle (Kafka, Franz)`, into its title and authors.
+fn title_and_authors(line: &str) -> (String, Vec<String>) {
+    let line = line.trim_start_matches('\u{feff}').trim();
+    let authors = line
+        .strip_suffix(')')
+        .and_then(|l| l.rfind('(').map(|i| (l[..i].trim(), &l[i + 1..])));
+
+    match authors {
+        Some((title, authors)) if !title.is_empty() => {
+            let authors = authors
+                .split(';')
+                .map(unsort_name)
+                .filter(|a| !a.is_empty())
+                .collect();
+            (title.to_string(), authors)
+        }
+        _ => (line.to_string(), Vec::new()),
+    }
+}
+
+/// A copy of a book with any highlights it doesn't have yet added to the end of its notes.
+/// Returns None if the book already has all of them.
+pub fn add_highlights(book: &Book, highlights: &str) -> Option<Book> {
+    let notes = book.custom.get(field::NOTES).cloned().unwrap_or_default();
+    let new: Vec<&str> = highlights
+        .lines()
+        .filter(|h| !h.trim().is_empty() && !notes.lines().any(|n| n == *h))
+        .collect();
+    if new.is_empty() {
+        return None;
+    }
+
+    let mut book = book.clone();
+    let lines: Vec<&str> = notes
+        .lines()
+        .chain(new)
+        .filter(|l| !l.trim().is_empty())
+        .collect();
+    book.custom.insert(field::NOTES.into(), lines.join("\n"));
+    Some(book)
+}
+
+impl Importer for Kindle {
+    /// Each clipping is a title and author line, a line describing the clipping, a blank line,
+    /// then the highlighted text, which is empty for bookmarks.
+    fn import(&self, contents: &[u8], source: &Path) -> Result<BookRecords, Box<dyn Error>> {
+        let contents = std::str::from_utf8(contents)
+            .map_err(|e| format!("{} is not valid UTF-8, {e}", source.display()))?;
+
+        let mut books: Vec<Book> = Vec::new();
+        for clipping in contents.split(SEPARATOR) {
+            let mut lines = clipping.lines().skip_while(|l| l.trim().is_empty());
+            let (Some(first), Some(description)) = (lines.next(), lines.next()) else {
+                continue;
+            };
+            if !description.starts_with("- ") {
+                continue;
+            }
+            let (title, authors) = title_and_authors(first);
+            let text = lines
+                .map(str::trim)
+                .filter(|l| !l.is_empty())
+                .collect::<Vec<_>>()
+                .join(" ");
+
+            // different books can share a title
+            let author = authors.first().map_or(UNKNOWN_AUTHOR, |a| a.as_str());
+            let index = match books.iter().position(|b| b.is_by(author, &title)) {
+                Some(index) => index,
+                None => {
+                    let mut book = Book::new(author.to_string(), title, 1);
+                    if authors.len() > 1 {
+                        book.custom
+                            .insert(field::ADDITIONAL_AUTHORS.into(), authors[1..].join(", "));
+                    }
+                    books.push(book);
+                    books.len() - 1
+                }
+            };
+
+            if self.highlights && !text.is_empty() {
+                if let Some(book) = add_highlights(&books[index], &text) {
+                    books[index] = book;
+                }
+            }
+        }
+
+        if books.is_empty() && !contents.trim_start_matches('\u{feff}').trim().is_empty() {
+            return Err(format!("{} has no Kindle clippings", source.display()).into());
+        }
+        Ok(books.into())
+    }
+}
+
+//
+//
+//
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CLIPPINGS: &str = "\u{feff}The Castle (Kafka, Franz)
+- Your Highlight on page 12 | Location 170-171 | Added on Monday, 1 January 2024 10:00:00
+
+It was late evening when K. arrived.
+==========
+The Memory Police (Yōko Ogawa)
+- Your Bookmark on page 3 | Location 40 | Added on Tuesday, 2 January 2024 09:00:00
+
+
+==========
+The Castle (Kafka, Franz)
+- Your Note on page 20 | Location 300 | Added on Wednesday, 3 January 2024 08:00:00
+
+Compare with The Trial
+==========
+The Castle (Kafka, Franz)
+- Your Highlight on page 12 | Location 170-171 | Added on Thursday, 4 January 2024 10:00:00
+
+It was late evening when K. arrived.
+==========
+Good Omens (Neil Gaiman;Terry Pratchett)
+- La subrayado en la página 5 | posición 70 | Añadido el viernes, 5 de enero de 2024 11:00:00
+
+In the beginning
+==========
+notes-on-the-trip
+- Your Highlight on Location 1-2 | Added on Friday, 5 January 2024 12:00:00
+
+Pack a book
+==========
+";
+
+    #[test]
+    fn test_kindle_import() {
+        let source = Path::new(CLIPPINGS_FILE);
+        let books = Kindle { highlights: false }
+            .import(CLIPPINGS.as_bytes(), source)
+            .unwrap();
+        let books: Vec<(&str, &str)> = books
+            .records()
+            .iter()
+            .map(|b| (b.title.as_str(), b.author.as_str()))
+            .collect();
+        assert_eq!(
+            books,
+            vec![
+                ("The Castle", "Franz Kafka"),
+                ("The Memory Police", "Yōko Ogawa"),
+                ("Good Omens", "Neil Gaiman"),
+                ("notes-on-the-trip", UNKNOWN_AUTHOR),
+            ]
+        );
+
+        let books = Kindle { highlights: true }
+            .import(CLIPPINGS.as_bytes(), source)
+            .unwrap();
+        assert_eq!(
+            books.get(0).unwrap().field(field::NOTES),
+            Some("It was late evening when K. arrived.\nCompare with The Trial".into())
+        );
+        assert_eq!(books.get(1).unwrap().field(field::NOTES), None);
+        let good_omens = books.get(2).unwrap();
+        assert_eq!(
+            good_omens.field(field::ADDITIONAL_AUTHORS),
+            Some("Terry Pratchett".into())
+        );
+        assert_eq!(
+            good_omens.field(field::NOTES),
+            Some("In the beginning".into())
+        );
+
+        assert!(Kindle { highlights: false }
+            .import(b"Title,Author\n", source)
+            .is_err());
+        assert!(Kindle::is_clippings_file(Path::new(
+            "/media/Kindle/documents/My Clippings.txt"
+        )));
+    }
+
+    #[test]
+    fn test_books_sharing_a_title() {
+        let clippings = "Beloved (Toni Morrison)
+- Your Highlight on page 1 | Location 1-2 | Added on Monday, 1 January 2024 10:00:00
+
+124 was spiteful.
+==========
+Beloved (Frank Matheson)
+- Your Highlight on page 1 | Location 1-2 | Added on Monday, 1 January 2024 11:00:00
+
+A different book.
+==========
+";
+        let books = Kindle { highlights: true }
+            .import(clippings.as_bytes(), Path::new(CLIPPINGS_FILE))
+            .unwrap();
+        let notes: Vec<(String, Option<String>)> = books
+            .records()
+            .iter()
+            .map(|b| (b.author.clone(), b.field(field::NOTES)))
+            .collect();
+        assert_eq!(
+            notes,
+            vec![
+                ("Toni Morrison".into(), Some("124 was spiteful.".into())),
+                ("Frank Matheson".into(), Some("A different book.".into())),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_add_highlights() {
+        let mut book = Book::new("Franz Kafka".into(), "The Castle".into(), 1);
+        book.custom
+            .insert(field::NOTES.into(), "Read for book club".into());
+
+        let updated = add_highlights(&book, "It was late evening\nRead for book club").unwrap();
+        assert_eq!(
+            updated.field(field::NOTES),
+            Some("Read for book club\nIt was late evening".into())
+        );
+        assert_eq!(add_highlights(&updated, "It was late evening"), None);
+    }
+}
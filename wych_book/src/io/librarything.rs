@@ -0,0 +1,142 @@
+use super::import::{read_rows, set_fields, shelf_name, Importer};
+use crate::books::{
+    book::{field, Book},
+    book_records::BookRecords,
+};
+use serde::Deserialize;
+use std::{error::Error, path::Path};
+
+/// Imports a LibraryThing tab-delimited export (More > Import/Export > Export).
+pub struct LibraryThing;
+
+/// The columns used from a row of a LibraryThing export.
+#[derive(Debug, Deserialize)]
+struct LibraryThingRow {
+    #[serde(rename = "Title")]
+    title: String,
+    #[serde(rename = "Primary Author")]
+    author: String,
+    #[serde(rename = "Secondary Author", default)]
+    secondary_author: String,
+    #[serde(rename = "ISBN", default)]
+    isbn: String,
+    #[serde(rename = "Page Count", default)]
+    pages: String,
+    #[serde(rename = "Rating", default)]
+    rating: String,
+    #[serde(rename = "Tags", default)]
+    tags: String,
+    #[serde(rename = "Collections", default)]
+    collections: String,
+}
+
+/// Turn a name sorted by surname, such as `Kafka, Franz`, back into `Franz Kafka`.
+pub(crate) fn unsort_name(name: &str) -> String {
+    match name.split_once(',') {
+        Some((last, first)) if !first.contains(',') => format!("{} {}", first.trim(), last.trim()),
+        _ => name.trim().to_string(),
+    }
+}
+
+impl From<LibraryThingRow> for Book {
+    fn from(row: LibraryThingRow) -> Self {
+        let mut book = Book::new(unsort_name(&row.author), row.title, 1);
+
+        // collections such as "Your library, To read" become shelves like "your-library, to-read"
+        let shelves: Vec<String> = row
+            .collections
+            .split(',')
+            .filter(|c| !c.trim().is_empty())
+            .map(shelf_name)
+            .collect();
+        let secondary_author = Some(row.secondary_author)
+            .filter(|a| !a.trim().is_empty())
+            .map(|a| unsort_name(&a));
+        let rating = Some(row.rating).filter(|r| r.trim() != "0");
+
+        set_fields(
+            &mut book,
+            [
+                (field::ADDITIONAL_AUTHORS, secondary_author),
+                // LibraryThing writes ISBNs as [0805211063]
+                (
+                    field::ISBN,
+                    Some(row.isbn.trim_matches(['[', ']']).to_string()),
+                ),
+                (field::PAGES, Some(row.pages)),
+                (field::SHELF, Some(shelves.join(", "))),
+                (field::RATING, rating),
+                (field::TAGS, Some(row.tags)),
+            ],
+        );
+        book
+    }
+}
+
+impl Importer for LibraryThing {
+    fn import(&self, contents: &[u8], source: &Path) -> Result<BookRecords, Box<dyn Error>> {
+        // fields are separated by tabs and never quoted
+        let reader = csv::ReaderBuilder::new()
+            .delimiter(b'\t')
+            .quoting(false)
+            .from_reader(contents);
+        let rows: Vec<LibraryThingRow> = read_rows(reader, source)?;
+        Ok(BookRecords::from(
+            rows.into_iter().map(Book::from).collect::<Vec<_>>(),
+        ))
+    }
+
+    fn default_shelf(&self) -> Option<&str> {
+        Some("to-read")
+    }
+}
+
+//
+//
+//
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXPORT: &str = "Book Id\tTitle\tPrimary Author\tSecondary Author\tRating\tWeight\tPage Count\tTags\tCollections\tISBN
+1\tThe \"Castle\"\tKafka, Franz\tMuir, Willa\t0\t1.2 pounds\t352\tclassics\tYour library, To read\t[0805211063]
+2\tThe Hobbit\tTolkien, J. R. R.\t\t4.5\t\t310\t\tRead but unowned\t[]
+";
+
+    #[test]
+    fn test_librarything_import() {
+        let books = LibraryThing
+            .import(EXPORT.as_bytes(), Path::new("librarything.tsv"))
+            .unwrap();
+
+        let castle = books.get(0).unwrap();
+        assert_eq!(castle.author, "Franz Kafka");
+        assert_eq!(castle.title, "The \"Castle\"");
+        assert_eq!(castle.weight, 1);
+        assert_eq!(
+            castle.field(field::ADDITIONAL_AUTHORS),
+            Some("Willa Muir".into())
+        );
+        assert_eq!(castle.field(field::ISBN), Some("0805211063".into()));
+        assert_eq!(
+            castle.field(field::SHELF),
+            Some("your-library, to-read".into())
+        );
+        assert_eq!(castle.field(field::RATING), None);
+
+        let hobbit = books.get(1).unwrap();
+        assert_eq!(hobbit.author, "J. R. R. Tolkien");
+        assert_eq!(hobbit.field(field::ISBN), None);
+        assert_eq!(hobbit.field(field::RATING), Some("4.5".into()));
+    }
+
+    #[test]
+    fn test_unsort_name() {
+        assert_eq!(unsort_name("Kafka, Franz"), "Franz Kafka");
+        assert_eq!(unsort_name("Plato"), "Plato");
+        assert_eq!(
+            unsort_name("King, Martin Luther, Jr."),
+            "King, Martin Luther, Jr."
+        );
+    }
+}
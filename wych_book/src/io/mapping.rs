@@ -0,0 +1,151 @@
+use super::import::{set_fields, Importer};
+use crate::books::{
+    book::Book,
+    book_records::{BookRecords, MAX_WEIGHT},
+};
+use serde::Deserialize;
+use std::{collections::BTreeMap, error::Error, fs, path::Path};
+
+/// Imports a CSV or TSV file using a TOML file that names the column holding each field, e.g.
+///
+/// ```toml
+/// author = "Writer"
+/// title = "Book Title"
+/// weight = "Priority"     # optional, books start with a weight of 1 without it
+/// delimiter = "\t"        # optional, defaults to ","
+///
+/// [fields]                # optional, kept as user-defined columns
+/// isbn = "ISBN"
+/// notes = "My Notes"
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ColumnMapping {
+    pub author: String,
+    pub title: String,
+    #[serde(default)]
+    pub weight: Option<String>,
+    #[serde(default)]
+    pub delimiter: Option<char>,
+    #[serde(default)]
+    pub fields: BTreeMap<String, String>,
+}
+
+impl ColumnMapping {
+    pub fn from_file(filename: &Path) -> Result<Self, Box<dyn Error>> {
+        let contents = fs::read_to_string(filename)
+            .map_err(|e| format!("Cannot open {}, {e}", filename.display()))?;
+        toml::from_str(&contents)
+            .map_err(|e| format!("Invalid column mapping {}, {e}", filename.display()).into())
+    }
+
+    fn delimiter(&self) -> Result<u8, Box<dyn Error>> {
+        match self.delimiter {
+            None => Ok(b','),
+            Some(c) if c.is_ascii() => Ok(c as u8),
+            Some(c) => {
+                Err(format!("Invalid delimiter {c:?}, it must be a single ASCII character").into())
+            }
+        }
+    }
+}
+
+impl Importer for ColumnMapping {
+    fn import(&self, contents: &[u8], source: &Path) -> Result<BookRecords, Box<dyn Error>> {
+        let mut reader = csv::ReaderBuilder::new()
+            .delimiter(self.delimiter()?)
+            .flexible(true)
+            .from_reader(contents);
+        let headers = reader.headers()?.clone();
+        let position = |column: &str| {
+            headers
+                .iter()
+                .position(|h| h.trim() == column)
+                .ok_or_else(|| format!("{}: no column named '{column}'", source.display()))
+        };
+
+        let author = position(&self.author)?;
+        let title = position(&self.title)?;
+        let weight = self.weight.as_deref().map(position).transpose()?;
+        let fields = self
+            .fields
+            .iter()
+            .map(|(name, column)| Ok((name.as_str(), position(column)?)))
+            .collect::<Result<Vec<_>, String>>()?;
+
+        let mut books = Vec::new();
+        for (row, result) in reader.records().enumerate() {
+            let record = result?;
+            let value = |i: usize| record.get(i).unwrap_or_default().trim().to_string();
+
+            let book_weight = match weight.map(value).filter(|w| !w.is_empty()) {
+                Some(w) => w.parse::<u8>().map(|w| w.min(MAX_WEIGHT)).map_err(|_| {
+                    // the header is line 1
+                    format!("{}, line {}: invalid weight {w}", source.display(), row + 2)
+                })?,
+                None => 1,
+            };
+            let mut book = Book::new(value(author), value(title), book_weight);
+            for (name, i) in &fields {
+                set_fields(&mut book, [(*name, Some(value(*i)))]);
+            }
+            books.push(book);
+        }
+
+        Ok(BookRecords::from(books))
+    }
+}
+
+//
+//
+//
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mapping(toml: &str) -> ColumnMapping {
+        toml::from_str(toml).unwrap()
+    }
+
+    #[test]
+    fn test_mapping_import() {
+        let mapping = mapping(
+            "author = \"Writer\"\ntitle = \"Name\"\nweight = \"Priority\"\ndelimiter = \";\"\n[fields]\nnotes = \"Notes\"",
+        );
+        let contents = "Name;Writer;Priority;Notes\nThe Castle;Franz Kafka;3;from sam\nDracula;Bram Stoker;;\n";
+        let books = mapping
+            .import(contents.as_bytes(), Path::new("books.csv"))
+            .unwrap();
+
+        assert_eq!(books.custom_columns(), &["notes".to_string()]);
+        let castle = books.get(0).unwrap();
+        assert_eq!((castle.author.as_str(), castle.weight), ("Franz Kafka", 3));
+        assert_eq!(castle.field("notes"), Some("from sam".into()));
+        let dracula = books.get(1).unwrap();
+        assert_eq!((dracula.weight, dracula.field("notes")), (1, None));
+    }
+
+    #[test]
+    fn test_mapping_errors() {
+        let missing = mapping("author = \"Writer\"\ntitle = \"Title\"");
+        let result = missing.import(b"Name,Writer\n", Path::new("books.csv"));
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("no column named 'Title'"));
+
+        let weighted = mapping("author = \"A\"\ntitle = \"T\"\nweight = \"W\"");
+        let result = weighted.import(b"A,T,W\nKafka,The Castle,lots\n", Path::new("books.csv"));
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("line 2: invalid weight lots"));
+
+        let books = weighted
+            .import(b"A,T,W\nKafka,The Castle,80\n", Path::new("books.csv"))
+            .unwrap();
+        assert_eq!(books.get(0).unwrap().weight, MAX_WEIGHT);
+
+        assert!(toml::from_str::<ColumnMapping>("author = \"A\"\ntitel = \"T\"").is_err());
+    }
+}
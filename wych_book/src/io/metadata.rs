@@ -0,0 +1,149 @@
+use crate::books::{
+    book::{field, Book},
+    isbn::Isbn,
+};
+use std::error::Error;
+
+/// Max number of subjects kept for a book.
+const MAX_SUBJECTS: usize = 5;
+
+/// What a metadata provider knows about a book.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct BookMetadata {
+    pub title: Option<String>,
+    /// The author's name as the provider spells it.
+    pub author: Option<String>,
+    pub pages: Option<u32>,
+    pub year: Option<u16>,
+    pub publisher: Option<String>,
+    pub subjects: Vec<String>,
+}
+
+/// Looks up details of books from an online catalogue.
+pub trait MetadataProvider {
+    /// Look up a book by its ISBN if it has one, otherwise by its title and author if it has them.
+    /// Returns None if the provider doesn't know the book, or an error if it can't be reached.
+    fn lookup(&self, book: &Book) -> Result<Option<BookMetadata>, Box<dyn Error>>;
+}
+
+/// Look up the book with an ISBN. Returns None if the provider doesn't know its title and author.
+pub fn book_from_isbn(
+    provider: &dyn MetadataProvider,
+    isbn: &Isbn,
+) -> Result<Option<Book>, Box<dyn Error>> {
+    let mut book = Book::new(String::new(), String::new(), 1);
+    book.custom
+        .insert(field::ISBN.to_string(), isbn.to_string());
+
+    let metadata = match provider.lookup(&book)? {
+        Some(metadata) => metadata,
+        None => return Ok(None),
+    };
+    match &metadata.title {
+        Some(title) if metadata.author.is_some() => {
+            book.title = title.trim().to_string();
+            Ok(Some(enrich_book(&book, &metadata)))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// A copy of a book with its author spelt as the metadata has it, and any missing pages,
+/// year, publisher and subjects filled in. Fields the book already has are kept.
+pub fn enrich_book(book: &Book, metadata: &BookMetadata) -> Book {
+    let mut book = book.clone();
+    if let Some(author) = metadata.author.as_ref().filter(|a| !a.trim().is_empty()) {
+        book.author = author.trim().to_string();
+    }
+
+    let subjects = metadata
+        .subjects
+        .iter()
+        .take(MAX_SUBJECTS)
+        .cloned()
+        .collect::<Vec<_>>()
+        .join(", ");
+    let fields = [
+        (field::PAGES, metadata.pages.map(|p| p.to_string())),
+        (field::YEAR, metadata.year.map(|y| y.to_string())),
+        (
+            field::PUBLISHER,
+            metadata.publisher.clone().filter(|p| !p.trim().is_empty()),
+        ),
+        (field::SUBJECTS, Some(subjects).filter(|s| !s.is_empty())),
+    ];
+    for (name, value) in fields {
+        if let Some(value) = value {
+            book.custom.entry(name.to_string()).or_insert(value);
+        }
+    }
+    book
+}
+
+//
+//
+//
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_enrich_book() {
+        let mut book = Book::new("franz kafka".into(), "The Castle".into(), 2);
+        book.custom.insert(field::PAGES.into(), "352".into());
+        let metadata = BookMetadata {
+            title: Some("Das Schloss".into()),
+            author: Some("Franz Kafka".into()),
+            pages: Some(316),
+            year: Some(1926),
+            publisher: Some("Schocken".into()),
+            subjects: (1..=7).map(|i| format!("subject {i}")).collect(),
+        };
+
+        let enriched = enrich_book(&book, &metadata);
+        assert_eq!(enriched.author, "Franz Kafka");
+        assert_eq!(enriched.title, "The Castle");
+        assert_eq!(enriched.weight, 2);
+        assert_eq!(enriched.field(field::PAGES), Some("352".into()));
+        assert_eq!(enriched.field(field::YEAR), Some("1926".into()));
+        assert_eq!(enriched.field(field::PUBLISHER), Some("Schocken".into()));
+        assert_eq!(
+            enriched.field(field::SUBJECTS),
+            Some("subject 1, subject 2, subject 3, subject 4, subject 5".into())
+        );
+
+        assert_eq!(enrich_book(&book, &BookMetadata::default()), book);
+    }
+
+    /// Knows one book, by its ISBN.
+    struct Catalogue;
+
+    impl MetadataProvider for Catalogue {
+        fn lookup(&self, book: &Book) -> Result<Option<BookMetadata>, Box<dyn Error>> {
+            if book.field(field::ISBN) != Some("9780805211061".into()) {
+                return Ok(None);
+            }
+            Ok(Some(BookMetadata {
+                title: Some("The Castle".into()),
+                author: Some("Franz Kafka".into()),
+                year: Some(1926),
+                ..Default::default()
+            }))
+        }
+    }
+
+    #[test]
+    fn test_book_from_isbn() {
+        let isbn = Isbn::parse("0805211063").unwrap();
+        let book = book_from_isbn(&Catalogue, &isbn).unwrap().unwrap();
+        assert_eq!(
+            (book.author.as_str(), book.title.as_str()),
+            ("Franz Kafka", "The Castle")
+        );
+        assert_eq!(book.isbn(), Some(isbn));
+        assert_eq!(book.field(field::YEAR), Some("1926".into()));
+
+        let unknown = Isbn::parse("9791090636071").unwrap();
+        assert_eq!(book_from_isbn(&Catalogue, &unknown).unwrap(), None);
+    }
+}
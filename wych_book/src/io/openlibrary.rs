@@ -0,0 +1,200 @@
+use super::metadata::{BookMetadata, MetadataProvider};
+use crate::books::book::{field, Book};
+use serde::Deserialize;
+use std::{error::Error, time::Duration};
+
+/// Where the Open Library API is, unless the config names another server.
+pub const OPEN_LIBRARY_URL: &str = "https://openlibrary.org";
+
+/// Fields asked for from the search API, to keep responses small.
+const SEARCH_FIELDS: &str =
+    "title,author_name,number_of_pages_median,first_publish_year,publisher,subject";
+
+/// Looks up books with the Open Library search API.
+pub struct OpenLibrary {
+    base_url: String,
+    agent: ureq::Agent,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchResponse {
+    docs: Vec<SearchResult>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchResult {
+    title: Option<String>,
+    #[serde(default)]
+    author_name: Vec<String>,
+    number_of_pages_median: Option<u32>,
+    first_publish_year: Option<u16>,
+    #[serde(default)]
+    publisher: Vec<String>,
+    #[serde(default)]
+    subject: Vec<String>,
+}
+
+impl From<SearchResult> for BookMetadata {
+    fn from(result: SearchResult) -> Self {
+        BookMetadata {
+            title: result.title,
+            author: result.author_name.into_iter().next(),
+            pages: result.number_of_pages_median,
+            year: result.first_publish_year,
+            publisher: result.publisher.into_iter().next(),
+            subjects: result.subject,
+        }
+    }
+}
+
+impl OpenLibrary {
+    /// A client for the Open Library API at `base_url`, such as `OPEN_LIBRARY_URL`.
+    pub fn new(base_url: &str) -> Self {
+        let agent = ureq::AgentBuilder::new()
+            .timeout(Duration::from_secs(10))
+            .user_agent(concat!("wych_book/", env!("CARGO_PKG_VERSION")))
+            .build();
+
+        OpenLibrary {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            agent,
+        }
+    }
+
+    /// The first search result for the given query parameters.
+    fn search(&self, query: &[(&str, &str)]) -> Result<Option<BookMetadata>, Box<dyn Error>> {
+        let url = format!("{}/search.json", self.base_url);
+        let mut request = self
+            .agent
+            .get(&url)
+            .query("fields", SEARCH_FIELDS)
+            .query("limit", "1");
+        for (name, value) in query {
+            request = request.query(name, value);
+        }
+
+        let response = request.call().map_err(|e| match e {
+            ureq::Error::Status(status, _) => {
+                format!("Open Library at {} responded with {status}", self.base_url)
+            }
+            ureq::Error::Transport(transport) => {
+                let reason = transport.message().map(String::from);
+                let reason = reason.unwrap_or_else(|| transport.kind().to_string());
+                format!("Cannot reach Open Library at {}, {reason}", self.base_url)
+            }
+        })?;
+        let response: SearchResponse = serde_json::from_str(&response.into_string()?)
+            .map_err(|e| format!("Unexpected response from Open Library, {e}"))?;
+        Ok(response.docs.into_iter().next().map(BookMetadata::from))
+    }
+}
+
+impl MetadataProvider for OpenLibrary {
+    fn lookup(&self, book: &Book) -> Result<Option<BookMetadata>, Box<dyn Error>> {
+        if let Some(isbn) = book.field(field::ISBN) {
+            if let Some(metadata) = self.search(&[("isbn", &isbn)])? {
+                return Ok(Some(metadata));
+            }
+        }
+        if book.title.trim().is_empty() {
+            return Ok(None);
+        }
+        self.search(&[("title", &book.title), ("author", &book.author)])
+    }
+}
+
+//
+//
+//
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        io::{BufRead, BufReader, Write},
+        net::TcpListener,
+        thread,
+    };
+
+    /// Serve one JSON response for each expected request, returning the request lines received.
+    fn mock_server(responses: Vec<&'static str>) -> (String, thread::JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        let handle = thread::spawn(move || {
+            let mut requests = Vec::new();
+            for body in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                requests.push(line.trim().to_string());
+                // skip the rest of the headers
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    line.clear();
+                }
+
+                write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                )
+                .unwrap();
+            }
+            requests
+        });
+        (url, handle)
+    }
+
+    #[test]
+    fn test_lookup_by_isbn_then_title() {
+        let (url, server) = mock_server(vec![
+            r#"{"numFound": 0, "docs": []}"#,
+            r#"{"numFound": 1, "docs": [{"title": "The Castle", "author_name": ["Franz Kafka", "Max Brod"],
+                "first_publish_year": 1926, "number_of_pages_median": 316, "publisher": ["Schocken"],
+                "subject": ["Fiction", "Bureaucracy"]}]}"#,
+        ]);
+        let mut book = Book::new("franz kafka".into(), "The Castle".into(), 1);
+        book.custom
+            .insert(field::ISBN.into(), "9780805211061".into());
+
+        let metadata = OpenLibrary::new(&url).lookup(&book).unwrap().unwrap();
+        assert_eq!(
+            metadata,
+            BookMetadata {
+                title: Some("The Castle".into()),
+                author: Some("Franz Kafka".into()),
+                pages: Some(316),
+                year: Some(1926),
+                publisher: Some("Schocken".into()),
+                subjects: vec!["Fiction".into(), "Bureaucracy".into()],
+            }
+        );
+
+        let requests = server.join().unwrap();
+        assert!(requests[0].starts_with("GET /search.json?"));
+        assert!(requests[0].contains("isbn=9780805211061"));
+        assert!(requests[1].contains("title=The+Castle"));
+        assert!(requests[1].contains("author=franz"));
+    }
+
+    #[test]
+    fn test_lookup_unknown_book() {
+        let (url, server) = mock_server(vec![r#"{"numFound": 0, "docs": []}"#]);
+        let book = Book::new("Nobody".into(), "Nothing".into(), 1);
+        assert_eq!(OpenLibrary::new(&url).lookup(&book).unwrap(), None);
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_lookup_offline() {
+        // nothing is listening once the listener is dropped
+        let url = {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            format!("http://{}", listener.local_addr().unwrap())
+        };
+        let book = Book::new("Franz Kafka".into(), "The Castle".into(), 1);
+
+        let error = OpenLibrary::new(&url).lookup(&book).unwrap_err();
+        assert!(error.to_string().starts_with("Cannot reach Open Library"));
+    }
+}
@@ -0,0 +1,207 @@
+use crate::books::{
+    book::{field, status, Book},
+    book_records::BookRecords,
+};
+use std::error::Error;
+
+const TODO: &str = "TODO";
+const DONE: &str = "DONE";
+
+/// A list as an Org-mode file, with a TODO heading for each book.
+/// The author, weight and any other fields are kept in each heading's properties,
+/// with the other fields named as their columns are.
+/// Finished books are marked DONE.
+pub fn org_document(books: &BookRecords, name: &str) -> String {
+    let mut org = format!("#+TITLE: {}\n", one_line(name));
+
+    for book in books.records() {
+        let keyword = match book.field(field::STATUS).as_deref() {
+            Some(status::FINISHED) => DONE,
+            _ => TODO,
+        };
+        org.push_str(&format!("\n* {keyword} {}\n", one_line(&book.title)));
+        org.push_str("  :PROPERTIES:\n");
+        org.push_str(&format!("  :AUTHOR: {}\n", one_line(&book.author)));
+        org.push_str(&format!("  :WEIGHT: {}\n", book.weight));
+        for (name, value) in &book.custom {
+            org.push_str(&format!("  :{name}: {}\n", one_line(value)));
+        }
+        org.push_str("  :END:\n");
+    }
+    org
+}
+
+/// Read the books from an Org-mode file written by `org_document`.
+/// Each top-level heading is a book. Marking a heading DONE sets the book's status to finished,
+/// and marking it TODO again clears it. The AUTHOR, WEIGHT and STATUS properties can be in any case,
+/// and other properties keep the case of their names, to match the list's columns.
+pub fn read_org(contents: &str) -> Result<BookRecords, Box<dyn Error>> {
+    let mut books = Vec::new();
+    // the book being read, and whether its properties drawer is open
+    let mut current: Option<(Book, bool)> = None;
+
+    for (i, line) in contents.lines().enumerate() {
+        if let Some(heading) = line.strip_prefix("* ") {
+            books.extend(current.take().map(|(book, _)| book));
+
+            let (keyword, title) = match heading.trim().split_once(' ') {
+                Some((k, title)) if k == TODO || k == DONE => (Some(k), title.trim()),
+                _ if heading.trim() == TODO || heading.trim() == DONE => (Some(heading.trim()), ""),
+                _ => (None, heading.trim()),
+            };
+            let mut book = Book::new(String::new(), title.to_string(), 1);
+            if keyword == Some(DONE) {
+                book.custom
+                    .insert(field::STATUS.into(), status::FINISHED.into());
+            }
+            current = Some((book, false));
+            continue;
+        }
+
+        let Some((book, in_drawer)) = current.as_mut() else {
+            continue;
+        };
+        let line = line.trim();
+        if line.eq_ignore_ascii_case(":PROPERTIES:") {
+            *in_drawer = true;
+        } else if line.eq_ignore_ascii_case(":END:") {
+            *in_drawer = false;
+        } else if *in_drawer {
+            let property = line
+                .strip_prefix(':')
+                .and_then(|p| p.split_once(':'))
+                .ok_or_else(|| format!("Line {}: cannot read property {line}", i + 1))?;
+            let (name, value) = (property.0, property.1.trim());
+
+            match name.to_lowercase().as_str() {
+                "author" => book.author = value.to_string(),
+                "weight" => {
+                    book.weight = value
+                        .parse()
+                        .map_err(|e| format!("Line {}: invalid weight {value}, {e}", i + 1))?
+                }
+                field::STATUS => {
+                    // the heading's keyword wins, as it's what gets changed in Org-mode
+                    let done = book.custom.contains_key(field::STATUS);
+                    if !done && value != status::FINISHED && !value.is_empty() {
+                        book.custom.insert(field::STATUS.into(), value.to_string());
+                    }
+                }
+                _ if !value.is_empty() => {
+                    book.custom.insert(name.to_string(), value.to_string());
+                }
+                _ => (),
+            }
+        }
+    }
+    books.extend(current.map(|(book, _)| book));
+
+    if let Some(book) = books.iter().find(|b| b.author.is_empty()) {
+        return Err(format!("{} has no AUTHOR property", book.title).into());
+    }
+    Ok(books.into())
+}
+
+/// Org-mode headings and properties end at a line break.
+fn one_line(s: &str) -> String {
+    s.replace(['\r', '\n'], " ")
+}
+
+//
+//
+//
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn books_to_test() -> BookRecords {
+        let mut castle = Book::new("Franz Kafka".into(), "The Castle".into(), 3);
+        castle.custom.insert("recommended_by".into(), "Sam".into());
+        castle
+            .custom
+            .insert(field::STATUS.into(), status::PICKED.into());
+        let mut police = Book::new("Yōko Ogawa".into(), "The Memory Police".into(), 1);
+        police
+            .custom
+            .insert(field::STATUS.into(), status::FINISHED.into());
+
+        let mut books: BookRecords = vec![castle, police].into();
+        books.add_custom_column("recommended_by");
+        books.add_custom_column(field::STATUS);
+        books
+    }
+
+    #[test]
+    fn test_property_case() {
+        let mut book = Book::new("Franz Kafka".into(), "The Castle".into(), 1);
+        book.custom.insert("Shelf".into(), "Hall".into());
+        book.custom.insert("ISBN_note".into(), "reprint".into());
+        let books: BookRecords = vec![book].into();
+
+        let org = org_document(&books, "books");
+        assert!(org.contains("  :ISBN_note: reprint\n  :Shelf: Hall\n"));
+        assert_eq!(read_org(&org).unwrap().records(), books.records());
+    }
+
+    #[test]
+    fn test_org_document() {
+        let org = org_document(&books_to_test(), "books");
+        assert_eq!(
+            org,
+            "#+TITLE: books
+
+* TODO The Castle
+  :PROPERTIES:
+  :AUTHOR: Franz Kafka
+  :WEIGHT: 3
+  :recommended_by: Sam
+  :status: picked
+  :END:
+
+* DONE The Memory Police
+  :PROPERTIES:
+  :AUTHOR: Yōko Ogawa
+  :WEIGHT: 1
+  :status: finished
+  :END:
+"
+        );
+        assert_eq!(read_org(&org).unwrap().records(), books_to_test().records());
+    }
+
+    #[test]
+    fn test_read_org_status_changes() {
+        let org = "#+TITLE: books
+Some notes before the first book.
+
+* DONE The Castle
+  :PROPERTIES:
+  :AUTHOR: Franz Kafka
+  :STATUS: picked
+  :END:
+** Notes
+  A sub-heading is part of the book above.
+
+* TODO The Memory Police
+  :properties:
+  :Author: Yōko Ogawa
+  :Weight: 4
+  :Status: finished
+  :end:
+";
+        let books = read_org(org).unwrap();
+        let castle = books.get(0).unwrap();
+        assert_eq!(castle.field(field::STATUS), Some("finished".into()));
+        assert_eq!(castle.weight, 1);
+
+        let police = books.get(1).unwrap();
+        assert_eq!(police.author, "Yōko Ogawa");
+        assert_eq!(police.weight, 4);
+        assert_eq!(police.field(field::STATUS), None);
+
+        assert!(read_org("* TODO Dracula\n").is_err());
+        assert!(
+            read_org("* Dracula\n:PROPERTIES:\n:AUTHOR: Bram Stoker\n:WEIGHT: heavy\n").is_err()
+        );
+    }
+}
@@ -0,0 +1,149 @@
+use std::{
+    env,
+    error::Error,
+    ffi::OsString,
+    path::{Path, PathBuf},
+};
+
+/// Environment variable naming the wych_book directory, instead of finding it in the config directory.
+pub const HOME_VAR: &str = "WYCH_BOOK_HOME";
+const XDG_CONFIG_VAR: &str = "XDG_CONFIG_HOME";
+/// Config directory in the home directory, when `XDG_CONFIG_HOME` isn't set.
+const DEFAULT_CONFIG_DIR: &str = ".config";
+const APP_DIR: &str = "wych_book";
+
+pub const CONFIG_FILE: &str = "config.json";
+const LISTS_DIR: &str = "lists";
+const BACKUPS_DIR: &str = "backups";
+const JOURNAL_DIR: &str = "journal";
+const HISTORY_FILE: &str = "history.json";
+#[cfg(feature = "sqlite")]
+const DATABASE_FILE: &str = "wych_book.db";
+
+/// Where the config, lists, history and backups of a workspace are kept.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WychPaths {
+    dir: PathBuf,
+}
+
+impl WychPaths {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        WychPaths { dir: dir.into() }
+    }
+
+    /// Find the wych_book directory. Uses the directory given, such as by `--config-dir`,
+    /// then `$WYCH_BOOK_HOME`, then `wych_book` in `$XDG_CONFIG_HOME`, then `~/.config/wych_book`.
+    pub fn resolve(dir: Option<&Path>) -> Result<Self, Box<dyn Error>> {
+        WychPaths::resolve_from(
+            dir,
+            env::var_os(HOME_VAR),
+            env::var_os(XDG_CONFIG_VAR),
+            home::home_dir(),
+        )
+    }
+
+    fn resolve_from(
+        dir: Option<&Path>,
+        wych_home: Option<OsString>,
+        xdg_config: Option<OsString>,
+        home: Option<PathBuf>,
+    ) -> Result<Self, Box<dyn Error>> {
+        if let Some(dir) = dir {
+            return Ok(WychPaths::new(dir));
+        }
+        if let Some(dir) = wych_home.filter(|d| !d.is_empty()) {
+            return Ok(WychPaths::new(dir));
+        }
+        // the XDG spec says to ignore relative paths
+        if let Some(config) = xdg_config.map(PathBuf::from).filter(|d| d.is_absolute()) {
+            return Ok(WychPaths::new(config.join(APP_DIR)));
+        }
+        match home {
+            Some(home) => Ok(WychPaths::new(home.join(DEFAULT_CONFIG_DIR).join(APP_DIR))),
+            None => {
+                Err(format!("Cannot find home directory, please set $HOME or ${HOME_VAR}").into())
+            }
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn config_file(&self) -> PathBuf {
+        self.dir.join(CONFIG_FILE)
+    }
+
+    pub fn lists_dir(&self) -> PathBuf {
+        self.dir.join(LISTS_DIR)
+    }
+
+    pub fn journal_dir(&self) -> PathBuf {
+        self.dir.join(JOURNAL_DIR)
+    }
+
+    pub fn backups_dir(&self) -> PathBuf {
+        self.dir.join(BACKUPS_DIR)
+    }
+
+    /// File recording which books were picked and finished, across all lists.
+    pub fn history_file(&self) -> PathBuf {
+        self.dir.join(HISTORY_FILE)
+    }
+
+    #[cfg(feature = "sqlite")]
+    pub fn database_file(&self) -> PathBuf {
+        self.dir.join(DATABASE_FILE)
+    }
+}
+
+//
+//
+//
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve() {
+        let home = Some(PathBuf::from("/home/k"));
+        let var = |v: &str| Some(OsString::from(v));
+        let resolve = |dir: Option<&str>, wych_home, xdg_config| {
+            WychPaths::resolve_from(dir.map(Path::new), wych_home, xdg_config, home.clone())
+                .unwrap()
+        };
+
+        assert_eq!(
+            resolve(None, None, None).dir(),
+            Path::new("/home/k/.config/wych_book")
+        );
+        assert_eq!(
+            resolve(None, None, var("/home/k/xdg")).dir(),
+            Path::new("/home/k/xdg/wych_book")
+        );
+        assert_eq!(
+            resolve(None, var("/srv/books"), var("/home/k/xdg")).dir(),
+            Path::new("/srv/books")
+        );
+        assert_eq!(
+            resolve(Some("work"), var("/srv/books"), var("/home/k/xdg")).dir(),
+            Path::new("work")
+        );
+
+        // empty or relative variables are ignored
+        assert_eq!(
+            resolve(None, var(""), var("xdg")).dir(),
+            Path::new("/home/k/.config/wych_book")
+        );
+        assert!(WychPaths::resolve_from(None, None, None, None).is_err());
+    }
+
+    #[test]
+    fn test_paths() {
+        let paths = WychPaths::new("/srv/books");
+        assert_eq!(paths.config_file(), Path::new("/srv/books/config.json"));
+        assert_eq!(paths.lists_dir(), Path::new("/srv/books/lists"));
+        assert_eq!(paths.backups_dir(), Path::new("/srv/books/backups"));
+        assert_eq!(paths.history_file(), Path::new("/srv/books/history.json"));
+    }
+}
@@ -0,0 +1,269 @@
+use super::import::Importer;
+use crate::books::{book::Book, book_records::BookRecords};
+use std::{error::Error, path::Path};
+
+/// Patterns tried in order when none are given.
+pub const DEFAULT_PATTERNS: [&str; 4] = [
+    "\"{title}\" - {author}",
+    "{last}, {first} - {title}",
+    "{title} by {author}",
+    "{author} - {title}",
+];
+
+/// One piece of a pattern.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Text(Vec<char>),
+    Title,
+    Author,
+    /// An author's surname.
+    Last,
+    /// An author's forenames.
+    First,
+}
+
+/// A line pattern such as `{title} by {author}`, made of `{title}`, `{author}`, `{last}` and
+/// `{first}` placeholders and the text between them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pattern {
+    parts: Vec<Part>,
+}
+
+impl Pattern {
+    pub fn parse(pattern: &str) -> Result<Self, Box<dyn Error>> {
+        let invalid = |reason: &str| format!("Invalid pattern {pattern}, {reason}");
+        let mut parts = Vec::new();
+        let mut rest = pattern;
+
+        while let Some(start) = rest.find('{') {
+            let end = rest[start..]
+                .find('}')
+                .ok_or_else(|| invalid("a { is not closed"))?;
+            if start > 0 {
+                parts.push(Part::Text(rest[..start].chars().collect()));
+            } else if !parts.is_empty() {
+                return Err(invalid("placeholders must have text between them").into());
+            }
+            parts.push(match &rest[start + 1..start + end] {
+                "title" => Part::Title,
+                "author" => Part::Author,
+                "last" => Part::Last,
+                "first" => Part::First,
+                other => return Err(invalid(&format!("unknown placeholder {{{other}}}")).into()),
+            });
+            rest = &rest[start + end + 1..];
+        }
+        if !rest.is_empty() {
+            parts.push(Part::Text(rest.chars().collect()));
+        }
+
+        let count = |part: Part| parts.iter().filter(|p| **p == part).count();
+        if count(Part::Title) != 1 {
+            return Err(invalid("it needs one {title}").into());
+        }
+        match (count(Part::Author), count(Part::Last), count(Part::First)) {
+            (1, 0, 0) | (0, 1, 0) | (0, 1, 1) => Ok(Pattern { parts }),
+            _ => Err(invalid("it needs one {author}, or a {last} with an optional {first}").into()),
+        }
+    }
+
+    /// The book a line describes, if the line matches the pattern.
+    pub fn book(&self, line: &str) -> Option<Book> {
+        let line: Vec<char> = line.trim().chars().collect();
+        let values = match_parts(&self.parts, &line)?;
+
+        let value = |part: Part| {
+            self.parts
+                .iter()
+                .filter(|p| !matches!(p, Part::Text(_)))
+                .zip(&values)
+                .find(|(p, _)| **p == part)
+                .map(|(_, v)| v.trim().to_string())
+        };
+        let author = match (value(Part::Author), value(Part::Last), value(Part::First)) {
+            (Some(author), _, _) => author,
+            (None, Some(last), Some(first)) => format!("{first} {last}"),
+            (None, Some(last), None) => last,
+            (None, None, _) => return None,
+        };
+        let title = value(Part::Title)?;
+
+        if author.is_empty() || title.is_empty() {
+            return None;
+        }
+        Some(Book::new(author, title, 1))
+    }
+}
+
+/// The values of a pattern's placeholders in a line. Earlier placeholders take as much
+/// of the line as they can, so `{title} by {author}` reads `Stand by Me by Stephen King`.
+fn match_parts(parts: &[Part], line: &[char]) -> Option<Vec<String>> {
+    match parts {
+        [] => line.is_empty().then(Vec::new),
+        [Part::Text(text), rest @ ..] => {
+            let matched = line.len() >= text.len() && same_text(&line[..text.len()], text);
+            matched.then(|| match_parts(rest, &line[text.len()..]))?
+        }
+        [_, Part::Text(text), rest @ ..] => (1..=line.len().saturating_sub(text.len()))
+            .rev()
+            .filter(|&i| same_text(&line[i..i + text.len()], text))
+            .find_map(|i| {
+                let mut values = match_parts(rest, &line[i + text.len()..])?;
+                values.insert(0, line[..i].iter().collect());
+                Some(values)
+            }),
+        [_] => (!line.is_empty()).then(|| vec![line.iter().collect()]),
+        // placeholders always have text between them
+        [_, _, ..] => None,
+    }
+}
+
+/// Compare text, treating any kind of dash as the same, and any kind of double quote.
+fn same_text(a: &[char], b: &[char]) -> bool {
+    let normal = |c: char| match c {
+        '–' | '—' | '‒' | '―' => '-',
+        '“' | '”' | '„' | '«' | '»' => '"',
+        c => c.to_lowercase().next().unwrap_or(c),
+    };
+    a.len() == b.len() && a.iter().zip(b).all(|(x, y)| normal(*x) == normal(*y))
+}
+
+/// Remove a list bullet or number, such as `- `, `* ` or `3. `, from the start of a line.
+fn strip_bullet(line: &str) -> &str {
+    let line = line.trim();
+    for bullet in ["- ", "* ", "• "] {
+        if let Some(rest) = line.strip_prefix(bullet) {
+            return rest.trim_start();
+        }
+    }
+
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    match line[digits..].strip_prefix(['.', ')']) {
+        Some(rest) if digits > 0 && rest.starts_with(' ') => rest.trim_start(),
+        _ => line,
+    }
+}
+
+/// Reads books from free-form lines of text, such as a list pasted from an email.
+#[derive(Debug, Clone)]
+pub struct PlainText {
+    patterns: Vec<Pattern>,
+}
+
+impl PlainText {
+    /// A reader that tries each pattern in order. Uses `DEFAULT_PATTERNS` if none are given.
+    pub fn new(patterns: &[String]) -> Result<Self, Box<dyn Error>> {
+        let defaults = DEFAULT_PATTERNS.map(String::from);
+        let patterns = if patterns.is_empty() {
+            &defaults[..]
+        } else {
+            patterns
+        };
+        Ok(PlainText {
+            patterns: patterns
+                .iter()
+                .map(|p| Pattern::parse(p))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    /// Read a book from each line that matches a pattern, ignoring blank lines.
+    /// Returns the books along with the lines that didn't match, and their line numbers.
+    pub fn parse(&self, text: &str) -> (BookRecords, Vec<(usize, String)>) {
+        let mut books = BookRecords::default();
+        let mut unmatched = Vec::new();
+
+        for (i, line) in text.lines().enumerate() {
+            let line = strip_bullet(line);
+            if line.is_empty() {
+                continue;
+            }
+            match self.patterns.iter().find_map(|p| p.book(line)) {
+                Some(book) => books.push(book),
+                None => unmatched.push((i + 1, line.to_string())),
+            }
+        }
+        (books, unmatched)
+    }
+}
+
+impl Default for PlainText {
+    fn default() -> Self {
+        PlainText::new(&[]).expect("default patterns are valid")
+    }
+}
+
+impl Importer for PlainText {
+    /// Lines that don't match any pattern are skipped.
+    fn import(&self, contents: &[u8], source: &Path) -> Result<BookRecords, Box<dyn Error>> {
+        let text = std::str::from_utf8(contents)
+            .map_err(|e| format!("{} is not valid UTF-8, {e}", source.display()))?;
+        Ok(self.parse(text).0)
+    }
+}
+
+//
+//
+//
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn titles_and_authors(books: &BookRecords) -> Vec<(&str, &str)> {
+        books
+            .records()
+            .iter()
+            .map(|b| (b.title.as_str(), b.author.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn test_default_patterns() {
+        let text = "Some books for the club:
+
+- The Castle by Franz Kafka
+2. Kafka, Franz - The Trial
+* “The Memory Police” — Ogawa
+Stand by Me by Stephen King
+Bram Stoker – Dracula
+Kafka, Franz - Letters to Milena, Volume 2
+";
+        let (books, unmatched) = PlainText::default().parse(text);
+        assert_eq!(
+            titles_and_authors(&books),
+            vec![
+                ("The Castle", "Franz Kafka"),
+                ("The Trial", "Franz Kafka"),
+                ("The Memory Police", "Ogawa"),
+                ("Stand by Me", "Stephen King"),
+                ("Dracula", "Bram Stoker"),
+                ("Letters to Milena, Volume 2", "Franz Kafka"),
+            ]
+        );
+        assert_eq!(unmatched, vec![(1, "Some books for the club:".to_string())]);
+    }
+
+    #[test]
+    fn test_custom_patterns() {
+        let patterns = vec!["{title} ({author})".to_string(), "{title}/{last}".into()];
+        let text = PlainText::new(&patterns).unwrap();
+        let (books, unmatched) =
+            text.parse("The Castle (Franz Kafka)\nDracula/Stoker\nThe Castle by Franz Kafka\n");
+        assert_eq!(
+            titles_and_authors(&books),
+            vec![("The Castle", "Franz Kafka"), ("Dracula", "Stoker")]
+        );
+        assert_eq!(unmatched.len(), 1);
+
+        for pattern in [
+            "{title}",
+            "{title}{author}",
+            "{title} by {name}",
+            "{title} by {author",
+            "{first} {title}",
+            "{author}: {title} ({last})",
+        ] {
+            assert!(Pattern::parse(pattern).is_err(), "{pattern}");
+        }
+    }
+}
@@ -0,0 +1,299 @@
+use super::files::write_atomic;
+use crate::books::{
+    book_records::BookRecords,
+    history::{Event, History, HistoryEntry},
+    render::{escape_html, html_document, html_table},
+};
+use std::{
+    collections::BTreeSet,
+    error::Error,
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+/// Number of picks in the Atom feed, and on the index page.
+const FEED_ENTRIES: usize = 20;
+const INDEX_PICKS: usize = 5;
+
+const LISTS_DIR: &str = "lists";
+const TIMELINE_PAGE: &str = "finished.html";
+const FEED_FILE: &str = "feed.xml";
+
+/// Write a static website of the lists and reading history into `out_dir`:
+/// an index of lists, a page per list, a timeline of finished books and an Atom feed of picks.
+/// `base_url` is where the site will be hosted, which makes the feed's links absolute.
+/// Returns the files written.
+pub fn publish(
+    out_dir: &Path,
+    lists: &[(String, BookRecords)],
+    history: &History,
+    base_url: Option<&str>,
+) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    fs::create_dir_all(out_dir.join(LISTS_DIR))
+        .map_err(|e| format!("Cannot create {}, {e}", out_dir.display()))?;
+
+    let slugs = slugs(lists.iter().map(|(name, _)| name.as_str()));
+    let mut pages = vec![(
+        PathBuf::from("index.html"),
+        index_page(lists, &slugs, history),
+    )];
+    for ((name, books), slug) in lists.iter().zip(&slugs) {
+        let body = format!(
+            "  <p><a href=\"../index.html\">All lists</a></p>\n{}",
+            html_table(books)
+        );
+        pages.push((list_page_path(slug), html_document(name, &body)));
+    }
+    pages.push((PathBuf::from(TIMELINE_PAGE), timeline_page(history)));
+    pages.push((PathBuf::from(FEED_FILE), atom_feed(history, base_url)));
+
+    let mut written = Vec::new();
+    for (path, contents) in pages {
+        let filename = out_dir.join(path);
+        write_atomic(&filename, |file| Ok(file.write_all(contents.as_bytes())?))?;
+        written.push(filename);
+    }
+    Ok(written)
+}
+
+fn list_page_path(slug: &str) -> PathBuf {
+    Path::new(LISTS_DIR).join(format!("{slug}.html"))
+}
+
+/// A file name for each list, made of lowercase letters, digits and hyphens.
+/// Names that would clash get a number on the end.
+fn slugs<'a>(names: impl Iterator<Item = &'a str>) -> Vec<String> {
+    let mut used = BTreeSet::new();
+    names
+        .map(|name| {
+            let slug: String = name
+                .to_lowercase()
+                .chars()
+                .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+                .collect();
+            let slug = slug
+                .split('-')
+                .filter(|s| !s.is_empty())
+                .collect::<Vec<_>>()
+                .join("-");
+            let slug = if slug.is_empty() { "list".into() } else { slug };
+
+            let mut unique = slug.clone();
+            let mut n = 2;
+            while !used.insert(unique.clone()) {
+                unique = format!("{slug}-{n}");
+                n += 1;
+            }
+            unique
+        })
+        .collect()
+}
+
+fn index_page(lists: &[(String, BookRecords)], slugs: &[String], history: &History) -> String {
+    let lists: String = lists
+        .iter()
+        .zip(slugs)
+        .map(|((name, books), slug)| {
+            format!(
+                "    <li><a href=\"{}\">{}</a> ({} books)</li>\n",
+                list_page_path(slug).display(),
+                escape_html(name),
+                books.records().len()
+            )
+        })
+        .collect();
+
+    let picks: String = history
+        .recent(Event::Picked, INDEX_PICKS)
+        .iter()
+        .map(|e| format!("    <li>{}</li>\n", entry_html(e)))
+        .collect();
+    let picks = if picks.is_empty() {
+        "  <p>Nothing picked yet.</p>\n".to_string()
+    } else {
+        format!("  <ul>\n{picks}  </ul>\n")
+    };
+
+    let body = format!(
+        "  <h2>Lists</h2>\n  <ul>\n{lists}  </ul>\n  <h2>Recent picks</h2>\n{picks}  <p><a href=\"{TIMELINE_PAGE}\">Finished books</a> · <a href=\"{FEED_FILE}\">Feed of picks</a></p>\n"
+    );
+    html_document("Reading Lists", &body)
+}
+
+/// Finished books, most recent first, under a heading for each month.
+fn timeline_page(history: &History) -> String {
+    let mut body = String::from("  <p><a href=\"index.html\">All lists</a></p>\n");
+    let mut month = String::new();
+
+    let finished = history.recent(Event::Finished, usize::MAX);
+    for entry in &finished {
+        let entry_month = entry.time.format("%B %Y").to_string();
+        if entry_month != month {
+            if !month.is_empty() {
+                body.push_str("  </ul>\n");
+            }
+            body.push_str(&format!("  <h2>{entry_month}</h2>\n  <ul>\n"));
+            month = entry_month;
+        }
+        body.push_str(&format!(
+            "    <li><time datetime=\"{}\">{}</time> {}</li>\n",
+            entry.time.format("%Y-%m-%d"),
+            entry.time.format("%-d %b"),
+            entry_html(entry)
+        ));
+    }
+
+    if finished.is_empty() {
+        body.push_str("  <p>No finished books yet.</p>\n");
+    } else {
+        body.push_str("  </ul>\n");
+    }
+    html_document("Finished Books", &body)
+}
+
+fn entry_html(entry: &HistoryEntry) -> String {
+    format!(
+        "<cite>{}</cite> by {} <small>({})</small>",
+        escape_html(&entry.title),
+        escape_html(&entry.author),
+        escape_html(&entry.list)
+    )
+}
+
+/// An Atom feed of the most recent picks.
+fn atom_feed(history: &History, base_url: Option<&str>) -> String {
+    let picks = history.recent(Event::Picked, FEED_ENTRIES);
+    let updated = picks.first().map(|e| e.time).unwrap_or_default();
+    let base_url = base_url.map(|u| u.trim_end_matches('/'));
+
+    let link = match base_url {
+        Some(url) => format!("  <link href=\"{}/index.html\"/>\n", escape_html(url)),
+        None => String::new(),
+    };
+    let id = match base_url {
+        Some(url) => format!("{url}/{FEED_FILE}"),
+        None => "urn:wych-book:picks".to_string(),
+    };
+
+    let entries: String = picks
+        .iter()
+        .map(|e| {
+            format!(
+                "  <entry>\n    <title>{} by {}</title>\n    <id>urn:wych-book:pick:{}</id>\n    <updated>{}</updated>\n    <author><name>{}</name></author>\n    <summary>Picked from {}</summary>\n  </entry>\n",
+                escape_html(&e.title),
+                escape_html(&e.author),
+                e.time.timestamp_millis(),
+                e.time.to_rfc3339(),
+                escape_html(&e.author),
+                escape_html(&e.list)
+            )
+        })
+        .collect();
+
+    format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<feed xmlns=\"http://www.w3.org/2005/Atom\">\n  <title>Recent picks</title>\n  <id>{}</id>\n  <updated>{}</updated>\n{link}{entries}</feed>\n",
+        escape_html(&id),
+        updated.to_rfc3339()
+    )
+}
+
+//
+//
+//
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::books::book::Book;
+    use chrono::{TimeZone, Utc};
+    use tempdir::TempDir;
+
+    fn history() -> History {
+        let mut history = History::default();
+        let castle = Book::new("Franz Kafka".into(), "The Castle".into(), 1);
+        let dracula = Book::new("Bram Stoker".into(), "Dracula".into(), 1);
+        let time = |m, d| Utc.with_ymd_and_hms(2024, m, d, 9, 30, 0).unwrap();
+
+        history.record_at(time(1, 5), Event::Picked, "books", &castle);
+        history.record_at(time(2, 10), Event::Finished, "books", &castle);
+        history.record_at(time(3, 1), Event::Picked, "Book Club!", &dracula);
+        history.record_at(time(3, 20), Event::Finished, "Book Club!", &dracula);
+        history
+    }
+
+    #[test]
+    fn test_publish() {
+        let temp_dir = TempDir::new("wych_book_tests").unwrap();
+        let lists = vec![
+            (
+                "books".to_string(),
+                vec![Book::new(
+                    "Yōko Ogawa".into(),
+                    "The Memory Police".into(),
+                    1,
+                )]
+                .into(),
+            ),
+            ("Book Club!".to_string(), BookRecords::default()),
+        ];
+
+        let written = publish(
+            temp_dir.path(),
+            &lists,
+            &history(),
+            Some("https://example.com/club/"),
+        )
+        .unwrap();
+        assert_eq!(written.len(), 5);
+
+        let read = |path: &str| fs::read_to_string(temp_dir.path().join(path)).unwrap();
+        let index = read("index.html");
+        assert!(index.contains("<a href=\"lists/books.html\">books</a> (1 books)"));
+        assert!(index.contains("<a href=\"lists/book-club.html\">Book Club!</a> (0 books)"));
+        assert!(index.find("Dracula").unwrap() < index.find("The Castle").unwrap());
+
+        assert!(read("lists/books.html").contains("<td>The Memory Police</td>"));
+
+        let timeline = read("finished.html");
+        assert!(
+            timeline.find("<h2>March 2024</h2>").unwrap()
+                < timeline.find("<h2>February 2024</h2>").unwrap()
+        );
+        assert!(timeline
+            .contains("<time datetime=\"2024-02-10\">10 Feb</time> <cite>The Castle</cite>"));
+
+        let feed = read("feed.xml");
+        assert!(feed.contains("<id>https://example.com/club/feed.xml</id>"));
+        assert!(feed.contains("<updated>2024-03-01T09:30:00+00:00</updated>"));
+        assert!(feed.contains("<title>Dracula by Bram Stoker</title>"));
+        assert_eq!(feed.matches("<entry>").count(), 2);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_publish_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        // pages can be served by a web server running as another user, as the umask allows
+        let temp_dir = TempDir::new("wych_book_tests").unwrap();
+        let created = temp_dir.path().join("created.html");
+        fs::File::create(&created).unwrap();
+        let mode = |path: &Path| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+
+        let site = temp_dir.path().join("site");
+        let lists = vec![("books".to_string(), BookRecords::default())];
+        let written = publish(&site, &lists, &history(), None).unwrap();
+        for path in written {
+            assert_eq!(mode(&path), mode(&created), "{}", path.display());
+        }
+    }
+
+    #[test]
+    fn test_slugs() {
+        let names = ["To Read", "to-read", "", "Ünïcode"];
+        assert_eq!(
+            slugs(names.into_iter()),
+            vec!["to-read", "to-read-2", "list", "n-code"]
+        );
+    }
+}
@@ -0,0 +1,303 @@
+use crate::books::{
+    book::{field, Book},
+    book_records::BookRecords,
+};
+use std::{
+    collections::BTreeSet,
+    error::Error,
+    fs::{self, File},
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+/// Author given to an ebook that doesn't name one.
+pub const UNKNOWN_AUTHOR: &str = "Unknown";
+
+const DUBLIN_CORE: &str = "http://purl.org/dc/elements/1.1/";
+
+/// Title, author and page count read from an ebook file.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct EbookMetadata {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub pages: Option<u32>,
+}
+
+/// Books found by scanning a directory of ebooks.
+#[derive(Debug, Default)]
+pub struct Scan {
+    /// Books that aren't on the list yet.
+    pub books: BookRecords,
+    /// Books already on the list without a file, with the file found for them, and their indexes.
+    pub found: Vec<(usize, Book)>,
+    /// A warning for each file whose metadata couldn't be read, which is named after the file instead.
+    pub warnings: Vec<String>,
+}
+
+/// Propose a book for each EPUB and PDF file in a directory and its subdirectories.
+/// Files already on the list are left out, as are books already on it with the same author and title,
+/// which are given the file instead if they don't have one.
+pub fn scan_directory(dir: &Path, existing: &BookRecords) -> Result<Scan, Box<dyn Error>> {
+    let dir = dir
+        .canonicalize()
+        .map_err(|e| format!("Cannot open {}, {e}", dir.display()))?;
+    let known: BTreeSet<String> = existing
+        .records()
+        .iter()
+        .filter_map(|b| b.field(field::FILE))
+        .collect();
+
+    let mut books = Vec::new();
+    let mut found: Vec<(usize, Book)> = Vec::new();
+    let mut warnings = Vec::new();
+    for path in ebook_files(&dir)? {
+        let file = path.display().to_string();
+        if known.contains(&file) {
+            continue;
+        }
+
+        let metadata = match read_ebook_metadata(&path) {
+            Ok(metadata) if metadata.title.is_none() => {
+                warnings.push(format!("{file}: no title, using the file name"));
+                metadata
+            }
+            Ok(metadata) => metadata,
+            Err(e) => {
+                warnings.push(format!("{file}: {e}, using the file name"));
+                EbookMetadata::default()
+            }
+        };
+
+        let title = metadata.title.unwrap_or_else(|| {
+            path.file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into()
+        });
+        let author = metadata
+            .author
+            .unwrap_or_else(|| UNKNOWN_AUTHOR.to_string());
+        let mut book = Book::new(author, title, 1);
+        if let Some(index) = existing.position(&book) {
+            let known = &existing.records()[index];
+            if known.field(field::FILE).is_none() && !found.iter().any(|(i, _)| *i == index) {
+                let mut known = known.clone();
+                known.custom.insert(field::FILE.to_string(), file);
+                found.push((index, known));
+            }
+            continue;
+        }
+
+        book.custom.insert(field::FILE.to_string(), file);
+        if let Some(pages) = metadata.pages.filter(|p| *p > 0) {
+            book.custom
+                .insert(field::PAGES.to_string(), pages.to_string());
+        }
+        books.push(book);
+    }
+
+    Ok(Scan {
+        books: BookRecords::from(books),
+        found,
+        warnings,
+    })
+}
+
+/// Every EPUB and PDF file under a directory, in path order.
+fn ebook_files(dir: &Path) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            files.extend(ebook_files(&path)?);
+        } else if ebook_extension(&path).is_some() {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+fn ebook_extension(path: &Path) -> Option<String> {
+    let extension = path.extension()?.to_string_lossy().to_lowercase();
+    ["epub", "pdf"]
+        .contains(&extension.as_str())
+        .then_some(extension)
+}
+
+/// Read the metadata of an EPUB or PDF file.
+pub fn read_ebook_metadata(path: &Path) -> Result<EbookMetadata, Box<dyn Error>> {
+    match ebook_extension(path).as_deref() {
+        Some("epub") => read_epub_metadata(path),
+        Some("pdf") => read_pdf_metadata(path),
+        _ => Err(format!("{} is not an EPUB or PDF file", path.display()).into()),
+    }
+}
+
+/// Read the title and first author from an EPUB's package (OPF) document.
+fn read_epub_metadata(path: &Path) -> Result<EbookMetadata, Box<dyn Error>> {
+    let mut archive = zip::ZipArchive::new(File::open(path)?)?;
+    let read_entry = |archive: &mut zip::ZipArchive<File>, name: &str| {
+        let mut contents = String::new();
+        archive
+            .by_name(name)
+            .map_err(|e| format!("cannot read {name}, {e}"))?
+            .read_to_string(&mut contents)?;
+        Ok::<_, Box<dyn Error>>(contents)
+    };
+
+    // the container names the package document, which holds the metadata
+    let container = read_entry(&mut archive, "META-INF/container.xml")?;
+    let container = parse_xml(&container)?;
+    let package_path = container
+        .descendants()
+        .find(|n| n.has_tag_name("rootfile"))
+        .and_then(|n| n.attribute("full-path"))
+        .ok_or("no package document in META-INF/container.xml")?;
+
+    let package = read_entry(&mut archive, package_path)?;
+    let package = parse_xml(&package)?;
+    let dublin_core = |name: &str| {
+        package
+            .descendants()
+            .filter(|n| n.has_tag_name((DUBLIN_CORE, name)))
+            .filter_map(|n| n.text())
+            .map(|t| t.trim().to_string())
+            .find(|t| !t.is_empty())
+    };
+
+    Ok(EbookMetadata {
+        title: dublin_core("title"),
+        author: dublin_core("creator"),
+        pages: None,
+    })
+}
+
+/// Parse an XML document, which may have a DTD as older EPUBs often do.
+fn parse_xml(text: &str) -> Result<roxmltree::Document<'_>, roxmltree::Error> {
+    let options = roxmltree::ParsingOptions {
+        allow_dtd: true,
+        ..Default::default()
+    };
+    roxmltree::Document::parse_with_options(text, options)
+}
+
+/// Read the title and author from a PDF's document information dictionary.
+fn read_pdf_metadata(path: &Path) -> Result<EbookMetadata, Box<dyn Error>> {
+    let metadata = lopdf::Document::load_metadata(path)?;
+    let text = |t: Option<String>| t.map(|t| t.trim().to_string()).filter(|t| !t.is_empty());
+
+    Ok(EbookMetadata {
+        title: text(metadata.title),
+        author: text(metadata.author),
+        pages: Some(metadata.page_count),
+    })
+}
+
+//
+//
+//
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lopdf::{dictionary, Object};
+    use std::io::Write;
+    use tempdir::TempDir;
+    use zip::write::SimpleFileOptions;
+
+    fn write_epub(path: &Path, title: &str, author: &str) {
+        let mut zip = zip::ZipWriter::new(File::create(path).unwrap());
+        let options = SimpleFileOptions::default();
+        zip.start_file("mimetype", options).unwrap();
+        zip.write_all(b"application/epub+zip").unwrap();
+        zip.start_file("META-INF/container.xml", options).unwrap();
+        zip.write_all(br#"<?xml version="1.0"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles><rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/></rootfiles>
+</container>"#).unwrap();
+        zip.start_file("OEBPS/content.opf", options).unwrap();
+        write!(
+            zip,
+            r#"<?xml version="1.0"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <dc:title>{title}</dc:title>
+    <dc:creator>{author}</dc:creator>
+  </metadata>
+</package>"#
+        )
+        .unwrap();
+        zip.finish().unwrap();
+    }
+
+    fn write_pdf(path: &Path, title: &str) {
+        let mut pdf = lopdf::Document::with_version("1.5");
+        let pages =
+            pdf.add_object(dictionary! { "Type" => "Pages", "Kids" => vec![], "Count" => 0 });
+        let catalog = pdf.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages });
+        let info = pdf.add_object(dictionary! { "Title" => Object::string_literal(title) });
+        pdf.trailer.set("Root", catalog);
+        pdf.trailer.set("Info", info);
+        pdf.save(path).unwrap();
+    }
+
+    #[test]
+    fn test_read_ebook_metadata() {
+        let temp_dir = TempDir::new("wych_book_tests").unwrap();
+        let epub = temp_dir.path().join("castle.epub");
+        write_epub(&epub, "The Castle", "Franz Kafka");
+        let pdf = temp_dir.path().join("dracula.PDF");
+        write_pdf(&pdf, "Dracula");
+
+        let metadata = read_ebook_metadata(&epub).unwrap();
+        assert_eq!(metadata.title, Some("The Castle".into()));
+        assert_eq!(metadata.author, Some("Franz Kafka".into()));
+
+        let metadata = read_ebook_metadata(&pdf).unwrap();
+        assert_eq!(metadata.title, Some("Dracula".into()));
+        assert_eq!(metadata.author, None);
+    }
+
+    #[test]
+    fn test_scan_directory() {
+        let temp_dir = TempDir::new("wych_book_tests").unwrap();
+        let nested = temp_dir.path().join("kafka");
+        fs::create_dir(&nested).unwrap();
+        write_epub(&nested.join("castle.epub"), "The Castle", "Franz Kafka");
+        write_pdf(&temp_dir.path().join("dracula.pdf"), "Dracula");
+        fs::write(temp_dir.path().join("broken.epub"), "not a zip").unwrap();
+        fs::write(temp_dir.path().join("notes.txt"), "not an ebook").unwrap();
+
+        let Scan {
+            books, warnings, ..
+        } = scan_directory(temp_dir.path(), &BookRecords::default()).unwrap();
+        let titles: Vec<&str> = books.records().iter().map(|b| b.title.as_str()).collect();
+        assert_eq!(titles, vec!["broken", "Dracula", "The Castle"]);
+        assert_eq!(books.get(0).unwrap().author, UNKNOWN_AUTHOR);
+        assert_eq!(warnings.len(), 1);
+
+        let castle = books.get(2).unwrap();
+        let castle_file = nested.canonicalize().unwrap().join("castle.epub");
+        assert_eq!(
+            castle.field(field::FILE),
+            Some(castle_file.display().to_string())
+        );
+
+        // files already on the list are left out
+        let rescan = scan_directory(temp_dir.path(), &books).unwrap();
+        assert!(rescan.books.records().is_empty());
+        assert!(rescan.found.is_empty());
+
+        // books already on the list are given the file they were found in
+        let existing: BookRecords =
+            vec![Book::new("franz kafka".into(), "The Castle".into(), 4)].into();
+        let rescan = scan_directory(temp_dir.path(), &existing).unwrap();
+        assert_eq!(rescan.books.records().len(), 2);
+        let (index, castle) = &rescan.found[0];
+        assert_eq!((*index, castle.weight), (0, 4));
+        assert_eq!(
+            castle.field(field::FILE),
+            Some(castle_file.display().to_string())
+        );
+    }
+}
@@ -0,0 +1,129 @@
+use super::csv::{read_csv_bytes, write_csv_bytes};
+use crate::books::book_records::BookRecords;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use flate2::{read::DeflateDecoder, write::DeflateEncoder, Compression};
+use qrcode::{render::unicode::Dense1x2, EcLevel, QrCode};
+use std::{
+    error::Error,
+    io::{Read, Write},
+    path::Path,
+};
+
+/// Start of every share code, naming the version of the encoding.
+const PREFIX: &str = "WYCH1-";
+
+/// Bytes of CRC-32 checksum at the end of a decoded share code.
+const CHECKSUM_LEN: usize = 4;
+
+/// A list as a short code that can be pasted into a chat, such as `WYCH1-JcyxCoAg...`.
+/// The list's name and CSV are compressed, followed by a checksum, and written in URL-safe base64.
+pub fn share_code(name: &str, books: &BookRecords) -> Result<String, Box<dyn Error>> {
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::best());
+    writeln!(encoder, "{}", name.replace(['\r', '\n'], " "))?;
+    encoder.write_all(&write_csv_bytes(books)?)?;
+    let mut bytes = encoder.finish()?;
+    bytes.extend(crc32fast::hash(&bytes).to_be_bytes());
+
+    Ok(format!("{PREFIX}{}", URL_SAFE_NO_PAD.encode(bytes)))
+}
+
+/// Read the list's name and books from a share code. Any whitespace in the code is ignored,
+/// in case it was wrapped across lines when pasted.
+pub fn read_share_code(code: &str) -> Result<(String, BookRecords), Box<dyn Error>> {
+    let code: String = code.split_whitespace().collect();
+    let encoded = code
+        .strip_prefix(PREFIX)
+        .ok_or("Not a wych share code, it should start with WYCH1-")?;
+    let damaged = "Share code is damaged, check it was copied in full";
+
+    let bytes = URL_SAFE_NO_PAD.decode(encoded).map_err(|_| damaged)?;
+    if bytes.len() < CHECKSUM_LEN {
+        return Err(damaged.into());
+    }
+    let (compressed, checksum) = bytes.split_at(bytes.len() - CHECKSUM_LEN);
+    if crc32fast::hash(compressed).to_be_bytes() != checksum {
+        return Err(damaged.into());
+    }
+
+    let mut contents = Vec::new();
+    DeflateDecoder::new(compressed)
+        .read_to_end(&mut contents)
+        .map_err(|_| damaged)?;
+    let newline = contents.iter().position(|b| *b == b'\n').ok_or(damaged)?;
+    let name = String::from_utf8(contents[..newline].to_vec()).map_err(|_| damaged)?;
+    let books = read_csv_bytes(&contents[newline + 1..], Path::new("share code"))?;
+    Ok((name, books))
+}
+
+/// A share code as a QR code drawn with block characters, two rows to a line, for the terminal.
+pub fn qr_code(code: &str) -> Result<String, Box<dyn Error>> {
+    let qr = QrCode::with_error_correction_level(code, EcLevel::L)
+        .map_err(|e| format!("Cannot make a QR code, {e}. The list may be too long"))?;
+    Ok(qr
+        .render::<Dense1x2>()
+        .dark_color(Dense1x2::Light)
+        .light_color(Dense1x2::Dark)
+        .build())
+}
+
+//
+//
+//
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::books::book::Book;
+
+    fn books_to_test() -> BookRecords {
+        let mut castle = Book::new("Franz Kafka".into(), "The Castle".into(), 3);
+        castle
+            .custom
+            .insert("recommended_by".into(), "Sam, at the café".into());
+        let mut books: BookRecords = vec![
+            castle,
+            Book::new("Yōko Ogawa".into(), "The Memory Police".into(), 1),
+        ]
+        .into();
+        books.add_custom_column("recommended_by");
+        books
+    }
+
+    #[test]
+    fn test_share_code() {
+        let code = share_code("book club", &books_to_test()).unwrap();
+        assert!(code.starts_with(PREFIX));
+        assert!(code
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+
+        let (name, books) = read_share_code(&code).unwrap();
+        assert_eq!(name, "book club");
+        assert_eq!(books, books_to_test());
+
+        // wrapped when pasted
+        let wrapped = format!("  {}\n{}\n", &code[..20], &code[20..]);
+        assert_eq!(read_share_code(&wrapped).unwrap().1, books_to_test());
+    }
+
+    #[test]
+    fn test_damaged_share_code() {
+        let code = share_code("books", &books_to_test()).unwrap();
+
+        assert!(read_share_code(&code[PREFIX.len()..]).is_err());
+        assert!(read_share_code(&code[..code.len() - 3]).is_err());
+        assert!(read_share_code(PREFIX).is_err());
+
+        let mut changed = code.into_bytes();
+        let i = changed.len() / 2;
+        changed[i] = if changed[i] == b'A' { b'B' } else { b'A' };
+        assert!(read_share_code(&String::from_utf8(changed).unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_qr_code() {
+        let code = share_code("books", &books_to_test()).unwrap();
+        let qr = qr_code(&code).unwrap();
+        assert!(qr.lines().count() > 10);
+        assert!(qr_code(&"A".repeat(5000)).is_err());
+    }
+}
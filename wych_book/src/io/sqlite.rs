@@ -0,0 +1,286 @@
+use super::store::ListStore;
+use crate::books::{book::Book, book_records::BookRecords, journal::Journal};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::{error::Error, path::Path};
+
+const SCHEMA_VERSION: &str = "1";
+
+const SCHEMA: &str = "
+    PRAGMA foreign_keys = ON;
+
+    CREATE TABLE IF NOT EXISTS lists (
+        id INTEGER PRIMARY KEY,
+        name TEXT NOT NULL UNIQUE,
+        -- JSON array of user-defined column names, in display order
+        columns TEXT NOT NULL DEFAULT '[]'
+    );
+
+    CREATE TABLE IF NOT EXISTS books (
+        id INTEGER PRIMARY KEY,
+        list_id INTEGER NOT NULL REFERENCES lists(id) ON DELETE CASCADE,
+        position INTEGER NOT NULL,
+        author TEXT NOT NULL,
+        title TEXT NOT NULL,
+        weight INTEGER NOT NULL
+    );
+    CREATE UNIQUE INDEX IF NOT EXISTS books_list_position ON books(list_id, position);
+    CREATE INDEX IF NOT EXISTS books_title ON books(title);
+    CREATE INDEX IF NOT EXISTS books_author ON books(author);
+
+    CREATE TABLE IF NOT EXISTS book_fields (
+        book_id INTEGER NOT NULL REFERENCES books(id) ON DELETE CASCADE,
+        name TEXT NOT NULL,
+        value TEXT NOT NULL,
+        PRIMARY KEY (book_id, name)
+    );
+
+    CREATE TABLE IF NOT EXISTS history (
+        list_id INTEGER PRIMARY KEY REFERENCES lists(id) ON DELETE CASCADE,
+        journal TEXT NOT NULL
+    );
+
+    CREATE TABLE IF NOT EXISTS metadata (
+        key TEXT PRIMARY KEY,
+        value TEXT NOT NULL
+    );
+";
+
+/// All lists, their journals and other metadata kept in a single SQLite database.
+#[derive(Debug)]
+pub struct SqliteStore {
+    connection: Connection,
+}
+
+impl SqliteStore {
+    /// Open the database, creating it and its tables if needed.
+    pub fn open(filename: &Path) -> Result<Self, Box<dyn Error>> {
+        let connection = Connection::open(filename)
+            .map_err(|e| format!("Cannot open database {}, {e}", filename.display()))?;
+        SqliteStore::from_connection(connection)
+    }
+
+    /// Open a database that only exists in memory.
+    pub fn open_in_memory() -> Result<Self, Box<dyn Error>> {
+        SqliteStore::from_connection(Connection::open_in_memory()?)
+    }
+
+    fn from_connection(connection: Connection) -> Result<Self, Box<dyn Error>> {
+        connection.execute_batch(SCHEMA)?;
+        let mut store = SqliteStore { connection };
+        store.set_metadata("schema_version", SCHEMA_VERSION)?;
+        Ok(store)
+    }
+
+    pub fn get_metadata(&self, key: &str) -> Result<Option<String>, Box<dyn Error>> {
+        let value = self
+            .connection
+            .query_row("SELECT value FROM metadata WHERE key = ?1", [key], |row| {
+                row.get(0)
+            })
+            .optional()?;
+        Ok(value)
+    }
+
+    pub fn set_metadata(&mut self, key: &str, value: &str) -> Result<(), Box<dyn Error>> {
+        self.connection.execute(
+            "INSERT INTO metadata (key, value) VALUES (?1, ?2)
+             ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+            params![key, value],
+        )?;
+        Ok(())
+    }
+
+    fn list_id(&self, name: &str) -> Result<Option<i64>, Box<dyn Error>> {
+        let id = self
+            .connection
+            .query_row("SELECT id FROM lists WHERE name = ?1", [name], |row| {
+                row.get(0)
+            })
+            .optional()?;
+        Ok(id)
+    }
+
+    fn existing_list_id(&self, name: &str) -> Result<i64, Box<dyn Error>> {
+        match self.list_id(name)? {
+            Some(id) => Ok(id),
+            None => Err(format!("List {name} does not exist").into()),
+        }
+    }
+}
+
+impl ListStore for SqliteStore {
+    fn load(&self, name: &str) -> Result<BookRecords, Box<dyn Error>> {
+        let id = self.existing_list_id(name)?;
+
+        let columns: String =
+            self.connection
+                .query_row("SELECT columns FROM lists WHERE id = ?1", [id], |row| {
+                    row.get(0)
+                })?;
+        let columns: Vec<String> = serde_json::from_str(&columns)?;
+
+        let mut statement = self.connection.prepare(
+            "SELECT id, author, title, weight FROM books WHERE list_id = ?1 ORDER BY position",
+        )?;
+        let rows = statement.query_map([id], |row| {
+            let book = Book::new(row.get(1)?, row.get(2)?, row.get(3)?);
+            Ok((row.get::<_, i64>(0)?, book))
+        })?;
+
+        let mut fields = self
+            .connection
+            .prepare("SELECT name, value FROM book_fields WHERE book_id = ?1")?;
+
+        let mut books = BookRecords::default();
+        for row in rows {
+            let (book_id, mut book) = row?;
+            let custom = fields.query_map([book_id], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            for field in custom {
+                let (name, value): (String, String) = field?;
+                book.custom.insert(name, value);
+            }
+            books.push(book);
+        }
+        columns.iter().for_each(|c| books.add_custom_column(c));
+
+        Ok(books)
+    }
+
+    fn save(&mut self, name: &str, books: &BookRecords) -> Result<(), Box<dyn Error>> {
+        let columns = serde_json::to_string(books.custom_columns())?;
+        let transaction = self.connection.transaction()?;
+
+        transaction.execute(
+            "INSERT INTO lists (name, columns) VALUES (?1, ?2)
+             ON CONFLICT(name) DO UPDATE SET columns = excluded.columns",
+            params![name, columns],
+        )?;
+        let list_id: i64 =
+            transaction.query_row("SELECT id FROM lists WHERE name = ?1", [name], |row| {
+                row.get(0)
+            })?;
+        transaction.execute("DELETE FROM books WHERE list_id = ?1", [list_id])?;
+
+        {
+            let mut insert_book = transaction.prepare(
+                "INSERT INTO books (list_id, position, author, title, weight) VALUES (?1, ?2, ?3, ?4, ?5)",
+            )?;
+            let mut insert_field = transaction
+                .prepare("INSERT INTO book_fields (book_id, name, value) VALUES (?1, ?2, ?3)")?;
+
+            for (position, book) in books.records().iter().enumerate() {
+                let book_id = insert_book.insert(params![
+                    list_id,
+                    position as i64,
+                    book.author,
+                    book.title,
+                    book.weight
+                ])?;
+                for (field, value) in &book.custom {
+                    insert_field.execute(params![book_id, field, value])?;
+                }
+            }
+        }
+
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn list(&self) -> Result<Vec<String>, Box<dyn Error>> {
+        let mut statement = self
+            .connection
+            .prepare("SELECT name FROM lists ORDER BY name")?;
+        let names = statement
+            .query_map([], |row| row.get(0))?
+            .collect::<Result<Vec<String>, _>>()?;
+        Ok(names)
+    }
+
+    fn delete(&mut self, name: &str) -> Result<(), Box<dyn Error>> {
+        let id = self.existing_list_id(name)?;
+        self.connection
+            .execute("DELETE FROM lists WHERE id = ?1", [id])?;
+        Ok(())
+    }
+
+    fn rename(&mut self, from: &str, to: &str) -> Result<(), Box<dyn Error>> {
+        let id = self.existing_list_id(from)?;
+        if self.exists(to)? {
+            return Err(format!("List {to} already exists").into());
+        }
+        self.connection
+            .execute("UPDATE lists SET name = ?1 WHERE id = ?2", params![to, id])?;
+        Ok(())
+    }
+
+    fn exists(&self, name: &str) -> Result<bool, Box<dyn Error>> {
+        Ok(self.list_id(name)?.is_some())
+    }
+
+    fn load_journal(&self, name: &str) -> Result<Journal, Box<dyn Error>> {
+        let id = match self.list_id(name)? {
+            Some(id) => id,
+            None => return Ok(Journal::default()),
+        };
+
+        let journal: Option<String> = self
+            .connection
+            .query_row(
+                "SELECT journal FROM history WHERE list_id = ?1",
+                [id],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        match journal {
+            Some(journal) => Ok(serde_json::from_str(&journal)?),
+            None => Ok(Journal::default()),
+        }
+    }
+
+    fn save_journal(&mut self, name: &str, journal: &Journal) -> Result<(), Box<dyn Error>> {
+        let id = self.existing_list_id(name)?;
+        self.connection.execute(
+            "INSERT INTO history (list_id, journal) VALUES (?1, ?2)
+             ON CONFLICT(list_id) DO UPDATE SET journal = excluded.journal",
+            params![id, serde_json::to_string(journal)?],
+        )?;
+        Ok(())
+    }
+}
+
+//
+//
+//
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::store::tests::test_store;
+    use tempdir::TempDir;
+
+    #[test]
+    fn test_sqlite_store() {
+        test_store(&mut SqliteStore::open_in_memory().unwrap());
+    }
+
+    #[test]
+    fn test_sqlite_file() {
+        let temp_dir = TempDir::new("wych_book_tests").unwrap();
+        let filename = temp_dir.path().join("wych_book.db");
+        let books: BookRecords =
+            vec![Book::new("Franz Kafka".into(), "The Castle".into(), 2)].into();
+
+        let mut store = SqliteStore::open(&filename).unwrap();
+        store.save("books", &books).unwrap();
+        store.set_metadata("owner", "k").unwrap();
+        drop(store);
+
+        let store = SqliteStore::open(&filename).unwrap();
+        assert_eq!(store.load("books").unwrap(), books);
+        assert_eq!(store.get_metadata("owner").unwrap(), Some("k".into()));
+        assert_eq!(
+            store.get_metadata("schema_version").unwrap(),
+            Some("1".into())
+        );
+    }
+}
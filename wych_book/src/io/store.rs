@@ -0,0 +1,363 @@
+use super::{
+    csv::{self, RowWarning},
+    encryption::Keyring,
+    files::write_atomic,
+};
+use crate::books::{book_records::BookRecords, journal::Journal};
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    error::Error,
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+/// Storage for named book lists.
+pub trait ListStore {
+    /// Read a list. Fails if the list doesn't exist or cannot be read.
+    fn load(&self, name: &str) -> Result<BookRecords, Box<dyn Error>>;
+
+    /// Read a list, skipping any books that cannot be read.
+    /// Returns the books that were read, along with a warning for each skipped book.
+    fn load_lenient(&self, name: &str) -> Result<(BookRecords, Vec<RowWarning>), Box<dyn Error>> {
+        Ok((self.load(name)?, Vec::new()))
+    }
+
+    /// Write a list, creating it if it doesn't exist.
+    /// Fails if rows were skipped when the list was loaded leniently, as they would be lost.
+    fn save(&mut self, name: &str, books: &BookRecords) -> Result<(), Box<dyn Error>>;
+
+    /// Names of all stored lists, in alphabetical order.
+    fn list(&self) -> Result<Vec<String>, Box<dyn Error>>;
+
+    /// Delete a list, along with its journal.
+    fn delete(&mut self, name: &str) -> Result<(), Box<dyn Error>>;
+
+    /// Rename a list, along with its journal. Fails if `from` doesn't exist, or `to` already exists.
+    fn rename(&mut self, from: &str, to: &str) -> Result<(), Box<dyn Error>>;
+
+    fn exists(&self, name: &str) -> Result<bool, Box<dyn Error>>;
+
+    /// Read the journal of changes to a list. A list without a journal has an empty one.
+    fn load_journal(&self, name: &str) -> Result<Journal, Box<dyn Error>>;
+
+    fn save_journal(&mut self, name: &str, journal: &Journal) -> Result<(), Box<dyn Error>>;
+
+    /// Keys of the lists that are encrypted at rest, for stores that support encryption.
+    fn keyring(&self) -> Option<&Keyring> {
+        None
+    }
+
+    fn keyring_mut(&mut self) -> Option<&mut Keyring> {
+        None
+    }
+}
+
+/// Lists stored as `<name>.csv` files in a directory, with their journals kept as `<name>.json` files in another.
+/// The files of lists in the keyring are encrypted.
+#[derive(Debug, Clone)]
+pub struct CsvStore {
+    dir: PathBuf,
+    journal_dir: PathBuf,
+    keyring: Keyring,
+    /// Rows skipped when each list was last loaded leniently.
+    skipped: RefCell<BTreeMap<String, Vec<RowWarning>>>,
+}
+
+impl CsvStore {
+    pub fn new(dir: &Path, journal_dir: &Path) -> Self {
+        CsvStore {
+            dir: dir.to_path_buf(),
+            journal_dir: journal_dir.to_path_buf(),
+            keyring: Keyring::default(),
+            skipped: RefCell::default(),
+        }
+    }
+
+    pub fn with_keyring(mut self, keyring: Keyring) -> Self {
+        self.keyring = keyring;
+        self
+    }
+
+    pub fn path(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{name}.csv"))
+    }
+
+    fn journal_path(&self, name: &str) -> PathBuf {
+        self.journal_dir.join(format!("{name}.json"))
+    }
+
+    /// Read one of a list's files, decrypting it if needed.
+    fn read(&self, name: &str, filename: &Path) -> Result<Vec<u8>, Box<dyn Error>> {
+        let contents =
+            fs::read(filename).map_err(|e| format!("Cannot open {}, {e}", filename.display()))?;
+        self.keyring.unseal(name, contents)
+    }
+
+    /// Write one of a list's files, encrypting it if the list is encrypted.
+    fn write(&self, name: &str, filename: &Path, contents: Vec<u8>) -> Result<(), Box<dyn Error>> {
+        if let Some(dir) = filename.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let contents = self.keyring.seal(name, contents)?;
+        write_atomic(filename, |file| Ok(file.write_all(&contents)?))
+    }
+}
+
+impl ListStore for CsvStore {
+    fn load(&self, name: &str) -> Result<BookRecords, Box<dyn Error>> {
+        let filename = self.path(name);
+        csv::read_csv_bytes(&self.read(name, &filename)?, &filename)
+    }
+
+    fn load_lenient(&self, name: &str) -> Result<(BookRecords, Vec<RowWarning>), Box<dyn Error>> {
+        let filename = self.path(name);
+        let (books, warnings) =
+            csv::read_csv_bytes_lenient(&self.read(name, &filename)?, &filename)?;
+        match warnings.is_empty() {
+            true => self.skipped.borrow_mut().remove(name),
+            false => self
+                .skipped
+                .borrow_mut()
+                .insert(name.to_string(), warnings.clone()),
+        };
+        Ok((books, warnings))
+    }
+
+    fn save(&mut self, name: &str, books: &BookRecords) -> Result<(), Box<dyn Error>> {
+        if let Some(warnings) = self.skipped.get_mut().get(name) {
+            return Err(format!(
+                "Cannot save {name}, rows that could not be read would be lost. Fix {} first",
+                csv::skipped_lines(warnings)
+            )
+            .into());
+        }
+        self.write(name, &self.path(name), csv::write_csv_bytes(books)?)
+    }
+
+    fn list(&self) -> Result<Vec<String>, Box<dyn Error>> {
+        if !self.dir.try_exists()? {
+            return Ok(Vec::new());
+        }
+
+        let mut names = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|e| e == "csv") {
+                if let Some(stem) = path.file_stem() {
+                    names.push(stem.to_string_lossy().to_string());
+                }
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    fn delete(&mut self, name: &str) -> Result<(), Box<dyn Error>> {
+        fs::remove_file(self.path(name)).map_err(|e| format!("Cannot delete list {name}, {e}"))?;
+
+        let journal = self.journal_path(name);
+        if journal.try_exists()? {
+            fs::remove_file(journal)?;
+        }
+        self.skipped.get_mut().remove(name);
+        Ok(())
+    }
+
+    fn rename(&mut self, from: &str, to: &str) -> Result<(), Box<dyn Error>> {
+        if !self.exists(from)? {
+            return Err(format!("List {from} does not exist").into());
+        }
+        if self.exists(to)? {
+            return Err(format!("List {to} already exists").into());
+        }
+        fs::rename(self.path(from), self.path(to))?;
+
+        let journal = self.journal_path(from);
+        if journal.try_exists()? {
+            fs::rename(journal, self.journal_path(to))?;
+        }
+        self.keyring.rename(from, to);
+        if let Some(warnings) = self.skipped.get_mut().remove(from) {
+            self.skipped.get_mut().insert(to.to_string(), warnings);
+        }
+        Ok(())
+    }
+
+    fn exists(&self, name: &str) -> Result<bool, Box<dyn Error>> {
+        let filename = self.path(name);
+        match filename.try_exists() {
+            Ok(exists) => Ok(exists),
+            Err(e) => Err(format!("Cannot check existence of {}, {e}", filename.display()).into()),
+        }
+    }
+
+    fn load_journal(&self, name: &str) -> Result<Journal, Box<dyn Error>> {
+        let filename = self.journal_path(name);
+        if !filename.try_exists()? {
+            return Ok(Journal::default());
+        }
+        Ok(serde_json::from_slice(&self.read(name, &filename)?)?)
+    }
+
+    fn save_journal(&mut self, name: &str, journal: &Journal) -> Result<(), Box<dyn Error>> {
+        let filename = self.journal_path(name);
+        self.write(name, &filename, serde_json::to_vec(journal)?)
+    }
+
+    fn keyring(&self) -> Option<&Keyring> {
+        Some(&self.keyring)
+    }
+
+    fn keyring_mut(&mut self) -> Option<&mut Keyring> {
+        Some(&mut self.keyring)
+    }
+}
+
+/// Lists kept in memory, which are lost when the store is dropped.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryStore {
+    lists: BTreeMap<String, BookRecords>,
+    journals: BTreeMap<String, Journal>,
+}
+
+impl ListStore for MemoryStore {
+    fn load(&self, name: &str) -> Result<BookRecords, Box<dyn Error>> {
+        match self.lists.get(name) {
+            Some(books) => Ok(books.clone()),
+            None => Err(format!("List {name} does not exist").into()),
+        }
+    }
+
+    fn save(&mut self, name: &str, books: &BookRecords) -> Result<(), Box<dyn Error>> {
+        self.lists.insert(name.to_string(), books.clone());
+        Ok(())
+    }
+
+    fn list(&self) -> Result<Vec<String>, Box<dyn Error>> {
+        Ok(self.lists.keys().cloned().collect())
+    }
+
+    fn delete(&mut self, name: &str) -> Result<(), Box<dyn Error>> {
+        self.journals.remove(name);
+        match self.lists.remove(name) {
+            Some(_) => Ok(()),
+            None => Err(format!("List {name} does not exist").into()),
+        }
+    }
+
+    fn rename(&mut self, from: &str, to: &str) -> Result<(), Box<dyn Error>> {
+        if self.lists.contains_key(to) {
+            return Err(format!("List {to} already exists").into());
+        }
+        let books = match self.lists.remove(from) {
+            Some(books) => books,
+            None => return Err(format!("List {from} does not exist").into()),
+        };
+        self.lists.insert(to.to_string(), books);
+
+        if let Some(journal) = self.journals.remove(from) {
+            self.journals.insert(to.to_string(), journal);
+        }
+        Ok(())
+    }
+
+    fn exists(&self, name: &str) -> Result<bool, Box<dyn Error>> {
+        Ok(self.lists.contains_key(name))
+    }
+
+    fn load_journal(&self, name: &str) -> Result<Journal, Box<dyn Error>> {
+        Ok(self.journals.get(name).cloned().unwrap_or_default())
+    }
+
+    fn save_journal(&mut self, name: &str, journal: &Journal) -> Result<(), Box<dyn Error>> {
+        self.journals.insert(name.to_string(), journal.clone());
+        Ok(())
+    }
+}
+
+//
+//
+//
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::books::book::Book;
+    use tempdir::TempDir;
+
+    /// Checks the behaviour every store should share.
+    pub fn test_store(store: &mut dyn ListStore) {
+        let mut books: BookRecords =
+            vec![Book::new("Franz Kafka".into(), "The Castle".into(), 2)].into();
+        books
+            .get_mut(0)
+            .unwrap()
+            .custom
+            .insert("notes".into(), "Unfinished".into());
+        books.add_custom_column("notes");
+        books.add_custom_column("recommended_by");
+        let mut journal = Journal::default();
+        journal.record(books.add_book("Yoko Ogawa", "The Memory Police"));
+
+        assert!(!store.exists("books").unwrap());
+        assert!(store.load("books").is_err());
+
+        store.save("books", &books).unwrap();
+        store.save_journal("books", &journal).unwrap();
+        store.save("other", &BookRecords::default()).unwrap();
+        assert_eq!(store.load_journal("other").unwrap(), Journal::default());
+        assert!(store.exists("books").unwrap());
+        assert_eq!(store.load("books").unwrap(), books);
+        assert_eq!(store.list().unwrap(), vec!["books", "other"]);
+
+        assert!(store.rename("books", "other").is_err());
+        assert!(store.rename("missing", "new").is_err());
+        store.rename("books", "renamed").unwrap();
+        assert!(!store.exists("books").unwrap());
+        assert_eq!(store.load("renamed").unwrap(), books);
+        assert_eq!(store.load_journal("renamed").unwrap(), journal);
+
+        store.delete("renamed").unwrap();
+        assert!(store.delete("renamed").is_err());
+        assert_eq!(store.list().unwrap(), vec!["other"]);
+        assert_eq!(store.load_journal("renamed").unwrap(), Journal::default());
+    }
+
+    #[test]
+    fn test_csv_store() {
+        let temp_dir = TempDir::new("wych_book_tests").unwrap();
+        let dir = temp_dir.path();
+        let mut store = CsvStore::new(&dir.join("lists"), &dir.join("journal"));
+        assert!(store.list().unwrap().is_empty());
+        test_store(&mut store);
+    }
+
+    #[test]
+    fn test_lenient_load_keeps_skipped_rows() {
+        let temp_dir = TempDir::new("wych_book_tests").unwrap();
+        let dir = temp_dir.path();
+        let mut store = CsvStore::new(&dir.join("lists"), &dir.join("journal"));
+        let contents =
+            "author,title,weight\nFranz Kafka,The Castle,1\nYoko Ogawa,The Memory Police,heavy\n";
+        fs::create_dir_all(dir.join("lists")).unwrap();
+        fs::write(store.path("books"), contents).unwrap();
+
+        let (mut books, warnings) = store.load_lenient("books").unwrap();
+        assert_eq!(warnings.len(), 1);
+        books.add_book("Ursula K. Le Guin", "The Dispossessed");
+        let error = store.save("books", &books).unwrap_err().to_string();
+        assert!(error.contains("line 3"));
+        assert_eq!(fs::read_to_string(store.path("books")).unwrap(), contents);
+
+        // once the row is fixed, the list can be saved again
+        fs::write(store.path("books"), contents.replace("heavy", "2")).unwrap();
+        let (books, _) = store.load_lenient("books").unwrap();
+        assert!(store.save("books", &books).is_ok());
+    }
+
+    #[test]
+    fn test_memory_store() {
+        test_store(&mut MemoryStore::default());
+    }
+}
@@ -0,0 +1,99 @@
+use super::import::{read_rows, set_fields, Importer};
+use crate::books::{
+    book::{field, Book},
+    book_records::BookRecords,
+};
+use serde::Deserialize;
+use std::{error::Error, path::Path};
+
+/// Imports a StoryGraph library export (Manage Account > Export StoryGraph Library).
+pub struct StoryGraph;
+
+/// The columns used from a row of a StoryGraph library export.
+#[derive(Debug, Deserialize)]
+struct StoryGraphRow {
+    #[serde(rename = "Title")]
+    title: String,
+    #[serde(rename = "Authors")]
+    authors: String,
+    #[serde(rename = "ISBN/UID", default)]
+    isbn: String,
+    #[serde(rename = "Read Status", default)]
+    read_status: String,
+    #[serde(rename = "Star Rating", default)]
+    rating: String,
+    #[serde(rename = "Tags", default)]
+    tags: String,
+}
+
+impl From<StoryGraphRow> for Book {
+    fn from(row: StoryGraphRow) -> Self {
+        // the first of the comma-separated authors is the main author
+        let (author, additional_authors) = match row.authors.split_once(',') {
+            Some((author, others)) => (author.trim().to_string(), Some(others.to_string())),
+            None => (row.authors.trim().to_string(), None),
+        };
+        let mut book = Book::new(author, row.title, 1);
+
+        set_fields(
+            &mut book,
+            [
+                (field::ADDITIONAL_AUTHORS, additional_authors),
+                (field::ISBN, Some(row.isbn)),
+                (field::SHELF, Some(row.read_status)),
+                (field::RATING, Some(row.rating)),
+                (field::TAGS, Some(row.tags)),
+            ],
+        );
+        book
+    }
+}
+
+impl Importer for StoryGraph {
+    fn import(&self, contents: &[u8], source: &Path) -> Result<BookRecords, Box<dyn Error>> {
+        let rows: Vec<StoryGraphRow> = read_rows(csv::Reader::from_reader(contents), source)?;
+        Ok(BookRecords::from(
+            rows.into_iter().map(Book::from).collect::<Vec<_>>(),
+        ))
+    }
+
+    fn default_shelf(&self) -> Option<&str> {
+        Some("to-read")
+    }
+}
+
+//
+//
+//
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXPORT: &str = "Title,Authors,Contributors,ISBN/UID,Format,Read Status,Date Added,Star Rating,Review,Tags,Owned?
+The Castle,Franz Kafka,\"Willa Muir (Translator), Edwin Muir (Translator)\",9780805211061,paperback,to-read,2024/01/03,,,\"classics, german\",Yes
+Good Omens,\"Terry Pratchett, Neil Gaiman\",,9780060853983,paperback,read,2023/05/12,4.5,Very funny,,No
+";
+
+    #[test]
+    fn test_storygraph_import() {
+        let books = StoryGraph
+            .import(EXPORT.as_bytes(), Path::new("storygraph.csv"))
+            .unwrap();
+
+        let castle = books.get(0).unwrap();
+        assert_eq!(castle.author, "Franz Kafka");
+        assert_eq!(castle.field(field::ADDITIONAL_AUTHORS), None);
+        assert_eq!(castle.field(field::ISBN), Some("9780805211061".into()));
+        assert_eq!(castle.field(field::SHELF), Some("to-read".into()));
+        assert_eq!(castle.field(field::TAGS), Some("classics, german".into()));
+        assert_eq!(castle.field(field::RATING), None);
+
+        let good_omens = books.get(1).unwrap();
+        assert_eq!(good_omens.author, "Terry Pratchett");
+        assert_eq!(
+            good_omens.field(field::ADDITIONAL_AUTHORS),
+            Some("Neil Gaiman".into())
+        );
+        assert_eq!(good_omens.field(field::RATING), Some("4.5".into()));
+    }
+}
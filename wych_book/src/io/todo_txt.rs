@@ -0,0 +1,263 @@
+use crate::books::{
+    book::{field, status, Book},
+    book_records::BookRecords,
+};
+use std::error::Error;
+
+/// Field holding a book's todo.txt priority, such as `A`.
+const PRIORITY: &str = "priority";
+
+/// A list as todo.txt lines, such as `(A) The Castle by Franz Kafka +classics weight:3`.
+/// Tags become `+projects`, and fields without spaces in their values become `key:value` pairs.
+/// Finished books are marked complete with `x`. Words of the title or author that would be read as
+/// anything else, such as `10:04`, are escaped with a backslash.
+pub fn todo_txt(books: &BookRecords) -> String {
+    books
+        .records()
+        .iter()
+        .map(|book| {
+            let mut words = Vec::new();
+            let status = book.field(field::STATUS);
+            if status.as_deref() == Some(status::FINISHED) {
+                words.push("x".to_string());
+            }
+            if let Some(priority) = book.custom.get(PRIORITY) {
+                words.push(format!("({priority})"));
+            }
+            words.push(format!(
+                "{} by {}",
+                escape(&book.title, true),
+                escape(&book.author, false)
+            ));
+
+            let tags = book.custom.get(field::TAGS).map(String::as_str);
+            for tag in tags.unwrap_or_default().split(',').map(str::trim) {
+                if !tag.is_empty() {
+                    words.push(format!("+{}", tag.replace(char::is_whitespace, "-")));
+                }
+            }
+
+            words.push(format!("weight:{}", book.weight));
+            for (name, value) in &book.custom {
+                let skipped = [PRIORITY, field::TAGS].contains(&name.as_str())
+                    || (name == field::STATUS && value == status::FINISHED);
+                if !skipped && is_pair(name, value) {
+                    words.push(format!("{name}:{value}"));
+                }
+            }
+            words.join(" ") + "\n"
+        })
+        .collect()
+}
+
+/// Read the books from todo.txt lines written by `todo_txt`, or by hand.
+/// Each line must read `Title by Author`, with any priority, `+projects` and `key:value` pairs.
+/// Completing a line sets the book's status to finished, and un-completing it clears it.
+/// `@contexts` are kept in a contexts field. A word starting with a backslash is always part of the text.
+pub fn read_todo_txt(contents: &str) -> Result<BookRecords, Box<dyn Error>> {
+    let mut books = Vec::new();
+
+    for (i, line) in contents.lines().enumerate() {
+        let mut words: Vec<&str> = line.split_whitespace().collect();
+        if words.is_empty() {
+            continue;
+        }
+
+        let done = words[0] == "x";
+        if done {
+            words.remove(0);
+        }
+        let priority = words.first().and_then(|w| {
+            let letter = w.strip_prefix('(')?.strip_suffix(')')?;
+            (letter.len() == 1 && letter.chars().all(|c| c.is_ascii_uppercase())).then_some(letter)
+        });
+        if priority.is_some() {
+            words.remove(0);
+        }
+        // completion and creation dates
+        while words.first().is_some_and(|w| is_date(w)) {
+            words.remove(0);
+        }
+
+        let mut text = Vec::new();
+        let mut tags = Vec::new();
+        let mut contexts = Vec::new();
+        let mut pairs = Vec::new();
+        for word in words {
+            if let Some(tag) = word.strip_prefix('+').filter(|t| !t.is_empty()) {
+                tags.push(tag);
+            } else if let Some(context) = word.strip_prefix('@').filter(|c| !c.is_empty()) {
+                contexts.push(context);
+            } else if let Some((name, value)) = word.split_once(':').filter(|(n, v)| is_pair(n, v))
+            {
+                pairs.push((name, value));
+            } else {
+                text.push(word.strip_prefix('\\').unwrap_or(word));
+            }
+        }
+
+        let text = text.join(" ");
+        let (title, author) = text
+            .rsplit_once(" by ")
+            .ok_or_else(|| format!("Line {}: expected Title by Author, found {line}", i + 1))?;
+        let mut book = Book::new(author.trim().into(), title.trim().into(), 1);
+
+        for (name, value) in pairs {
+            match name {
+                "weight" => {
+                    book.weight = value
+                        .parse()
+                        .map_err(|e| format!("Line {}: invalid weight {value}, {e}", i + 1))?
+                }
+                field::STATUS if value == status::FINISHED => (),
+                _ => {
+                    book.custom.insert(name.into(), value.into());
+                }
+            }
+        }
+        let fields = [
+            (PRIORITY, priority.map(String::from)),
+            (field::TAGS, Some(tags.join(", "))),
+            ("contexts", Some(contexts.join(", "))),
+            (field::STATUS, done.then(|| status::FINISHED.to_string())),
+        ];
+        for (name, value) in fields {
+            if let Some(value) = value.filter(|v| !v.is_empty()) {
+                book.custom.insert(name.into(), value);
+            }
+        }
+        books.push(book);
+    }
+
+    Ok(books.into())
+}
+
+/// Whether a word is a date such as `2024-03-20`.
+fn is_date(word: &str) -> bool {
+    word.len() == 10
+        && word.char_indices().all(|(i, c)| match i {
+            4 | 7 => c == '-',
+            _ => c.is_ascii_digit(),
+        })
+}
+
+/// Whether a field can be written as a todo.txt `key:value` pair, and read back.
+fn is_pair(name: &str, value: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+        && !value.is_empty()
+        && !value.contains(char::is_whitespace)
+        && !value.starts_with("//")
+}
+
+/// Escape each word of text that `read_todo_txt` would take for something other than text,
+/// including the completion mark, priority or date that can only come at the start of a line.
+fn escape(text: &str, at_start: bool) -> String {
+    let is_special = |i: usize, word: &str| {
+        let at_start = at_start && i == 0;
+        word.starts_with('\\')
+            || (word.len() > 1 && (word.starts_with('+') || word.starts_with('@')))
+            || word.split_once(':').is_some_and(|(n, v)| is_pair(n, v))
+            || (at_start && (word == "x" || is_date(word)))
+            || (at_start && word.starts_with('(') && word.ends_with(')'))
+    };
+    text.split_whitespace()
+        .enumerate()
+        .map(|(i, word)| match is_special(i, word) {
+            true => format!("\\{word}"),
+            false => word.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+//
+//
+//
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn books_to_test() -> BookRecords {
+        let mut castle = Book::new("Franz Kafka".into(), "The Castle".into(), 3);
+        castle.custom.insert(PRIORITY.into(), "A".into());
+        castle
+            .custom
+            .insert(field::TAGS.into(), "classics, book-club".into());
+        castle
+            .custom
+            .insert(field::STATUS.into(), status::PICKED.into());
+        castle
+            .custom
+            .insert(field::ISBN.into(), "9780805211061".into());
+        let mut police = Book::new("Yōko Ogawa".into(), "The Memory Police".into(), 1);
+        police
+            .custom
+            .insert(field::STATUS.into(), status::FINISHED.into());
+
+        vec![castle, police].into()
+    }
+
+    #[test]
+    fn test_todo_txt() {
+        let todo = todo_txt(&books_to_test());
+        assert_eq!(
+            todo,
+            "(A) The Castle by Franz Kafka +classics +book-club weight:3 isbn:9780805211061 status:picked
+x The Memory Police by Yōko Ogawa weight:1
+"
+        );
+        assert_eq!(
+            read_todo_txt(&todo).unwrap().records(),
+            books_to_test().records()
+        );
+    }
+
+    #[test]
+    fn test_escaped_words() {
+        let books: BookRecords = vec![
+            Book::new("Ben Lerner".into(), "10:04".into(), 1),
+            Book::new("@author".into(), "x Marks the +1 Spot".into(), 1),
+            Book::new("A. Writer".into(), "(A) \\Path 2024-01-02".into(), 1),
+        ]
+        .into();
+
+        let todo = todo_txt(&books);
+        assert!(todo.starts_with("\\10:04 by Ben Lerner weight:1\n"));
+        assert_eq!(read_todo_txt(&todo).unwrap(), books);
+    }
+
+    #[test]
+    fn test_read_todo_txt() {
+        let todo = "
+(B) Stand by Me by Stephen King @library weight:5 due:2024-05-01
+x 2024-03-20 Dracula by Bram Stoker +horror status:picked
+2024-01-02 The Trial by Franz Kafka +to-read
+";
+        let books = read_todo_txt(todo).unwrap();
+        let king = books.get(0).unwrap();
+        assert_eq!(
+            (king.title.as_str(), king.author.as_str()),
+            ("Stand by Me", "Stephen King")
+        );
+        assert_eq!(king.weight, 5);
+        assert_eq!(king.field("contexts"), Some("library".into()));
+        assert_eq!(king.field("due"), Some("2024-05-01".into()));
+        assert_eq!(king.field(PRIORITY), Some("B".into()));
+
+        let dracula = books.get(1).unwrap();
+        assert_eq!(dracula.title, "Dracula");
+        assert_eq!(dracula.field(field::STATUS), Some("finished".into()));
+        assert_eq!(dracula.field(field::TAGS), Some("horror".into()));
+
+        let trial = books.get(2).unwrap();
+        assert_eq!(trial.title, "The Trial");
+        assert_eq!(trial.author, "Franz Kafka");
+        assert_eq!(trial.field(field::STATUS), None);
+
+        assert!(read_todo_txt("The Castle, Franz Kafka\n").is_err());
+        assert!(read_todo_txt("The Castle by Franz Kafka weight:300\n").is_err());
+    }
+}
@@ -1,11 +1,43 @@
 pub mod books {
     pub mod book;
     pub mod book_records;
+    pub mod citation;
+    pub mod history;
+    pub mod isbn;
+    pub mod journal;
+    pub mod render;
 }
 
 pub mod io {
+    pub mod archive;
+    pub mod backup;
+    #[cfg(feature = "sqlite")]
+    pub mod calibre;
     pub mod config;
     pub mod csv;
+    pub mod encryption;
+    pub mod export;
+    pub mod files;
+    pub mod goodreads;
+    pub mod history;
+    pub mod import;
+    pub mod journal;
+    pub mod kindle;
+    pub mod librarything;
+    pub mod mapping;
+    pub mod metadata;
+    pub mod openlibrary;
+    pub mod org;
+    pub mod paths;
+    pub mod plain_text;
+    pub mod publish;
+    pub mod scan;
+    pub mod share;
+    #[cfg(feature = "sqlite")]
+    pub mod sqlite;
+    pub mod store;
+    pub mod storygraph;
+    pub mod todo_txt;
 }
 
 pub mod search;
@@ -62,7 +62,7 @@ mod tests {
         let tester = Tester::values();
 
         let result = tester.get_from_input("1");
-        let expected = (1 as usize, &"world".to_string());
+        let expected = (1_usize, &"world".to_string());
         assert_eq!(result.unwrap(), expected);
 
         let result = tester.get_from_input("100");
@@ -74,7 +74,7 @@ mod tests {
         let tester = Tester::values();
 
         let result = tester.get_from_input("saluton");
-        let expected = (2 as usize, &"saluton".to_string());
+        let expected = (2_usize, &"saluton".to_string());
         assert_eq!(result.unwrap(), expected);
 
         let result = tester.get_from_input("hola");